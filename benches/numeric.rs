@@ -0,0 +1,92 @@
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sas_reader_2000::sas::numeric::{gather_numeric, gather_numeric_scalar, missing_value_to_bits};
+use sas_reader_2000::sas::{
+    Column, Endianness, MissingValue, NumericColumn, Sas7bdatReader, Sas7bdatWriter, Schema, Value,
+    WriterOptions,
+};
+use sas_reader_2000::traits::DatasetReader;
+
+const ROWS: usize = 4096;
+const COLUMNS: usize = 8;
+const ROW_LENGTH: usize = COLUMNS * 8;
+
+/// A page's worth of rows of eight numbers, one in ten of them missing.
+fn rows(endianness: Endianness) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(ROWS * ROW_LENGTH);
+    for i in 0..ROWS * COLUMNS {
+        let bits = if i % 10 == 0 {
+            missing_value_to_bits(MissingValue::System)
+        } else {
+            (i as f64 * 0.5).to_bits()
+        };
+        match endianness {
+            Endianness::Little => bytes.extend(bits.to_le_bytes()),
+            Endianness::Big => bytes.extend(bits.to_be_bytes()),
+        }
+    }
+    bytes
+}
+
+fn gather(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gather_numeric");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    for endianness in [Endianness::Little, Endianness::Big] {
+        let bytes = rows(endianness);
+        let name = format!("{:?}", endianness);
+        group.bench_with_input(BenchmarkId::new("vectorized", &name), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut column = NumericColumn::with_capacity(ROWS);
+                gather_numeric(bytes, ROW_LENGTH, 24, endianness, &mut column).unwrap();
+                column
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", &name), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut column = NumericColumn::with_capacity(ROWS);
+                gather_numeric_scalar(bytes, ROW_LENGTH, 24, 8, endianness, &mut column).unwrap();
+                column
+            })
+        });
+    }
+    group.finish();
+}
+
+fn read_column(c: &mut Criterion) {
+    let schema = Schema::new(
+        "NUMBERS",
+        (0..COLUMNS)
+            .map(|i| Column::numeric(&format!("x{}", i)))
+            .collect(),
+    );
+    let mut writer =
+        Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, WriterOptions::default()).unwrap();
+    for i in 0..ROWS * 16 {
+        let row: Vec<Value> = (0..COLUMNS)
+            .map(|j| Value::Number((i * j) as f64))
+            .collect();
+        writer.write_row(&row).unwrap();
+    }
+    let bytes = writer.finish().unwrap().into_inner();
+
+    let mut group = c.benchmark_group("read_numeric_column");
+    group.throughput(Throughput::Elements((ROWS * 16) as u64));
+    group.bench_function("gathered", |b| {
+        b.iter(|| {
+            let mut reader = Sas7bdatReader::new(Cursor::new(&bytes)).unwrap();
+            reader.read_numeric_column("x3").unwrap()
+        })
+    });
+    group.bench_function("rows", |b| {
+        b.iter(|| {
+            let mut reader = Sas7bdatReader::new(Cursor::new(&bytes)).unwrap();
+            reader.select(&["x3"]).unwrap();
+            reader.rows().count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, gather, read_column);
+criterion_main!(benches);
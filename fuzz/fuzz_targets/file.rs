@@ -0,0 +1,29 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use sas_reader_2000::sas::validate::validate;
+use sas_reader_2000::sas::Sas7bdatReader;
+use sas_reader_2000::traits::DatasetReader;
+
+// A whole file through the reader and the validator.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut reader) = Sas7bdatReader::new(Cursor::new(data)) {
+        let names: Vec<String> = reader
+            .schema()
+            .columns
+            .iter()
+            .map(|column| column.name.clone())
+            .collect();
+        for row in reader.rows() {
+            if row.is_err() {
+                break;
+            }
+        }
+        for name in names {
+            let _ = reader.read_numeric_column(&name);
+        }
+    }
+    let _ = validate(&mut Cursor::new(data));
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sas_reader_2000::sas::header::SasHeaderUnknowns;
+use sas_reader_2000::sas::{SasHeaderBinary, SasProperties, UnknownsSurvey};
+
+fuzz_target!(|data: &[u8]| {
+    let header = SasHeaderBinary::new(data);
+    let _ = header.validate_sas_file();
+    let _ = header.get_ascii_file_type_from_header();
+    let _ = header.get_creation_timestamp_from_header();
+    let _ = header.get_modification_timestamp_from_header();
+    let _ = header.get_header_length_from_header();
+    let _ = header.get_page_size_from_header();
+    let _ = header.get_page_count_from_header();
+    let _ = header.get_sas_release_from_header();
+    let _ = header.get_host_sas_server_type_from_header();
+    let _ = header.get_os_version_number_from_header();
+    let _ = header.get_os_maker_or_version_from_header();
+    let _ = SasProperties::from_header(&header);
+
+    let mut unknowns = SasHeaderUnknowns::new(data);
+    unknowns.read();
+    let mut survey = UnknownsSurvey::new();
+    if survey.add(&header).is_ok() {
+        survey.summarize();
+    }
+});
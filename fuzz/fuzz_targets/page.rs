@@ -0,0 +1,33 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sas_reader_2000::sas::layout::Layout;
+use sas_reader_2000::sas::subheader::SubheaderSignature;
+use sas_reader_2000::sas::{Endianness, PageBinary};
+
+// The first byte picks the layout; the rest is the page.
+fuzz_target!(|data: &[u8]| {
+    let Some((&flags, bytes)) = data.split_first() else {
+        return;
+    };
+    let endianness = if flags & 2 == 0 {
+        Endianness::Little
+    } else {
+        Endianness::Big
+    };
+    let layout = Layout::new(flags & 1 == 1, endianness);
+
+    let Ok(page) = PageBinary::new(bytes, &layout) else {
+        return;
+    };
+    let _ = page.page_type();
+    let _ = page.row_offset(&layout);
+    let Ok(pointers) = page.pointers(&layout) else {
+        return;
+    };
+    for pointer in pointers {
+        if let Ok(subheader) = page.subheader(&pointer) {
+            let _ = SubheaderSignature::read(subheader, &layout);
+        }
+    }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sas_reader_2000::sas::rle;
+
+// The first two bytes give the row length; the rest is compressed input.
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let row_length = u16::from_le_bytes([data[0], data[1]]) as usize;
+    if let Ok(row) = rle::decompress(&data[2..], row_length) {
+        assert_eq!(row.len(), row_length);
+    }
+
+    let compressed = rle::compress(data);
+    assert_eq!(
+        rle::decompress(&compressed, data.len()).as_deref(),
+        Ok(data)
+    );
+});
@@ -0,0 +1,329 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use sas_reader_2000::export::csv::{write_csv, CsvOptions};
+use sas_reader_2000::export::json::{write_json, JsonFormat, JsonOptions};
+use sas_reader_2000::sas::xport::XportWriterOptions;
+use sas_reader_2000::sas::{
+    Encoding, Error, Row, Sas7bdatWriter, Schema, WriterOptions, XportWriter,
+};
+use sas_reader_2000::traits::DatasetReader;
+
+use crate::cli::input::{self, DecodeArgs, SelectArgs};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TargetFormat {
+    Csv,
+    Jsonl,
+    Json,
+    Parquet,
+    Arrow,
+    Xpt,
+    Sas7bdat,
+}
+
+impl TargetFormat {
+    fn from_path(path: &Path) -> Result<Self, Error> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match extension.as_str() {
+            "csv" => Ok(TargetFormat::Csv),
+            "jsonl" | "ndjson" => Ok(TargetFormat::Jsonl),
+            "json" => Ok(TargetFormat::Json),
+            "parquet" => Ok(TargetFormat::Parquet),
+            "arrow" | "ipc" | "feather" => Ok(TargetFormat::Arrow),
+            "xpt" => Ok(TargetFormat::Xpt),
+            "sas7bdat" => Ok(TargetFormat::Sas7bdat),
+            _ => Err(Error::Export(format!(
+                "Cannot tell the output format of {}. Use --to or one of the extensions \
+                 csv, jsonl, json, parquet, arrow, xpt or sas7bdat.",
+                path.display()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    #[command(flatten)]
+    select: SelectArgs,
+    #[command(flatten)]
+    decode: DecodeArgs,
+    /// Output format, instead of inferring it from the extension.
+    #[arg(long, value_enum)]
+    to: Option<TargetFormat>,
+    /// Encoding of text in CSV, XPORT and sas7bdat output. The other formats
+    /// are always UTF-8.
+    #[arg(long, default_value = "utf-8")]
+    output_encoding: Encoding,
+    input: PathBuf,
+    output: PathBuf,
+}
+
+pub fn run(args: &ConvertArgs, out: &mut dyn Write) -> Result<(), Error> {
+    let format = match args.to {
+        Some(format) => format,
+        None => TargetFormat::from_path(&args.output)?,
+    };
+    let reader = input::open(&args.input, &args.select, &args.decode)?;
+    let rows = convert(reader, &args.output, format, args.output_encoding)?;
+    writeln!(out, "Wrote {} rows to {}", rows, args.output.display())?;
+    Ok(())
+}
+
+/// Counts the rows passed through to a writer.
+struct Counted<D: DatasetReader> {
+    inner: D,
+    count: usize,
+}
+
+impl<D: DatasetReader> DatasetReader for Counted<D> {
+    fn schema(&self) -> &Schema {
+        self.inner.schema()
+    }
+
+    fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        let row = self.inner.read_row()?;
+        self.count += row.is_some() as usize;
+        Ok(row)
+    }
+
+    fn file_info(&self) -> Vec<(String, String)> {
+        self.inner.file_info()
+    }
+}
+
+fn convert<D: DatasetReader>(
+    reader: D,
+    path: &Path,
+    format: TargetFormat,
+    encoding: Encoding,
+) -> Result<usize, Error> {
+    let mut reader = Counted {
+        inner: reader,
+        count: 0,
+    };
+    let sink = || -> Result<BufWriter<File>, Error> { Ok(BufWriter::new(File::create(path)?)) };
+    let always_utf8 = matches!(
+        format,
+        TargetFormat::Jsonl | TargetFormat::Json | TargetFormat::Parquet | TargetFormat::Arrow
+    );
+    if always_utf8 && encoding != Encoding::Utf8 {
+        return Err(Error::Export(format!(
+            "{:?} output is always UTF-8, not {:?}",
+            format, encoding
+        )));
+    }
+    match format {
+        TargetFormat::Csv => {
+            let options = CsvOptions {
+                encoding,
+                ..CsvOptions::default()
+            };
+            write_csv(&mut reader, sink()?, options)?;
+        }
+        TargetFormat::Jsonl | TargetFormat::Json => {
+            let json_format = if format == TargetFormat::Json {
+                JsonFormat::Array
+            } else {
+                JsonFormat::Lines
+            };
+            let options = JsonOptions {
+                format: json_format,
+                ..JsonOptions::default()
+            };
+            write_json(&mut reader, sink()?, options)?;
+        }
+        TargetFormat::Parquet => write_parquet(&mut reader, path)?,
+        TargetFormat::Arrow => write_arrow(&mut reader, path)?,
+        TargetFormat::Xpt => {
+            // Transport names are upper case; SAS itself ignores case.
+            let mut schema = reader.schema().clone();
+            schema.name = schema.name.to_uppercase();
+            for column in &mut schema.columns {
+                column.name = column.name.to_uppercase();
+            }
+            let options = XportWriterOptions {
+                encoding,
+                ..XportWriterOptions::default()
+            };
+            let mut writer = XportWriter::new(sink()?, schema, options)?;
+            for row in reader.rows() {
+                writer.write_row(&row?)?;
+            }
+            writer.finish()?;
+        }
+        TargetFormat::Sas7bdat => {
+            let schema = reader.schema().clone();
+            let options = WriterOptions {
+                encoding,
+                ..WriterOptions::default()
+            };
+            let mut writer = Sas7bdatWriter::create(path, schema, options)?;
+            for row in reader.rows() {
+                writer.write_row(&row?)?;
+            }
+            writer.finish()?;
+        }
+    }
+    Ok(reader.count)
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet<D: DatasetReader>(reader: &mut D, path: &Path) -> Result<(), Error> {
+    use sas_reader_2000::export::parquet::{self, ParquetOptions};
+    let sink = BufWriter::new(File::create(path)?);
+    parquet::write_parquet(reader, sink, &ParquetOptions::default())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet<D: DatasetReader>(_reader: &mut D, _path: &Path) -> Result<(), Error> {
+    Err(missing_feature("parquet"))
+}
+
+#[cfg(feature = "arrow")]
+fn write_arrow<D: DatasetReader>(reader: &mut D, path: &Path) -> Result<(), Error> {
+    use sas_reader_2000::export::arrow::{write_ipc_file, DEFAULT_BATCH_SIZE};
+    let sink = BufWriter::new(File::create(path)?);
+    write_ipc_file(reader, sink, DEFAULT_BATCH_SIZE)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "arrow"))]
+fn write_arrow<D: DatasetReader>(_reader: &mut D, _path: &Path) -> Result<(), Error> {
+    Err(missing_feature("arrow"))
+}
+
+#[cfg(not(all(feature = "arrow", feature = "parquet")))]
+fn missing_feature(feature: &str) -> Error {
+    Error::Export(format!(
+        "This build cannot write {0}; rebuild with --features {0}",
+        feature
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use sas_reader_2000::sas::{Sas7bdatReader, XportReader};
+
+    use super::*;
+    use crate::cli::run_args;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sas-reader-2000-convert-{}", name))
+    }
+
+    #[test]
+    fn can_infer_format_from_extension() {
+        assert_eq!(
+            TargetFormat::from_path(Path::new("out.NDJSON")),
+            Ok(TargetFormat::Jsonl)
+        );
+        assert_eq!(
+            TargetFormat::from_path(Path::new("a/b.xpt")),
+            Ok(TargetFormat::Xpt)
+        );
+        assert!(TargetFormat::from_path(Path::new("out.txt")).is_err());
+    }
+
+    #[test]
+    fn can_convert_to_csv_and_jsonl() {
+        let csv = temp_path("rows.csv");
+        let message = run_args(&[
+            "convert",
+            "--limit",
+            "3",
+            "test/hadley.sas7bdat",
+            csv.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert!(message.starts_with("Wrote 3 rows to"));
+        assert_eq!(std::fs::read_to_string(&csv).unwrap().lines().count(), 4);
+
+        let jsonl = temp_path("rows.jsonl");
+        run_args(&[
+            "convert",
+            "-c",
+            "gender",
+            "test/hadley.sas7bdat",
+            jsonl.to_str().unwrap(),
+        ])
+        .unwrap();
+        let text = std::fs::read_to_string(&jsonl).unwrap();
+        assert_eq!(text.lines().next(), Some("{\"gender\":\"f\"}"));
+
+        std::fs::remove_file(csv).unwrap();
+        std::fs::remove_file(jsonl).unwrap();
+    }
+
+    #[test]
+    fn can_convert_between_sas_formats() {
+        let xpt = temp_path("rows.xpt");
+        let sas7bdat = temp_path("rows.sas7bdat");
+        run_args(&["convert", "test/hadley.sas7bdat", xpt.to_str().unwrap()]).unwrap();
+        run_args(&[
+            "convert",
+            "--offset",
+            "2",
+            xpt.to_str().unwrap(),
+            sas7bdat.to_str().unwrap(),
+        ])
+        .unwrap();
+
+        let reader = XportReader::open(&xpt).unwrap();
+        assert_eq!(reader.schema().columns[0].name, "ID");
+        let mut reader = Sas7bdatReader::open(&sas7bdat).unwrap();
+        let rows: Vec<_> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 6);
+
+        std::fs::remove_file(xpt).unwrap();
+        std::fs::remove_file(sas7bdat).unwrap();
+    }
+    #[test]
+    fn can_convert_between_encodings() {
+        use sas_reader_2000::sas::{Column, Value};
+
+        // Windows-1252 text in a file labelled UTF-8
+        let input = temp_path("mislabelled.sas7bdat");
+        let schema = Schema::new("CITIES", vec![Column::character("city", 8)]);
+        let options = WriterOptions {
+            encoding: Encoding::Windows1252,
+            ..WriterOptions::default()
+        };
+        let mut writer = Sas7bdatWriter::create(&input, schema, options).unwrap();
+        writer.write_row(&[Value::from("Zürich")]).unwrap();
+        writer.finish().unwrap();
+        let mut bytes = std::fs::read(&input).unwrap();
+        bytes[70] = Encoding::Utf8.to_u8().unwrap();
+        std::fs::write(&input, bytes).unwrap();
+
+        let csv = temp_path("latin1.csv");
+        let paths = [input.to_str().unwrap(), csv.to_str().unwrap()];
+        run_args(&[&["convert", "--encoding", "windows-1252"], &paths[..]].concat()).unwrap();
+        assert_eq!(std::fs::read(&csv).unwrap(), "city\nZürich\n".as_bytes());
+
+        let args = [
+            "convert",
+            "--encoding",
+            "windows1252",
+            "--output-encoding",
+            "latin1",
+        ];
+        run_args(&[&args[..], &paths[..]].concat()).unwrap();
+        assert_eq!(std::fs::read(&csv).unwrap(), b"city\nZ\xfcrich\n");
+
+        let strict = ["convert", "--invalid-bytes", "strict"];
+        assert!(run_args(&[&strict[..], &paths[..]].concat()).is_err());
+        let json = ["convert", "--output-encoding", "latin1", "--to", "json"];
+        assert!(run_args(&[&json[..], &paths[..]].concat()).is_err());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(csv).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Args;
+use sas_reader_2000::export::json::{object, string};
+use sas_reader_2000::sas::{Error, Sas7bdatReader};
+use sas_reader_2000::traits::ReadSeek;
+
+use crate::cli::OutputFormat;
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+    file: PathBuf,
+}
+
+/// A value shown by `info`, kept typed so JSON output can use numbers.
+enum Fact {
+    Text(String),
+    Count(usize),
+}
+
+impl Fact {
+    fn json(&self) -> String {
+        match self {
+            Fact::Text(text) => string(text),
+            Fact::Count(count) => count.to_string(),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Fact::Text(text) => text.clone(),
+            Fact::Count(count) => count.to_string(),
+        }
+    }
+}
+
+type Section = (&'static str, Vec<(&'static str, Fact)>);
+
+fn optional<T: std::fmt::Debug>(value: Option<T>) -> Fact {
+    Fact::Text(value.map_or_else(|| "unknown".to_string(), |v| format!("{:?}", v)))
+}
+
+fn sections<R: ReadSeek>(reader: &Sas7bdatReader<R>) -> Vec<Section> {
+    let header = reader.header();
+    let properties = reader.properties();
+    let timestamp = |time: Option<chrono::NaiveDateTime>| {
+        Fact::Text(time.map_or_else(
+            || "unknown".to_string(),
+            |time| time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        ))
+    };
+
+    let header_facts = vec![
+        (
+            "file_type",
+            optional(header.get_ascii_file_type_from_header().ok()),
+        ),
+        ("encoding", Fact::Text(format!("{:?}", properties.encoding))),
+        (
+            "endianness",
+            Fact::Text(format!("{:?}", properties.endianness())),
+        ),
+        (
+            "bits",
+            Fact::Count(if properties.is_u64() { 64 } else { 32 }),
+        ),
+        ("os_type", optional(properties.os_type)),
+        (
+            "os_maker",
+            optional(header.get_os_maker_or_version_from_header()),
+        ),
+        (
+            "os_version",
+            Fact::Text(
+                header
+                    .get_os_version_number_from_header()
+                    .unwrap_or_default(),
+            ),
+        ),
+        ("os_name", Fact::Text(properties.os_name.clone())),
+        ("sas_release", Fact::Text(properties.release.clone())),
+        ("server_type", Fact::Text(properties.server_type.clone())),
+        ("dataset_name", Fact::Text(properties.dataset_name.clone())),
+        ("created", timestamp(properties.created)),
+        ("modified", timestamp(properties.modified)),
+        ("header_length", Fact::Count(properties.header_length)),
+        ("page_size", Fact::Count(properties.page_size)),
+        ("page_count", Fact::Count(properties.page_count)),
+    ];
+    let dataset_facts = vec![
+        ("label", Fact::Text(properties.file_label.clone())),
+        ("row_count", Fact::Count(properties.row_count)),
+        ("column_count", Fact::Count(properties.column_count)),
+        ("row_length", Fact::Count(properties.row_length)),
+        (
+            "compression",
+            Fact::Text(format!("{:?}", properties.compression)),
+        ),
+    ];
+    vec![("header", header_facts), ("dataset", dataset_facts)]
+}
+
+pub fn run(args: &InfoArgs, out: &mut dyn Write) -> Result<(), Error> {
+    let reader = Sas7bdatReader::open(&args.file)?;
+    let sections = sections(&reader);
+
+    match args.format {
+        OutputFormat::Text => {
+            let width = sections
+                .iter()
+                .flat_map(|(_, facts)| facts.iter().map(|(name, _)| name.len()))
+                .max()
+                .unwrap_or(0);
+            for (index, (title, facts)) in sections.iter().enumerate() {
+                if index > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", title)?;
+                for (name, fact) in facts {
+                    let line = format!("  {:<width$}  {}", name, fact.text(), width = width);
+                    writeln!(out, "{}", line.trim_end())?;
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let sections: Vec<(&str, String)> = sections
+                .iter()
+                .map(|(title, facts)| {
+                    let facts: Vec<(&str, String)> = facts
+                        .iter()
+                        .map(|(name, fact)| (*name, fact.json()))
+                        .collect();
+                    (*title, object(&facts))
+                })
+                .collect();
+            writeln!(out, "{}", object(&sections))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::run_args;
+
+    #[test]
+    fn can_print_info_as_text() {
+        let text = run_args(&["info", "test/hadley.sas7bdat"]).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "header");
+        assert!(lines.contains(&"  encoding       Windows1252"));
+        assert!(lines.contains(&"  bits           32"));
+        assert!(lines.contains(&"  os_type        Windows"));
+        assert!(lines.contains(&"  page_size      65536"));
+        assert!(lines.contains(&"dataset"));
+        assert!(lines.contains(&"  row_count      8"));
+        assert!(lines.contains(&"  column_count   7"));
+        assert!(lines.contains(&"  compression    None"));
+    }
+
+    #[test]
+    fn can_print_info_as_json() {
+        let text = run_args(&["info", "--format", "json", "test/hadley.sas7bdat"]).unwrap();
+
+        assert!(
+            text.starts_with("{\"header\":{\"file_type\":\"Data\",\"encoding\":\"Windows1252\"")
+        );
+        assert!(text.contains("\"dataset_name\":\"HADLEY\""));
+        assert!(text.contains("\"dataset\":{\"label\":\"\",\"row_count\":8,\"column_count\":7"));
+    }
+
+    #[test]
+    fn cannot_print_info_for_missing_file() {
+        assert!(run_args(&["info", "test/missing.sas7bdat"]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use clap::{Args, ValueEnum};
+use sas_reader_2000::sas::{
+    Encoding, Error, InvalidBytes, ReaderOptions, Row, Sas7bdatReader, Schema, XportReader,
+};
+use sas_reader_2000::traits::DatasetReader;
+
+/// Which rows and columns of the input to use.
+#[derive(Debug, Default, Args)]
+pub struct SelectArgs {
+    /// Columns to keep, in order, separated by commas.
+    #[arg(long, short = 'c', value_delimiter = ',')]
+    pub columns: Vec<String>,
+    /// Number of rows to skip.
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+    /// Maximum number of rows.
+    #[arg(long, short = 'n')]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InvalidBytesArg {
+    /// Fail on the first cell that does not decode.
+    Strict,
+    /// Put U+FFFD in place of bytes that do not decode.
+    Replace,
+    /// Pass undecodable cells through as bytes.
+    Raw,
+}
+
+impl From<InvalidBytesArg> for InvalidBytes {
+    fn from(arg: InvalidBytesArg) -> Self {
+        match arg {
+            InvalidBytesArg::Strict => InvalidBytes::Strict,
+            InvalidBytesArg::Replace => InvalidBytes::Replace,
+            InvalidBytesArg::Raw => InvalidBytes::Raw,
+        }
+    }
+}
+
+/// How the text of a sas7bdat input is decoded.
+#[derive(Debug, Args)]
+pub struct DecodeArgs {
+    /// Encoding to read text in, instead of the one in the file header.
+    #[arg(long)]
+    pub encoding: Option<Encoding>,
+    /// What to do with text that is not valid in the encoding.
+    #[arg(long, value_enum, default_value = "replace")]
+    pub invalid_bytes: InvalidBytesArg,
+}
+
+impl Default for DecodeArgs {
+    fn default() -> Self {
+        DecodeArgs {
+            encoding: None,
+            invalid_bytes: InvalidBytesArg::Replace,
+        }
+    }
+}
+
+/// Applies a column selection, offset and limit to another reader.
+pub struct Window<D: DatasetReader> {
+    inner: D,
+    schema: Schema,
+    indexes: Option<Vec<usize>>,
+    offset: usize,
+    limit: Option<usize>,
+    read: usize,
+}
+
+impl<D: DatasetReader> Window<D> {
+    pub fn new(inner: D, select: &SelectArgs) -> Result<Self, Error> {
+        let mut schema = inner.schema().clone();
+        let indexes = if select.columns.is_empty() {
+            None
+        } else {
+            let indexes = select
+                .columns
+                .iter()
+                .map(|name| {
+                    schema
+                        .column_index(name)
+                        .ok_or_else(|| Error::InvalidSchema(format!("No column named {}", name)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            schema.columns = indexes.iter().map(|&i| schema.columns[i].clone()).collect();
+            Some(indexes)
+        };
+        Ok(Window {
+            inner,
+            schema,
+            indexes,
+            offset: select.offset,
+            limit: select.limit,
+            read: 0,
+        })
+    }
+}
+
+impl<D: DatasetReader> DatasetReader for Window<D> {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        while self.offset > 0 {
+            if self.inner.read_row()?.is_none() {
+                return Ok(None);
+            }
+            self.offset -= 1;
+        }
+        if self.limit.is_some_and(|limit| self.read >= limit) {
+            return Ok(None);
+        }
+        let Some(row) = self.inner.read_row()? else {
+            return Ok(None);
+        };
+        self.read += 1;
+        Ok(Some(match &self.indexes {
+            Some(indexes) => indexes.iter().map(|&i| row[i].clone()).collect(),
+            None => row,
+        }))
+    }
+
+    fn file_info(&self) -> Vec<(String, String)> {
+        self.inner.file_info()
+    }
+}
+
+/// Whether a file starts like a SAS transport file.
+fn is_xport(path: &Path) -> Result<bool, Error> {
+    let mut start = [0_u8; 20];
+    let mut file = File::open(path)?;
+    let len = file.read(&mut start)?;
+    Ok(start[..len] == *b"HEADER RECORD*******")
+}
+
+/// Open a sas7bdat or XPORT file, telling them apart by content. For
+/// sas7bdat files the column selection is pushed into the reader.
+pub fn open(
+    path: &Path,
+    select: &SelectArgs,
+    decode: &DecodeArgs,
+) -> Result<Window<Box<dyn DatasetReader>>, Error> {
+    if is_xport(path)? {
+        return Window::new(Box::new(XportReader::open(path)?), select);
+    }
+    let options = ReaderOptions {
+        encoding: decode.encoding,
+        invalid_bytes: decode.invalid_bytes.into(),
+    };
+    let mut reader = Sas7bdatReader::open_with_options(path, options)?;
+    if !select.columns.is_empty() {
+        reader.select(&select.columns)?;
+    }
+    let select = SelectArgs {
+        columns: Vec::new(),
+        offset: select.offset,
+        limit: select.limit,
+    };
+    Window::new(Box::new(reader), &select)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sas_reader_2000::sas::Value;
+
+    #[test]
+    fn can_window_rows_and_columns() {
+        let reader = Sas7bdatReader::open("test/hadley.sas7bdat").unwrap();
+        let select = SelectArgs {
+            columns: vec!["Q4".to_string(), "id".to_string()],
+            offset: 6,
+            limit: Some(5),
+        };
+        let mut window = Window::new(reader, &select).unwrap();
+
+        assert_eq!(window.schema().columns[0].name, "q4");
+        let rows: Vec<Row> = window.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][1], Value::Number(8.0));
+    }
+
+    #[test]
+    fn can_open_by_content() {
+        let select = SelectArgs::default();
+        let decode = DecodeArgs::default();
+        let reader = open(Path::new("test/hadley.sas7bdat"), &select, &decode).unwrap();
+        assert_eq!(reader.schema().name, "HADLEY");
+        assert!(open(Path::new("Cargo.toml"), &select, &decode).is_err());
+    }
+}
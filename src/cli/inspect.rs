@@ -0,0 +1,265 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use sas_reader_2000::sas::layout::Layout;
+use sas_reader_2000::sas::subheader::{SubheaderPointer, SubheaderSignature};
+use sas_reader_2000::sas::{
+    Compression, Error, PageBinary, SasConstants, SasHeaderBinary, SasProperties,
+};
+
+#[derive(Debug, Args)]
+pub struct InspectArgs {
+    /// Only show this page, counting from 0.
+    #[arg(long)]
+    page: Option<usize>,
+    /// Only show this subheader pointer of the page, counting from 0.
+    #[arg(long, requires = "page")]
+    subheader: Option<usize>,
+    /// Hexdump the selected page or subheader instead of listing it.
+    #[arg(long, requires = "page")]
+    hex: bool,
+    file: PathBuf,
+}
+
+/// Name what a subheader pointer points at, without trusting it.
+fn describe(page: &PageBinary, pointer: &SubheaderPointer, layout: &Layout) -> String {
+    if pointer.is_empty() {
+        return "empty".to_string();
+    }
+    if pointer.compression != Compression::None {
+        return "compressed row".to_string();
+    }
+    let Ok(bytes) = page.subheader(pointer) else {
+        return "outside the page".to_string();
+    };
+    match SubheaderSignature::read(bytes, layout) {
+        Ok(signature) if pointer.is_compressed_data && !signature.is_recognized() => {
+            "row".to_string()
+        }
+        Ok(SubheaderSignature::Unknown(value)) => format!("unknown {:#010x}", value),
+        Ok(signature) => signature.name().to_string(),
+        Err(_) => "too short for a signature".to_string(),
+    }
+}
+
+/// Write bytes sixteen to a line with their file offsets, collapsing runs
+/// of identical lines into a `*` the way `hexdump -C` does.
+fn hexdump(bytes: &[u8], base: usize, out: &mut dyn Write) -> Result<(), Error> {
+    let mut previous: Option<&[u8]> = None;
+    let mut skipping = false;
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        if chunk.len() == 16 && previous == Some(chunk) {
+            if !skipping {
+                writeln!(out, "*")?;
+                skipping = true;
+            }
+            continue;
+        }
+        previous = Some(chunk);
+        skipping = false;
+
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        writeln!(
+            out,
+            "{:08x}  {:<47}  |{}|",
+            base + i * 16,
+            hex.join(" "),
+            text
+        )?;
+    }
+    writeln!(out, "{:08x}", base + bytes.len())?;
+    Ok(())
+}
+
+pub fn run(args: &InspectArgs, out: &mut dyn Write) -> Result<(), Error> {
+    let mut source = BufReader::new(File::open(&args.file)?);
+    let mut header = vec![0_u8; SasConstants::new().file_header_size_32_bit as usize];
+    source
+        .read_exact(&mut header)
+        .map_err(|error| Error::InvalidHeader(format!("File is too short: {}", error)))?;
+    let properties = SasProperties::from_header(&SasHeaderBinary::new(&header))?;
+    let layout = properties.layout;
+
+    let pages = match args.page {
+        Some(index) if index >= properties.page_count => {
+            return Err(Error::InvalidPage(format!(
+                "Page {} is past the last of {} pages",
+                index, properties.page_count
+            )));
+        }
+        Some(index) => index..index + 1,
+        None => 0..properties.page_count,
+    };
+
+    if !args.hex {
+        writeln!(
+            out,
+            "header  length {}  page_size {}  page_count {}  bits {}  endianness {:?}",
+            properties.header_length,
+            properties.page_size,
+            properties.page_count,
+            if layout.u64 { 64 } else { 32 },
+            layout.endianness
+        )?;
+    }
+
+    for index in pages {
+        let page_offset = properties.page_offset(index)? as usize;
+        let mut bytes = vec![0_u8; properties.page_size];
+        source.seek(SeekFrom::Start(page_offset as u64))?;
+        source.read_exact(&mut bytes).map_err(|error| {
+            Error::InvalidPage(format!("Could not read page {}: {}", index, error))
+        })?;
+
+        if args.hex && args.subheader.is_none() {
+            return hexdump(&bytes, page_offset, out);
+        }
+        let page = match PageBinary::new(&bytes, &layout) {
+            Ok(page) => page,
+            Err(error) => {
+                writeln!(
+                    out,
+                    "page {}  offset {}  unreadable: {}",
+                    index, page_offset, error
+                )?;
+                continue;
+            }
+        };
+        if let Some(subheader) = args.subheader {
+            if subheader >= page.subheader_count as usize {
+                return Err(Error::InvalidSubheader(format!(
+                    "Page {} has {} subheader pointers",
+                    index, page.subheader_count
+                )));
+            }
+        }
+
+        if !args.hex {
+            writeln!(
+                out,
+                "page {}  offset {}  type {:?}  blocks {}  subheaders {}",
+                index,
+                page_offset,
+                page.page_type(),
+                page.block_count,
+                page.subheader_count
+            )?;
+        }
+        let pointer_size = layout.subheader_pointer_size();
+        for i in 0..page.subheader_count as usize {
+            if args.subheader.is_some_and(|subheader| subheader != i) {
+                continue;
+            }
+            let pointer =
+                match SubheaderPointer::read(&page.subheader_pointers, i * pointer_size, &layout) {
+                    Ok(pointer) => pointer,
+                    Err(error) if args.hex => return Err(error),
+                    Err(error) => {
+                        writeln!(out, "  subheader {}  unreadable: {}", i, error)?;
+                        continue;
+                    }
+                };
+            if args.hex {
+                return hexdump(page.subheader(&pointer)?, page_offset + pointer.offset, out);
+            }
+            writeln!(
+                out,
+                "  subheader {}  offset {}  length {}  compression {:?}  {}",
+                i,
+                pointer.offset,
+                pointer.length,
+                pointer.compression,
+                describe(&page, &pointer, &layout)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::run_args;
+
+    #[test]
+    fn can_list_pages_and_subheaders() {
+        let text = run_args(&["inspect", "test/hadley.sas7bdat"]).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "header  length 65536  page_size 65536  page_count 1  bits 32  endianness Little"
+        );
+        assert!(lines[1].starts_with("page 0  offset 65536  type Mix  blocks"));
+        assert!(lines[2].starts_with("  subheader 0  offset"));
+        assert!(lines
+            .iter()
+            .any(|line| line.ends_with("compression None  row size")));
+        assert!(lines.iter().any(|line| line.ends_with("column text")));
+    }
+
+    #[test]
+    fn can_hexdump_subheader() {
+        let listing = run_args(&[
+            "inspect",
+            "--page",
+            "0",
+            "--subheader",
+            "0",
+            "test/hadley.sas7bdat",
+        ])
+        .unwrap();
+        assert_eq!(
+            listing.lines().nth(2),
+            Some("  subheader 0  offset 65056  length 480  compression None  row size")
+        );
+        assert_eq!(listing.lines().count(), 3);
+
+        let text = run_args(&[
+            "inspect",
+            "--page",
+            "0",
+            "--subheader",
+            "0",
+            "--hex",
+            "test/hadley.sas7bdat",
+        ])
+        .unwrap();
+        let first = text.lines().next().unwrap();
+        assert!(first.starts_with(&format!("{:08x}  f7 f7 f7 f7", 65536 + 65056)));
+    }
+
+    #[test]
+    fn cannot_inspect_missing_page() {
+        assert!(run_args(&["inspect", "--page", "1", "test/hadley.sas7bdat"]).is_err());
+        assert!(run_args(&["inspect", "--hex", "test/hadley.sas7bdat"]).is_err());
+    }
+
+    #[test]
+    fn test_hexdump_collapses_repeated_lines() {
+        let mut bytes = b"SAS FILE".to_vec();
+        bytes.extend([0_u8; 56]);
+        let mut out = Vec::new();
+        hexdump(&bytes, 0x100, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00000100  53 41 53 20 46 49 4c 45 00 00 00 00 00 00 00 00  |SAS FILE........|\n\
+             00000110  00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00  |................|\n\
+             *\n\
+             00000140\n"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+pub mod convert;
+pub mod info;
+pub mod input;
+pub mod inspect;
+pub mod print;
+pub mod unknowns;
+pub mod validate;
+
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use sas_reader_2000::sas::Error;
+
+/// Read SAS datasets on machines without SAS.
+#[derive(Debug, Parser)]
+#[command(name = "sas-reader", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the header and dataset metadata of a sas7bdat file.
+    Info(info::InfoArgs),
+    /// List the pages and subheaders of a sas7bdat file, or hexdump one.
+    Inspect(inspect::InspectArgs),
+    /// Print the first rows of a file.
+    Head(print::PrintArgs),
+    /// Print every row of a file.
+    Cat(print::PrintArgs),
+    /// Convert a file to CSV, JSON, Parquet, Arrow, XPORT or sas7bdat.
+    Convert(convert::ConvertArgs),
+    /// Tabulate the undocumented header bytes of many sas7bdat files against
+    /// their known header fields.
+    Unknowns(unknowns::UnknownsArgs),
+    /// Check sas7bdat files for structural problems. Exits with 1 when any
+    /// file has errors, or warnings under --strict.
+    Validate(validate::ValidateArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+pub fn run(cli: Cli, out: &mut dyn Write) -> Result<ExitCode, Error> {
+    match cli.command {
+        Command::Info(args) => info::run(&args, out)?,
+        Command::Inspect(args) => inspect::run(&args, out)?,
+        Command::Head(args) => print::head(&args, out)?,
+        Command::Cat(args) => print::cat(&args, out)?,
+        Command::Convert(args) => convert::run(&args, out)?,
+        Command::Unknowns(args) => unknowns::run(&args, out)?,
+        Command::Validate(args) => return validate::run(&args, out),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let stdout = io::stdout();
+    match run(cli, &mut stdout.lock()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the command line given without the program name and return what it
+/// printed, whatever the exit status.
+#[cfg(test)]
+pub fn run_args(args: &[&str]) -> Result<String, Error> {
+    let cli = Cli::try_parse_from(std::iter::once("sas-reader").chain(args.iter().copied()))
+        .map_err(|error| Error::Io(error.to_string()))?;
+    let mut out = Vec::new();
+    run(cli, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cannot_run_unknown_command() {
+        assert!(run_args(&["explode", "test/hadley.sas7bdat"]).is_err());
+    }
+}
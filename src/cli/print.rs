@@ -0,0 +1,151 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use sas_reader_2000::export::csv::{CsvOptions, CsvWriter};
+use sas_reader_2000::sas::{ColumnType, Error};
+use sas_reader_2000::traits::DatasetReader;
+
+use crate::cli::input::{self, DecodeArgs, SelectArgs};
+
+/// Rows shown by `head` when no limit is given.
+const HEAD_ROWS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RowFormat {
+    /// Columns aligned under their names, with SAS formats applied.
+    Table,
+    Csv,
+}
+
+#[derive(Debug, Args)]
+pub struct PrintArgs {
+    #[command(flatten)]
+    select: SelectArgs,
+    #[command(flatten)]
+    decode: DecodeArgs,
+    #[arg(long, value_enum, default_value = "table")]
+    format: RowFormat,
+    file: PathBuf,
+}
+
+/// Print the first rows of a file.
+pub fn head(args: &PrintArgs, out: &mut dyn Write) -> Result<(), Error> {
+    let select = SelectArgs {
+        columns: args.select.columns.clone(),
+        offset: args.select.offset,
+        limit: Some(args.select.limit.unwrap_or(HEAD_ROWS)),
+    };
+    print(&args.file, &select, &args.decode, args.format, out)
+}
+
+/// Print every row of a file. Rows are streamed in both formats; table
+/// columns are as wide as their SAS formats.
+pub fn cat(args: &PrintArgs, out: &mut dyn Write) -> Result<(), Error> {
+    print(&args.file, &args.select, &args.decode, args.format, out)
+}
+
+fn print(
+    path: &Path,
+    select: &SelectArgs,
+    decode: &DecodeArgs,
+    format: RowFormat,
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut reader = input::open(path, select, decode)?;
+    match format {
+        RowFormat::Table => write_table(&mut reader, out),
+        RowFormat::Csv => {
+            let mut writer = CsvWriter::new(out, reader.schema().clone(), CsvOptions::default())?;
+            for row in reader.rows() {
+                writer.write_row(&row?)?;
+            }
+            writer.finish()?;
+            Ok(())
+        }
+    }
+}
+
+/// Write rows as they are read, each column as wide as its format or name.
+fn write_table<D: DatasetReader>(reader: &mut D, out: &mut dyn Write) -> Result<(), Error> {
+    let columns = reader.schema().columns.clone();
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|column| column.sas_format().width().max(column.name.chars().count()))
+        .collect();
+
+    let line = |fields: Vec<String>| fields.join("  ").trim_end().to_string();
+    let names = columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, &width)| format!("{:<width$}", column.name))
+        .collect();
+    writeln!(out, "{}", line(names))?;
+    let rules = widths.iter().map(|&width| "-".repeat(width)).collect();
+    writeln!(out, "{}", line(rules))?;
+    for row in reader.rows() {
+        let fields = row?
+            .iter()
+            .zip(columns.iter().zip(&widths))
+            .map(|(value, (column, &width))| {
+                let cell = column.render(value);
+                match column.column_type {
+                    ColumnType::Numeric => format!("{:>width$}", cell.trim()),
+                    ColumnType::Character => format!("{:<width$}", cell.trim()),
+                }
+            })
+            .collect();
+        writeln!(out, "{}", line(fields))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::run_args;
+
+    #[test]
+    fn can_print_head_as_table() {
+        let text = run_args(&[
+            "head",
+            "-n",
+            "2",
+            "-c",
+            "id,gender,q1",
+            "test/hadley.sas7bdat",
+        ])
+        .unwrap();
+        assert_eq!(
+            text,
+            "id            gender  q1\n\
+             ------------  ------  ------------\n\
+             \x20          1  f                  1\n\
+             \x20          2  f                  2\n"
+        );
+    }
+
+    #[test]
+    fn can_print_head_with_offset_as_csv() {
+        let text = run_args(&[
+            "head",
+            "--offset",
+            "6",
+            "--format",
+            "csv",
+            "--columns",
+            "id",
+            "test/hadley.sas7bdat",
+        ])
+        .unwrap();
+        assert_eq!(text, "id\n7\n8\n");
+    }
+
+    #[test]
+    fn can_cat_every_row() {
+        let text = run_args(&["cat", "--format", "csv", "test/hadley.sas7bdat"]).unwrap();
+        assert_eq!(text.lines().count(), 9);
+
+        let text = run_args(&["cat", "test/hadley.sas7bdat"]).unwrap();
+        assert_eq!(text.lines().count(), 10);
+    }
+}
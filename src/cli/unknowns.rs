@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use sas_reader_2000::export::json::{object, string};
+use sas_reader_2000::sas::header::unknowns::{RangeSummary, KNOWN_FIELDS};
+use sas_reader_2000::sas::{Error, SasConstants, SasHeaderBinary, UnknownsSurvey};
+
+use crate::cli::OutputFormat;
+
+#[derive(Debug, Args)]
+pub struct UnknownsArgs {
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+fn read_header(path: &Path) -> Result<SasHeaderBinary, Error> {
+    let mut bytes = vec![0_u8; SasConstants::new().file_header_size_32_bit as usize];
+    File::open(path)?
+        .read_exact(&mut bytes)
+        .map_err(|error| Error::InvalidHeader(format!("File is too short: {}", error)))?;
+    Ok(SasHeaderBinary::new(&bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn array(items: Vec<String>) -> String {
+    format!("[{}]", items.join(","))
+}
+
+fn write_text(summaries: &[RangeSummary], out: &mut dyn Write) -> Result<(), Error> {
+    for summary in summaries {
+        let values = summary.values.len();
+        let mut title = format!(
+            "{}..{}  {} value{}",
+            summary.range.start,
+            summary.range.end,
+            values,
+            if values == 1 { "" } else { "s" }
+        );
+        if !summary.determined_by.is_empty() {
+            title += &format!(", determined by {}", summary.determined_by.join(", "));
+        }
+        writeln!(out, "{}", title)?;
+
+        let width = summary.range.len() * 3 - 1;
+        for value in &summary.values {
+            let known: Vec<String> = KNOWN_FIELDS
+                .iter()
+                .zip(&value.known)
+                .map(|(name, seen)| {
+                    format!(
+                        "{} {}",
+                        name,
+                        seen.iter().cloned().collect::<Vec<_>>().join("/")
+                    )
+                })
+                .collect();
+            let files = format!(
+                "{} file{}",
+                value.files,
+                if value.files == 1 { "" } else { "s" }
+            );
+            writeln!(
+                out,
+                "  {:<width$}  {:<8} {}",
+                hex(&value.bytes),
+                files,
+                known.join("  "),
+                width = width
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(
+    survey: &UnknownsSurvey,
+    skipped: &[(String, String)],
+    summaries: &[RangeSummary],
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    let ranges = summaries
+        .iter()
+        .map(|summary| {
+            let values = summary
+                .values
+                .iter()
+                .map(|value| {
+                    let mut fields = vec![
+                        ("bytes", string(&hex(&value.bytes))),
+                        ("files", value.files.to_string()),
+                    ];
+                    for (name, seen) in KNOWN_FIELDS.iter().zip(&value.known) {
+                        fields.push((*name, array(seen.iter().map(|v| string(v)).collect())));
+                    }
+                    object(&fields)
+                })
+                .collect();
+            object(&[
+                ("start", summary.range.start.to_string()),
+                ("end", summary.range.end.to_string()),
+                (
+                    "determined_by",
+                    array(
+                        summary
+                            .determined_by
+                            .iter()
+                            .map(|name| string(name))
+                            .collect(),
+                    ),
+                ),
+                ("values", array(values)),
+            ])
+        })
+        .collect();
+    let skipped = skipped
+        .iter()
+        .map(|(path, error)| object(&[("file", string(path)), ("error", string(error))]))
+        .collect();
+    let report = object(&[
+        ("files", survey.file_count().to_string()),
+        ("skipped", array(skipped)),
+        ("ranges", array(ranges)),
+    ]);
+    writeln!(out, "{}", report)?;
+    Ok(())
+}
+
+/// Survey the undocumented header ranges of many files. Files that cannot
+/// be read are listed and left out.
+pub fn run(args: &UnknownsArgs, out: &mut dyn Write) -> Result<(), Error> {
+    let mut survey = UnknownsSurvey::new();
+    let mut skipped = Vec::new();
+    for path in &args.files {
+        if let Err(error) = read_header(path).and_then(|header| survey.add(&header)) {
+            skipped.push((path.display().to_string(), error.to_string()));
+        }
+    }
+    let summaries = survey.summarize();
+
+    match args.format {
+        OutputFormat::Text => {
+            writeln!(out, "{} files", survey.file_count())?;
+            for (path, error) in &skipped {
+                writeln!(out, "skipped {}: {}", path, error)?;
+            }
+            write_text(&summaries, out)
+        }
+        OutputFormat::Json => write_json(&survey, &skipped, &summaries, out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::run_args;
+
+    #[test]
+    fn can_survey_unknowns_as_text() {
+        let text = run_args(&[
+            "unknowns",
+            "test/hadley.sas7bdat",
+            "test/hadley.sas7bdat",
+            "Cargo.toml",
+        ])
+        .unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "2 files");
+        assert!(lines[1].starts_with("skipped Cargo.toml: "));
+        assert_eq!(lines[2], "33..35  1 value");
+        assert_eq!(
+            lines[3],
+            "  22 00  2 files  os_type Windows  release 9.0401M1  encoding Windows1252  u64 false"
+        );
+    }
+
+    #[test]
+    fn can_survey_unknowns_as_json() {
+        let text = run_args(&["unknowns", "--format", "json", "test/hadley.sas7bdat"]).unwrap();
+
+        assert!(text.starts_with(
+            "{\"files\":1,\"skipped\":[],\"ranges\":[{\"start\":33,\"end\":35,\
+             \"determined_by\":[],\"values\":[{\"bytes\":\"22 00\",\"files\":1,\
+             \"os_type\":[\"Windows\"],"
+        ));
+    }
+}
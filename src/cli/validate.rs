@@ -0,0 +1,158 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+use sas_reader_2000::export::json::{object, string};
+use sas_reader_2000::sas::validate::validate_file;
+use sas_reader_2000::sas::{Error, Issue, Severity};
+
+use crate::cli::OutputFormat;
+
+/// Exit status when any file fails. Usage errors exit with 2.
+const INVALID: u8 = 1;
+
+#[derive(Debug, Args)]
+pub struct ValidateArgs {
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    strict: bool,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+fn passes(issues: &[Issue], strict: bool) -> bool {
+    issues
+        .iter()
+        .all(|issue| issue.severity == Severity::Warning && !strict)
+}
+
+fn count(issues: &[Issue], severity: Severity) -> usize {
+    issues
+        .iter()
+        .filter(|issue| issue.severity == severity)
+        .count()
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Check every file, printing one report per file. Files that cannot be
+/// opened fail like invalid ones, so a whole drop is always reported.
+pub fn run(args: &ValidateArgs, out: &mut dyn Write) -> Result<ExitCode, Error> {
+    let mut all_pass = true;
+    for path in &args.files {
+        let issues = validate_file(path).unwrap_or_else(|error| {
+            vec![Issue {
+                severity: Severity::Error,
+                offset: 0,
+                message: format!("Could not read the file: {}", error),
+            }]
+        });
+        let pass = passes(&issues, args.strict);
+        all_pass &= pass;
+
+        let errors = count(&issues, Severity::Error);
+        let warnings = count(&issues, Severity::Warning);
+        match args.format {
+            OutputFormat::Text => {
+                if issues.is_empty() {
+                    writeln!(out, "{}: ok", path.display())?;
+                } else {
+                    writeln!(
+                        out,
+                        "{}: {}, {}",
+                        path.display(),
+                        plural(errors, "error"),
+                        plural(warnings, "warning")
+                    )?;
+                }
+                for issue in &issues {
+                    writeln!(out, "  {}", issue)?;
+                }
+            }
+            OutputFormat::Json => {
+                let issues: Vec<String> = issues
+                    .iter()
+                    .map(|issue| {
+                        object(&[
+                            ("severity", string(&issue.severity.to_string())),
+                            ("offset", issue.offset.to_string()),
+                            ("message", string(&issue.message)),
+                        ])
+                    })
+                    .collect();
+                let report = object(&[
+                    ("file", string(&path.display().to_string())),
+                    ("valid", pass.to_string()),
+                    ("errors", errors.to_string()),
+                    ("warnings", warnings.to_string()),
+                    ("issues", format!("[{}]", issues.join(","))),
+                ]);
+                writeln!(out, "{}", report)?;
+            }
+        }
+    }
+
+    Ok(if all_pass {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(INVALID)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::run_args;
+
+    fn issue(severity: Severity) -> Issue {
+        Issue {
+            severity,
+            offset: 0,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn can_decide_whether_files_pass() {
+        assert!(passes(&[], true));
+        assert!(passes(&[issue(Severity::Warning)], false));
+        assert!(!passes(&[issue(Severity::Warning)], true));
+        assert!(!passes(&[issue(Severity::Error)], false));
+    }
+
+    #[test]
+    fn can_validate_files_as_text() {
+        let text =
+            run_args(&["validate", "test/hadley.sas7bdat", "test/missing.sas7bdat"]).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "test/hadley.sas7bdat: ok");
+        assert_eq!(lines[1], "test/missing.sas7bdat: 1 error, 0 warnings");
+        assert!(lines[2].starts_with("  error at byte 0: Could not read the file"));
+    }
+
+    #[test]
+    fn can_validate_files_as_json() {
+        let path = std::env::temp_dir().join(format!(
+            "sas-reader-2000-validate-short-{}.sas7bdat",
+            std::process::id()
+        ));
+        std::fs::write(&path, [0_u8; 100]).unwrap();
+        let text = run_args(&["validate", "--format", "json", path.to_str().unwrap()]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(text.contains(
+            "\"valid\":false,\"errors\":1,\"warnings\":0,\
+             \"issues\":[{\"severity\":\"error\",\"offset\":0,\"message\":\"File is 100 bytes"
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, Date32Array, Float64Array, StringArray, Time64MicrosecondArray,
+    TimestampMicrosecondArray,
+};
+use arrow::datatypes::{DataType, Field, Schema as ArrowSchema, SchemaRef, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::{FileWriter, StreamWriter};
+use arrow::record_batch::RecordBatch;
+
+use crate::sas::errors::Error;
+use crate::sas::{Column, ColumnType, FormatKind, Row, Schema, Value};
+use crate::traits::DatasetReader;
+use crate::util::time::{microseconds, unix_days, unix_microseconds};
+
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+impl From<ArrowError> for Error {
+    fn from(error: ArrowError) -> Self {
+        Error::Export(error.to_string())
+    }
+}
+
+/// The Arrow type for a column. Numeric columns with a date, time or
+/// datetime format become the matching temporal type.
+pub fn data_type(column: &Column) -> DataType {
+    match column.column_type {
+        ColumnType::Character => DataType::Utf8,
+        ColumnType::Numeric => match column.sas_format().kind() {
+            FormatKind::Date => DataType::Date32,
+            FormatKind::Time => DataType::Time64(TimeUnit::Microsecond),
+            FormatKind::DateTime => DataType::Timestamp(TimeUnit::Microsecond, None),
+            _ => DataType::Float64,
+        },
+    }
+}
+
+/// An Arrow field for a column, keeping the SAS label, format, type and
+/// length as field metadata.
+pub fn arrow_field(column: &Column) -> Field {
+    let sas_type = match column.column_type {
+        ColumnType::Numeric => "numeric",
+        ColumnType::Character => "character",
+    };
+    let mut metadata = HashMap::from([
+        ("sas.type".to_string(), sas_type.to_string()),
+        ("sas.length".to_string(), column.length.to_string()),
+    ]);
+    if !column.label.is_empty() {
+        metadata.insert("sas.label".to_string(), column.label.clone());
+    }
+    if !column.format.is_empty() {
+        metadata.insert("sas.format".to_string(), column.format.to_string());
+    }
+    Field::new(&column.name, data_type(column), true).with_metadata(metadata)
+}
+
+pub fn arrow_schema(schema: &Schema) -> ArrowSchema {
+    let fields: Vec<Field> = schema.columns.iter().map(arrow_field).collect();
+    let mut metadata = HashMap::from([("sas.name".to_string(), schema.name.clone())]);
+    if !schema.label.is_empty() {
+        metadata.insert("sas.label".to_string(), schema.label.clone());
+    }
+    ArrowSchema::new_with_metadata(fields, metadata)
+}
+
+/// Build a record batch from rows of the given schema. Missing values,
+/// including special missing values, become nulls.
+pub fn record_batch(schema: SchemaRef, rows: &[Row]) -> Result<RecordBatch, Error> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| build_array(field, index, rows))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+fn build_array(field: &Field, index: usize, rows: &[Row]) -> Result<ArrayRef, Error> {
+    if *field.data_type() == DataType::Utf8 {
+        let strings = rows
+            .iter()
+            .map(|row| match &row[index] {
+                Value::String(text) => Ok(Some(Cow::Borrowed(text.as_str()))),
+                Value::Bytes(bytes) => Ok(Some(String::from_utf8_lossy(bytes))),
+                Value::Missing(_) => Ok(None),
+                Value::Number(number) => Err(Error::Export(format!(
+                    "Number {} in character column {}",
+                    number,
+                    field.name()
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(Arc::new(StringArray::from_iter(strings)));
+    }
+
+    let numbers = rows
+        .iter()
+        .map(|row| match &row[index] {
+            Value::Number(number) if !number.is_nan() => Ok(Some(*number)),
+            Value::Number(_) | Value::Missing(_) => Ok(None),
+            Value::String(_) | Value::Bytes(_) => Err(Error::Export(format!(
+                "String {:?} in numeric column {}",
+                row[index].to_text().unwrap_or_default(),
+                field.name()
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let array: ArrayRef = match field.data_type() {
+        DataType::Date32 => Arc::new(Date32Array::from(convert(&numbers, field, unix_days)?)),
+        DataType::Time64(_) => Arc::new(Time64MicrosecondArray::from(convert(
+            &numbers,
+            field,
+            microseconds,
+        )?)),
+        DataType::Timestamp(_, _) => Arc::new(TimestampMicrosecondArray::from(convert(
+            &numbers,
+            field,
+            unix_microseconds,
+        )?)),
+        _ => Arc::new(Float64Array::from(numbers)),
+    };
+    Ok(array)
+}
+
+/// Converts SAS numbers to Arrow units, failing on values the unit cannot hold.
+fn convert<T>(
+    numbers: &[Option<f64>],
+    field: &Field,
+    unit: fn(f64) -> Option<T>,
+) -> Result<Vec<Option<T>>, Error> {
+    numbers
+        .iter()
+        .map(|number| match number {
+            Some(number) => unit(*number).map(Some).ok_or_else(|| {
+                Error::Export(format!(
+                    "Number {} in column {} is out of range for {}",
+                    number,
+                    field.name(),
+                    field.data_type()
+                ))
+            }),
+            None => Ok(None),
+        })
+        .collect()
+}
+
+/// Iterator over the remaining rows of a reader, in record batches of up to
+/// `batch_size` rows. Stops after the first error.
+pub struct RecordBatches<'a, D: DatasetReader> {
+    reader: &'a mut D,
+    schema: SchemaRef,
+    batch_size: usize,
+    done: bool,
+}
+
+impl<'a, D: DatasetReader> RecordBatches<'a, D> {
+    pub fn new(reader: &'a mut D, batch_size: usize) -> Self {
+        let schema = Arc::new(arrow_schema(reader.schema()));
+        RecordBatches {
+            reader,
+            schema,
+            batch_size: batch_size.max(1),
+            done: false,
+        }
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, Error> {
+        let mut rows = Vec::with_capacity(self.batch_size);
+        while rows.len() < self.batch_size {
+            match self.reader.read_row()? {
+                Some(row) => rows.push(row),
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        if rows.is_empty() {
+            return Ok(None);
+        }
+        record_batch(self.schema.clone(), &rows).map(Some)
+    }
+}
+
+impl<D: DatasetReader> Iterator for RecordBatches<'_, D> {
+    type Item = Result<RecordBatch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let batch = self.next_batch().transpose();
+        if matches!(batch, Some(Err(_))) {
+            self.done = true;
+        }
+        batch
+    }
+}
+
+/// Write the remaining rows of a reader as an Arrow IPC file.
+pub fn write_ipc_file<D: DatasetReader, W: Write>(
+    reader: &mut D,
+    sink: W,
+    batch_size: usize,
+) -> Result<W, Error> {
+    let batches = RecordBatches::new(reader, batch_size);
+    let mut writer = FileWriter::try_new(sink, &batches.schema())?;
+    for batch in batches {
+        writer.write(&batch?)?;
+    }
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+/// Write the remaining rows of a reader as an Arrow IPC stream, which can be
+/// read before it is complete, e.g. through a pipe.
+pub fn write_ipc_stream<D: DatasetReader, W: Write>(
+    reader: &mut D,
+    sink: W,
+    batch_size: usize,
+) -> Result<W, Error> {
+    let batches = RecordBatches::new(reader, batch_size);
+    let mut writer = StreamWriter::try_new(sink, &batches.schema())?;
+    for batch in batches {
+        writer.write(&batch?)?;
+    }
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arrow::array::Array;
+    use arrow::ipc::reader::{FileReader, StreamReader};
+
+    use super::*;
+    use crate::sas::{ColumnFormat, MissingValue, Sas7bdatReader, Sas7bdatWriter, WriterOptions};
+    use crate::util::time::SAS_TO_UNIX_SECONDS;
+
+    fn dated_file() -> Cursor<Vec<u8>> {
+        let mut visit = Column::numeric("visit");
+        visit.format = ColumnFormat::new("DATE", 9, 0);
+        visit.label = "Visit date".to_string();
+        let mut time = Column::numeric("time");
+        time.format = ColumnFormat::new("TIME", 8, 0);
+        let mut stamp = Column::numeric("stamp");
+        stamp.format = ColumnFormat::new("DATETIME", 20, 0);
+        let columns = vec![
+            Column::character("site", 4),
+            visit,
+            time,
+            stamp,
+            Column::numeric("dose"),
+        ];
+        let schema = Schema::new("visits", columns);
+
+        let mut writer =
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, WriterOptions::default()).unwrap();
+        for index in 0..5 {
+            writer
+                .write_row(&[
+                    Value::String(format!("S{}", index)),
+                    Value::Number(3653.0 + index as f64),
+                    Value::Number(3600.5),
+                    Value::Number(SAS_TO_UNIX_SECONDS as f64 + 1.0),
+                    Value::Number(index as f64 / 2.0),
+                ])
+                .unwrap();
+        }
+        writer
+            .write_row(&[
+                Value::Missing(MissingValue::System),
+                Value::Missing(MissingValue::System),
+                Value::Missing(MissingValue::Special('A')),
+                Value::Missing(MissingValue::System),
+                Value::Missing(MissingValue::System),
+            ])
+            .unwrap();
+        let mut sink = writer.finish().unwrap();
+        sink.set_position(0);
+        sink
+    }
+
+    #[test]
+    fn can_map_schema() {
+        let reader = Sas7bdatReader::new(dated_file()).unwrap();
+        let schema = arrow_schema(reader.schema());
+
+        let types: Vec<&DataType> = schema.fields().iter().map(|f| f.data_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                &DataType::Utf8,
+                &DataType::Date32,
+                &DataType::Time64(TimeUnit::Microsecond),
+                &DataType::Timestamp(TimeUnit::Microsecond, None),
+                &DataType::Float64,
+            ]
+        );
+        let visit = schema.field(1).metadata();
+        assert_eq!(visit["sas.label"], "Visit date");
+        assert_eq!(visit["sas.format"], "DATE9.");
+        assert_eq!(visit["sas.type"], "numeric");
+        assert_eq!(schema.metadata()["sas.name"], "visits");
+    }
+
+    #[test]
+    fn can_read_record_batches() {
+        let mut reader = Sas7bdatReader::new(dated_file()).unwrap();
+        let batches: Vec<RecordBatch> = reader.record_batches(4).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].num_rows(), 4);
+        assert_eq!(batches[1].num_rows(), 2);
+
+        let first = &batches[0];
+        let visits = first
+            .column(1)
+            .as_any()
+            .downcast_ref::<Date32Array>()
+            .unwrap();
+        assert_eq!(visits.value(0), 0);
+        assert_eq!(visits.value(3), 3);
+        let times = first
+            .column(2)
+            .as_any()
+            .downcast_ref::<Time64MicrosecondArray>()
+            .unwrap();
+        assert_eq!(times.value(0), 3_600_500_000);
+        let stamps = first
+            .column(3)
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(stamps.value(0), 1_000_000);
+
+        let last = &batches[1];
+        // Character missing values are stored as blanks, so read back empty
+        let sites = last
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(sites.value(1), "");
+        assert!((1..5).all(|column| last.column(column).is_null(1)));
+    }
+
+    #[test]
+    fn cannot_read_dates_out_of_range() {
+        let mut visit = Column::numeric("visit");
+        visit.format = ColumnFormat::new("DATE", 9, 0);
+        let schema = Schema::new("visits", vec![visit]);
+        let mut writer =
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, WriterOptions::default()).unwrap();
+        writer.write_row(&[Value::Number(1e12)]).unwrap();
+        let mut sink = writer.finish().unwrap();
+        sink.set_position(0);
+
+        let mut reader = Sas7bdatReader::new(sink).unwrap();
+        let error = reader.record_batches(4).next().unwrap().unwrap_err();
+        assert!(matches!(error, Error::Export(message) if message.contains("out of range")));
+    }
+
+    #[test]
+    fn can_round_trip_ipc_file() {
+        let mut reader = Sas7bdatReader::new(dated_file()).unwrap();
+        let bytes = write_ipc_file(&mut reader, Vec::new(), 4).unwrap();
+
+        let ipc = FileReader::try_new(Cursor::new(bytes), None).unwrap();
+        assert_eq!(ipc.schema().field(0).name(), "site");
+        let rows: usize = ipc.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 6);
+    }
+
+    #[test]
+    fn can_round_trip_ipc_stream() {
+        let mut reader = Sas7bdatReader::open("test/hadley.sas7bdat").unwrap();
+        let row_count = reader.properties().row_count;
+        let bytes = write_ipc_stream(&mut reader, Vec::new(), DEFAULT_BATCH_SIZE).unwrap();
+
+        let ipc = StreamReader::try_new(Cursor::new(bytes), None).unwrap();
+        assert_eq!(ipc.schema().fields().len(), 7);
+        let batches: Vec<RecordBatch> = ipc.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), row_count);
+    }
+}
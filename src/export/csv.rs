@@ -0,0 +1,401 @@
+use std::io::Write;
+
+use derive_builder::Builder;
+
+use crate::export::{date_format, number_format, DateStyle, NumberStyle};
+use crate::sas::errors::Error;
+use crate::sas::{Column, ColumnType, Encoding, MissingValue, SasFormat, Schema, Value};
+use crate::traits::DatasetReader;
+
+/// When fields are wrapped in quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// Only fields containing the delimiter, the quote or a line break.
+    Necessary,
+    Always,
+    /// Character fields are always quoted, numeric fields never.
+    NonNumeric,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderStyle {
+    Names,
+    /// Column labels, falling back to the name for unlabelled columns.
+    Labels,
+    None,
+}
+
+/// How special missing values such as `.A` are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialMissingStyle {
+    /// The same text as an ordinary missing value.
+    AsMissing,
+    /// The SAS notation, e.g. `.A` or `._`.
+    Dotted,
+    /// Just the letter, e.g. `A`.
+    Letter,
+}
+
+/// Options for writing CSV.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quote: char,
+    pub quote_style: QuoteStyle,
+    pub header: HeaderStyle,
+    /// Pad character fields back to the column width instead of trimming.
+    pub keep_trailing_blanks: bool,
+    pub missing: String,
+    pub special_missing: SpecialMissingStyle,
+    pub dates: DateStyle,
+    pub numbers: NumberStyle,
+    pub encoding: Encoding,
+    pub line_terminator: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            quote: '"',
+            quote_style: QuoteStyle::Necessary,
+            header: HeaderStyle::Names,
+            keep_trailing_blanks: false,
+            missing: String::new(),
+            special_missing: SpecialMissingStyle::AsMissing,
+            dates: DateStyle::Iso,
+            numbers: NumberStyle::Raw,
+            encoding: Encoding::Utf8,
+            line_terminator: "\n".to_string(),
+        }
+    }
+}
+
+/// Writes rows as CSV, one line at a time.
+pub struct CsvWriter<W: Write> {
+    sink: W,
+    schema: Schema,
+    options: CsvOptions,
+    formats: Vec<Option<SasFormat>>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Start a CSV file, writing the header line unless it is turned off.
+    pub fn new(sink: W, schema: Schema, options: CsvOptions) -> Result<Self, Error> {
+        let formats = schema
+            .columns
+            .iter()
+            .map(|column| {
+                date_format(column, options.dates)
+                    .or_else(|| number_format(column, options.numbers))
+            })
+            .collect();
+        let mut writer = CsvWriter {
+            sink,
+            schema,
+            options,
+            formats,
+        };
+
+        let names: Vec<&str> = match writer.options.header {
+            HeaderStyle::Names => writer
+                .schema
+                .columns
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect(),
+            HeaderStyle::Labels => writer
+                .schema
+                .columns
+                .iter()
+                .map(|c| {
+                    if c.label.is_empty() {
+                        &c.name
+                    } else {
+                        &c.label
+                    }
+                    .as_str()
+                })
+                .collect(),
+            HeaderStyle::None => return Ok(writer),
+        };
+        let fields: Vec<String> = names.iter().map(|name| writer.quote(name, false)).collect();
+        writer.write_line(&fields)?;
+        Ok(writer)
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+        if row.len() != self.schema.columns.len() {
+            return Err(Error::InvalidRow(format!(
+                "Expected {} values but got {}",
+                self.schema.columns.len(),
+                row.len()
+            )));
+        }
+
+        let fields: Vec<String> = self
+            .schema
+            .columns
+            .iter()
+            .zip(&self.formats)
+            .zip(row)
+            .map(|((column, format), value)| {
+                let text = self.render(column, format.as_ref(), value);
+                self.quote(&text, column.column_type == ColumnType::Numeric)
+            })
+            .collect();
+        self.write_line(&fields)
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    fn render(&self, column: &Column, format: Option<&SasFormat>, value: &Value) -> String {
+        match value {
+            Value::Missing(MissingValue::Special(tag)) => match self.options.special_missing {
+                SpecialMissingStyle::AsMissing => self.options.missing.clone(),
+                SpecialMissingStyle::Dotted => format!(".{}", tag),
+                SpecialMissingStyle::Letter => tag.to_string(),
+            },
+            Value::Missing(MissingValue::System) => match column.column_type {
+                ColumnType::Character => self.pad(column, ""),
+                ColumnType::Numeric => self.options.missing.clone(),
+            },
+            Value::Number(number) if number.is_nan() => self.options.missing.clone(),
+            Value::Number(number) => match format {
+                Some(format) => format.render(value).trim().to_string(),
+                None => number.to_string(),
+            },
+            Value::String(text) => self.pad(column, text),
+            Value::Bytes(bytes) => self.pad(column, &String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    fn pad(&self, column: &Column, text: &str) -> String {
+        if self.options.keep_trailing_blanks {
+            format!("{:<width$}", text, width = column.length)
+        } else {
+            text.trim_end().to_string()
+        }
+    }
+
+    fn quote(&self, text: &str, numeric: bool) -> String {
+        let quote = self.options.quote;
+        let needed = match self.options.quote_style {
+            QuoteStyle::Always => true,
+            QuoteStyle::Never => false,
+            QuoteStyle::NonNumeric if !numeric => true,
+            QuoteStyle::Necessary | QuoteStyle::NonNumeric => text.contains(|c: char| {
+                c == self.options.delimiter || c == quote || c == '\n' || c == '\r'
+            }),
+        };
+        if !needed {
+            return text.to_string();
+        }
+        let doubled = text.replace(quote, &format!("{}{}", quote, quote));
+        format!("{}{}{}", quote, doubled, quote)
+    }
+
+    fn write_line(&mut self, fields: &[String]) -> Result<(), Error> {
+        let mut line = fields.join(&self.options.delimiter.to_string());
+        line.push_str(&self.options.line_terminator);
+        let bytes = self.options.encoding.encode(&line).map_err(Error::Export)?;
+        self.sink.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+/// Stream the remaining rows of a reader as CSV.
+pub fn write_csv<D: DatasetReader, W: Write>(
+    reader: &mut D,
+    sink: W,
+    options: CsvOptions,
+) -> Result<W, Error> {
+    let mut writer = CsvWriter::new(sink, reader.schema().clone(), options)?;
+    for row in reader.rows() {
+        writer.write_row(&row?)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sas::{ColumnFormat, Sas7bdatReader};
+
+    fn schema() -> Schema {
+        let mut name = Column::character("name", 8);
+        name.label = "Full name".to_string();
+        let mut visit = Column::numeric("visit");
+        visit.format = ColumnFormat::new("DATE", 9, 0);
+        let mut stamp = Column::numeric("stamp");
+        stamp.format = ColumnFormat::new("DATETIME", 20, 0);
+        Schema::new("visits", vec![name, visit, stamp, Column::numeric("score")])
+    }
+
+    fn write(options: CsvOptions, rows: &[Vec<Value>]) -> String {
+        let mut writer = CsvWriter::new(Vec::new(), schema(), options).unwrap();
+        for row in rows {
+            writer.write_row(row).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    fn row(name: &str, score: Value) -> Vec<Value> {
+        vec![
+            Value::String(name.to_string()),
+            Value::Number(59.0),
+            Value::Number(86_400.0 + 3725.0),
+            score,
+        ]
+    }
+
+    #[test]
+    fn can_write_with_defaults() {
+        let text = write(
+            CsvOptions::default(),
+            &[
+                row("Ann", Value::Number(1.5)),
+                row("O\"Neil, B", Value::Number(-2.0)),
+            ],
+        );
+        assert_eq!(
+            text,
+            "name,visit,stamp,score\n\
+             Ann,1960-02-29,1960-01-02T01:02:05,1.5\n\
+             \"O\"\"Neil, B\",1960-02-29,1960-01-02T01:02:05,-2\n"
+        );
+    }
+
+    #[test]
+    fn can_write_labels_and_sas_formatted_dates() {
+        let options = CsvOptionsBuilder::default()
+            .header(HeaderStyle::Labels)
+            .delimiter(';')
+            .dates(DateStyle::Formatted)
+            .quote_style(QuoteStyle::NonNumeric)
+            .build()
+            .unwrap();
+        let text = write(options, &[row("Ann", Value::Number(3.0))]);
+        assert_eq!(
+            text,
+            "\"Full name\";\"visit\";\"stamp\";\"score\"\n\
+             \"Ann\";29FEB1960;02JAN1960:01:02:05;3\n"
+        );
+    }
+
+    #[test]
+    fn can_write_sas_formatted_numbers() {
+        let mut price = Column::numeric("price");
+        price.format = ColumnFormat::new("DOLLAR", 10, 2);
+        let mut share = Column::numeric("share");
+        share.format = ColumnFormat::new("PERCENT", 8, 1);
+        let mut count = Column::numeric("count");
+        count.format = ColumnFormat::new("COMMA", 9, 0);
+        let schema = Schema::new("sales", vec![price, share, count, Column::numeric("raw")]);
+        let row = [
+            Value::Number(1234.5),
+            Value::Number(0.125),
+            Value::Number(1234567.0),
+            Value::Number(1234.5),
+        ];
+
+        let options = CsvOptionsBuilder::default()
+            .header(HeaderStyle::None)
+            .numbers(NumberStyle::Formatted)
+            .build()
+            .unwrap();
+        let mut writer = CsvWriter::new(Vec::new(), schema.clone(), options).unwrap();
+        writer.write_row(&row).unwrap();
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(text, "\"$1,234.50\",12.5%,\"1,234,567\",1234.5\n");
+
+        let options = CsvOptionsBuilder::default()
+            .header(HeaderStyle::None)
+            .build()
+            .unwrap();
+        let mut writer = CsvWriter::new(Vec::new(), schema, options).unwrap();
+        writer.write_row(&row).unwrap();
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(text, "1234.5,0.125,1234567,1234.5\n");
+    }
+
+    #[test]
+    fn can_write_raw_dates_and_keep_blanks() {
+        let options = CsvOptionsBuilder::default()
+            .header(HeaderStyle::None)
+            .dates(DateStyle::Raw)
+            .keep_trailing_blanks(true)
+            .build()
+            .unwrap();
+        let text = write(options, &[row("Ann", Value::Number(3.0))]);
+        assert_eq!(text, "Ann     ,59,90125,3\n");
+    }
+
+    #[test]
+    fn can_configure_missing_values() {
+        let rows = [
+            row("a", Value::Missing(MissingValue::System)),
+            row("b", Value::Missing(MissingValue::Special('A'))),
+        ];
+        let options = |style: SpecialMissingStyle| {
+            CsvOptionsBuilder::default()
+                .header(HeaderStyle::None)
+                .missing("NA")
+                .special_missing(style)
+                .build()
+                .unwrap()
+        };
+
+        let lines = |text: String| -> Vec<String> {
+            text.lines()
+                .map(|line| line.rsplit(',').next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            lines(write(options(SpecialMissingStyle::AsMissing), &rows)),
+            ["NA", "NA"]
+        );
+        assert_eq!(
+            lines(write(options(SpecialMissingStyle::Dotted), &rows)),
+            ["NA", ".A"]
+        );
+        assert_eq!(
+            lines(write(options(SpecialMissingStyle::Letter), &rows)),
+            ["NA", "A"]
+        );
+    }
+
+    #[test]
+    fn can_encode_output() {
+        let options = CsvOptionsBuilder::default()
+            .header(HeaderStyle::None)
+            .encoding(Encoding::Windows1252)
+            .build()
+            .unwrap();
+        let mut writer = CsvWriter::new(Vec::new(), schema(), options).unwrap();
+        writer.write_row(&row("José", Value::Number(1.0))).unwrap();
+        let bytes = writer.finish().unwrap();
+        assert_eq!(&bytes[..5], b"Jos\xe9,");
+    }
+
+    #[test]
+    fn can_stream_file_to_csv() {
+        let mut reader = Sas7bdatReader::open("test/hadley.sas7bdat").unwrap();
+        let row_count = reader.properties().row_count;
+        let bytes = write_csv(&mut reader, Vec::new(), CsvOptions::default()).unwrap();
+
+        let text = String::from_utf8(bytes).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("id,workshop,gender,q1,q2,q3,q4"));
+        assert_eq!(lines.count(), row_count);
+    }
+}
@@ -0,0 +1,339 @@
+use std::io::Write;
+
+use derive_builder::Builder;
+
+use crate::export::{date_format, DateStyle};
+use crate::sas::errors::Error;
+use crate::sas::{ColumnType, MissingValue, SasFormat, Schema, Value};
+use crate::traits::DatasetReader;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// One object per line (NDJSON).
+    Lines,
+    /// A single array of objects.
+    Array,
+}
+
+/// How special missing values such as `.A` are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingTagStyle {
+    Null,
+    /// An object holding the tag, e.g. `{"missing":"A"}`.
+    Tagged,
+}
+
+/// Options for writing JSON.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct JsonOptions {
+    pub format: JsonFormat,
+    pub dates: DateStyle,
+    pub special_missing: MissingTagStyle,
+    /// Write an object describing the schema and file before the rows.
+    pub metadata: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions {
+            format: JsonFormat::Lines,
+            dates: DateStyle::Iso,
+            special_missing: MissingTagStyle::Null,
+            metadata: false,
+        }
+    }
+}
+
+/// Writes rows as JSON objects keyed by column name.
+pub struct JsonWriter<W: Write> {
+    sink: W,
+    schema: Schema,
+    options: JsonOptions,
+    formats: Vec<Option<SasFormat>>,
+    keys: Vec<String>,
+    records: usize,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(mut sink: W, schema: Schema, options: JsonOptions) -> Result<Self, Error> {
+        if options.format == JsonFormat::Array {
+            sink.write_all(b"[")?;
+        }
+        let formats = schema
+            .columns
+            .iter()
+            .map(|column| date_format(column, options.dates))
+            .collect();
+        let keys = schema
+            .columns
+            .iter()
+            .map(|column| string(&column.name))
+            .collect();
+        Ok(JsonWriter {
+            sink,
+            schema,
+            options,
+            formats,
+            keys,
+            records: 0,
+        })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Write the metadata object: the schema plus file-level facts such as
+    /// those from `DatasetReader::file_info`. Must come before any row.
+    pub fn write_metadata(&mut self, info: &[(String, String)]) -> Result<(), Error> {
+        if self.records > 0 {
+            return Err(Error::Export(
+                "Metadata must be written before the rows".to_string(),
+            ));
+        }
+        let columns: Vec<String> = self
+            .schema
+            .columns
+            .iter()
+            .map(|column| {
+                let sas_type = match column.column_type {
+                    ColumnType::Numeric => "numeric",
+                    ColumnType::Character => "character",
+                };
+                object(&[
+                    ("name", string(&column.name)),
+                    ("label", string(&column.label)),
+                    ("type", string(sas_type)),
+                    ("length", column.length.to_string()),
+                    ("format", string(&column.format.to_string())),
+                ])
+            })
+            .collect();
+        let info: Vec<(&str, String)> = info
+            .iter()
+            .map(|(name, value)| (name.as_str(), string(value)))
+            .collect();
+
+        let metadata = object(&[
+            ("name", string(&self.schema.name)),
+            ("label", string(&self.schema.label)),
+            ("columns", format!("[{}]", columns.join(","))),
+            ("file", object(&info)),
+        ]);
+        self.write_record(&object(&[("metadata", metadata)]))
+    }
+
+    pub fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+        if row.len() != self.schema.columns.len() {
+            return Err(Error::InvalidRow(format!(
+                "Expected {} values but got {}",
+                self.schema.columns.len(),
+                row.len()
+            )));
+        }
+
+        let fields: Vec<String> = self
+            .keys
+            .iter()
+            .zip(&self.formats)
+            .zip(row)
+            .map(|((key, format), value)| {
+                format!("{}:{}", key, self.render(format.as_ref(), value))
+            })
+            .collect();
+        self.write_record(&format!("{{{}}}", fields.join(",")))
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        if self.options.format == JsonFormat::Array {
+            let end: &[u8] = if self.records > 0 { b"\n]\n" } else { b"]\n" };
+            self.sink.write_all(end)?;
+        }
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    fn render(&self, format: Option<&SasFormat>, value: &Value) -> String {
+        match value {
+            Value::Missing(MissingValue::Special(tag)) => match self.options.special_missing {
+                MissingTagStyle::Null => "null".to_string(),
+                MissingTagStyle::Tagged => format!("{{\"missing\":\"{}\"}}", tag),
+            },
+            Value::Missing(MissingValue::System) => "null".to_string(),
+            Value::Number(number) if !number.is_finite() => "null".to_string(),
+            Value::Number(number) => match format {
+                Some(format) => string(format.render(value).trim()),
+                None => number.to_string(),
+            },
+            Value::String(text) => string(text),
+            Value::Bytes(bytes) => string(&String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    fn write_record(&mut self, record: &str) -> Result<(), Error> {
+        let separator: &[u8] = match (self.options.format, self.records) {
+            (JsonFormat::Lines, _) => b"",
+            (JsonFormat::Array, 0) => b"\n",
+            (JsonFormat::Array, _) => b",\n",
+        };
+        self.sink.write_all(separator)?;
+        self.sink.write_all(record.as_bytes())?;
+        if self.options.format == JsonFormat::Lines {
+            self.sink.write_all(b"\n")?;
+        }
+        self.records += 1;
+        Ok(())
+    }
+}
+
+/// Quote and escape a JSON string.
+pub fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Build an object from keys and already-encoded values.
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Stream the remaining rows of a reader as JSON.
+pub fn write_json<D: DatasetReader, W: Write>(
+    reader: &mut D,
+    sink: W,
+    options: JsonOptions,
+) -> Result<W, Error> {
+    let metadata = options.metadata;
+    let mut writer = JsonWriter::new(sink, reader.schema().clone(), options)?;
+    if metadata {
+        writer.write_metadata(&reader.file_info())?;
+    }
+    for row in reader.rows() {
+        writer.write_row(&row?)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sas::{Column, ColumnFormat, Sas7bdatReader};
+
+    fn schema() -> Schema {
+        let mut visit = Column::numeric("visit");
+        visit.format = ColumnFormat::new("DATE", 9, 0);
+        Schema::new(
+            "visits",
+            vec![
+                Column::character("name", 8),
+                visit,
+                Column::numeric("score"),
+            ],
+        )
+    }
+
+    fn rows() -> Vec<Vec<Value>> {
+        vec![
+            vec![
+                Value::String("Ann \"A\"".to_string()),
+                Value::Number(59.0),
+                Value::Number(1.5),
+            ],
+            vec![
+                Value::String("Bob\\".to_string()),
+                Value::Missing(MissingValue::System),
+                Value::Missing(MissingValue::Special('A')),
+            ],
+        ]
+    }
+
+    fn write(options: JsonOptions) -> String {
+        let mut writer = JsonWriter::new(Vec::new(), schema(), options).unwrap();
+        for row in rows() {
+            writer.write_row(&row).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn can_write_lines() {
+        assert_eq!(
+            write(JsonOptions::default()),
+            "{\"name\":\"Ann \\\"A\\\"\",\"visit\":\"1960-02-29\",\"score\":1.5}\n\
+             {\"name\":\"Bob\\\\\",\"visit\":null,\"score\":null}\n"
+        );
+    }
+
+    #[test]
+    fn can_write_array_with_tagged_missing_and_raw_dates() {
+        let options = JsonOptionsBuilder::default()
+            .format(JsonFormat::Array)
+            .dates(DateStyle::Raw)
+            .special_missing(MissingTagStyle::Tagged)
+            .build()
+            .unwrap();
+        assert_eq!(
+            write(options),
+            "[\n{\"name\":\"Ann \\\"A\\\"\",\"visit\":59,\"score\":1.5},\n\
+             {\"name\":\"Bob\\\\\",\"visit\":null,\"score\":{\"missing\":\"A\"}}\n]\n"
+        );
+    }
+
+    #[test]
+    fn can_write_empty_array() {
+        let options = JsonOptionsBuilder::default()
+            .format(JsonFormat::Array)
+            .build()
+            .unwrap();
+        let writer = JsonWriter::new(Vec::new(), schema(), options).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"[]\n");
+    }
+
+    #[test]
+    fn can_escape_strings() {
+        assert_eq!(string("a\tb\u{1}c"), "\"a\\tb\\u0001c\"");
+        assert_eq!(string("caf\u{e9}"), "\"caf\u{e9}\"");
+    }
+
+    #[test]
+    fn can_write_metadata_first() {
+        let mut reader = Sas7bdatReader::open("test/hadley.sas7bdat").unwrap();
+        let options = JsonOptionsBuilder::default()
+            .metadata(true)
+            .build()
+            .unwrap();
+        let bytes = write_json(&mut reader, Vec::new(), options).unwrap();
+
+        let text = String::from_utf8(bytes).unwrap();
+        let first = text.lines().next().unwrap();
+        assert!(first.starts_with("{\"metadata\":{\"name\":\"HADLEY\""));
+        assert!(first.contains("{\"name\":\"gender\",\"label\":\"\",\"type\":\"character\""));
+        assert!(first.contains("\"format\":\"sas7bdat\""));
+        assert_eq!(text.lines().count(), reader.properties().row_count + 1);
+    }
+
+    #[test]
+    fn cannot_write_metadata_after_rows() {
+        let mut writer = JsonWriter::new(Vec::new(), schema(), JsonOptions::default()).unwrap();
+        writer.write_row(&rows()[0]).unwrap();
+        assert!(writer.write_metadata(&[]).is_err());
+    }
+}
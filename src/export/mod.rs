@@ -0,0 +1,58 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod csv;
+pub mod json;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "polars")]
+pub mod polars;
+
+use crate::sas::{Column, ColumnType, FormatKind, SasFormat};
+
+/// How numeric columns with a date, time or datetime format are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// `1960-01-31`, `13:05:09` and `1960-01-31T13:05:09`.
+    Iso,
+    /// With the column's own SAS format, e.g. `31JAN1960`.
+    Formatted,
+    /// The underlying number of days or seconds.
+    Raw,
+}
+
+/// How numeric columns with other formats, such as `COMMA10.2` or `DOLLAR8.`,
+/// are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberStyle {
+    /// The number in full, e.g. `1234.5`.
+    Raw,
+    /// With the column's own SAS format, e.g. `$1,234.50`. Columns without
+    /// a format are still written in full.
+    Formatted,
+}
+
+/// The format used for a date, time or datetime column, if any.
+pub(crate) fn date_format(column: &Column, style: DateStyle) -> Option<SasFormat> {
+    if column.column_type != ColumnType::Numeric {
+        return None;
+    }
+    let format = column.sas_format();
+    match (style, format.kind()) {
+        (DateStyle::Raw, _) | (_, FormatKind::Numeric | FormatKind::Character) => None,
+        (DateStyle::Iso, kind) => SasFormat::iso(kind),
+        (DateStyle::Formatted, _) => Some(format),
+    }
+}
+
+/// The format used for a formatted numeric column that is not a date, time or
+/// datetime, if any.
+pub(crate) fn number_format(column: &Column, style: NumberStyle) -> Option<SasFormat> {
+    if column.column_type != ColumnType::Numeric || column.format.is_empty() {
+        return None;
+    }
+    let format = column.sas_format();
+    match (style, format.kind()) {
+        (NumberStyle::Formatted, FormatKind::Numeric) => Some(format),
+        _ => None,
+    }
+}
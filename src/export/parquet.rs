@@ -0,0 +1,215 @@
+use std::io::Write;
+
+use derive_builder::Builder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::format::KeyValue;
+
+use crate::export::arrow::{RecordBatches, DEFAULT_BATCH_SIZE};
+use crate::sas::errors::Error;
+use crate::sas::{ColumnType, Schema};
+use crate::traits::DatasetReader;
+
+impl From<ParquetError> for Error {
+    fn from(error: ParquetError) -> Self {
+        Error::Export(error.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParquetCodec {
+    Uncompressed,
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+impl ParquetCodec {
+    fn compression(self) -> Compression {
+        match self {
+            ParquetCodec::Uncompressed => Compression::UNCOMPRESSED,
+            ParquetCodec::Snappy => Compression::SNAPPY,
+            ParquetCodec::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCodec::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        }
+    }
+}
+
+impl std::str::FromStr for ParquetCodec {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "uncompressed" | "none" => Ok(ParquetCodec::Uncompressed),
+            "snappy" => Ok(ParquetCodec::Snappy),
+            "gzip" => Ok(ParquetCodec::Gzip),
+            "zstd" => Ok(ParquetCodec::Zstd),
+            _ => Err(format!(
+                "Unknown Parquet codec: {}. Expected snappy, zstd, gzip or uncompressed.",
+                text
+            )),
+        }
+    }
+}
+
+/// Options for writing a Parquet file.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct ParquetOptions {
+    /// Maximum number of rows in a row group.
+    pub row_group_size: usize,
+    /// Number of rows read from the dataset before each write.
+    pub batch_size: usize,
+    pub codec: ParquetCodec,
+    pub dictionary: bool,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        ParquetOptions {
+            row_group_size: 1024 * 1024,
+            batch_size: DEFAULT_BATCH_SIZE,
+            codec: ParquetCodec::Snappy,
+            dictionary: true,
+        }
+    }
+}
+
+/// SAS metadata to store in the Parquet footer, so that tools which ignore
+/// the embedded Arrow schema can still see labels and formats.
+pub fn key_value_metadata(schema: &Schema) -> Vec<KeyValue> {
+    let mut metadata = vec![KeyValue::new("sas.name".to_string(), schema.name.clone())];
+    if !schema.label.is_empty() {
+        metadata.push(KeyValue::new("sas.label".to_string(), schema.label.clone()));
+    }
+    for column in &schema.columns {
+        let key = |field: &str| format!("sas.column.{}.{}", column.name, field);
+        let sas_type = match column.column_type {
+            ColumnType::Numeric => "numeric",
+            ColumnType::Character => "character",
+        };
+        metadata.push(KeyValue::new(key("type"), sas_type.to_string()));
+        if !column.label.is_empty() {
+            metadata.push(KeyValue::new(key("label"), column.label.clone()));
+        }
+        if !column.format.is_empty() {
+            metadata.push(KeyValue::new(key("format"), column.format.to_string()));
+        }
+    }
+    metadata
+}
+
+/// Stream the remaining rows of a reader into a Parquet file. Only one
+/// batch of rows and the current row group are held in memory.
+pub fn write_parquet<D: DatasetReader, W: Write + Send>(
+    reader: &mut D,
+    sink: W,
+    options: &ParquetOptions,
+) -> Result<W, Error> {
+    let properties = WriterProperties::builder()
+        .set_max_row_group_size(options.row_group_size.max(1))
+        .set_compression(options.codec.compression())
+        .set_dictionary_enabled(options.dictionary)
+        .set_key_value_metadata(Some(key_value_metadata(reader.schema())))
+        .build();
+
+    let batches = RecordBatches::new(reader, options.batch_size);
+    let mut writer = ArrowWriter::try_new(sink, batches.schema(), Some(properties))?;
+    for batch in batches {
+        writer.write(&batch?)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use super::*;
+    use crate::sas::Sas7bdatReader;
+
+    const FILENAME: &str = "test/hadley.sas7bdat";
+
+    fn write_to_temp(name: &str, options: &ParquetOptions) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("sas-reader-2000-{}.parquet", name));
+        let mut reader = Sas7bdatReader::open(FILENAME).unwrap();
+        let sink = BufWriter::new(File::create(&path).unwrap());
+        write_parquet(&mut reader, sink, options).unwrap();
+        path
+    }
+
+    #[test]
+    fn can_parse_codec() {
+        assert_eq!("ZSTD".parse(), Ok(ParquetCodec::Zstd));
+        assert_eq!("none".parse(), Ok(ParquetCodec::Uncompressed));
+        assert!("lz4".parse::<ParquetCodec>().is_err());
+    }
+
+    #[test]
+    fn can_write_row_groups_with_codec() {
+        let options = ParquetOptionsBuilder::default()
+            .row_group_size(10_usize)
+            .batch_size(7_usize)
+            .codec(ParquetCodec::Zstd)
+            .dictionary(false)
+            .build()
+            .unwrap();
+        let path = write_to_temp("row-groups", &options);
+        let row_count = Sas7bdatReader::open(FILENAME)
+            .unwrap()
+            .properties()
+            .row_count;
+
+        let file = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = file.metadata();
+        assert_eq!(metadata.num_row_groups(), row_count.div_ceil(10));
+        assert_eq!(metadata.file_metadata().num_rows() as usize, row_count);
+        assert_eq!(
+            metadata.row_group(0).column(0).compression(),
+            Compression::ZSTD(ZstdLevel::default())
+        );
+
+        let key_values = metadata.file_metadata().key_value_metadata().unwrap();
+        let value = |key: &str| {
+            key_values
+                .iter()
+                .find(|kv| kv.key == key)
+                .and_then(|kv| kv.value.clone())
+        };
+        assert_eq!(value("sas.name"), Some("HADLEY".to_string()));
+        assert_eq!(
+            value("sas.column.gender.type"),
+            Some("character".to_string())
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn can_read_back_rows() {
+        let path = write_to_temp("read-back", &ParquetOptions::default());
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+
+        let schema = batches[0].schema();
+        assert_eq!(schema.field(0).name(), "id");
+        assert_eq!(schema.field(0).metadata()["sas.type"], "numeric");
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(
+            rows,
+            Sas7bdatReader::open(FILENAME)
+                .unwrap()
+                .properties()
+                .row_count
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
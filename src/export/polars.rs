@@ -0,0 +1,491 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use derive_builder::Builder;
+use polars::prelude::{
+    col, AnonymousScan, AnonymousScanArgs, Categories, DataFrame, DataType, Field, IntoColumn,
+    LazyFrame, NamedFrom, PolarsError, PolarsResult, ScanArgsAnonymous, Schema as PolarsSchema,
+    SchemaRef, Series, TimeUnit,
+};
+
+use crate::sas::errors::Error;
+use crate::sas::{Column, ColumnType, FormatKind, MissingValue, Sas7bdatReader, Schema, Value};
+use crate::traits::DatasetReader;
+use crate::util::time::{microseconds, unix_days, unix_microseconds};
+
+impl From<PolarsError> for Error {
+    fn from(error: PolarsError) -> Self {
+        Error::Export(error.to_string())
+    }
+}
+
+fn polars_error(error: Error) -> PolarsError {
+    PolarsError::ComputeError(error.to_string().into())
+}
+
+/// Options for reading a dataset into a Polars DataFrame.
+#[derive(Debug, Clone, Default, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct PolarsOptions {
+    /// Columns to read, in order. SAS names are matched case-insensitively.
+    pub columns: Option<Vec<String>>,
+    pub n_rows: Option<usize>,
+    /// Character columns with at most this many distinct values become
+    /// categorical.
+    pub categorical_threshold: Option<usize>,
+}
+
+/// The Polars type for a column, following the Arrow mapping.
+pub fn data_type(column: &Column) -> DataType {
+    match column.column_type {
+        ColumnType::Character => DataType::String,
+        ColumnType::Numeric => match column.sas_format().kind() {
+            FormatKind::Date => DataType::Date,
+            FormatKind::Time => DataType::Time,
+            FormatKind::DateTime => DataType::Datetime(TimeUnit::Microseconds, None),
+            _ => DataType::Float64,
+        },
+    }
+}
+
+pub fn polars_schema(schema: &Schema) -> PolarsSchema {
+    schema
+        .columns
+        .iter()
+        .map(|column| Field::new(column.name.as_str().into(), data_type(column)))
+        .collect()
+}
+
+fn categorical() -> DataType {
+    DataType::from_categories(Categories::global())
+}
+
+enum Cells {
+    Numbers(Vec<Option<f64>>),
+    Strings(Vec<Option<String>>),
+}
+
+impl Cells {
+    fn push(&mut self, column: &Column, value: Value) -> Result<(), Error> {
+        match (self, value) {
+            (Cells::Numbers(numbers), Value::Number(number)) => {
+                numbers.push(Some(number).filter(|n| !n.is_nan()))
+            }
+            (Cells::Numbers(numbers), Value::Missing(_)) => numbers.push(None),
+            (Cells::Strings(strings), Value::String(text)) => strings.push(Some(text)),
+            (Cells::Strings(strings), Value::Bytes(bytes)) => {
+                strings.push(Some(String::from_utf8_lossy(&bytes).into_owned()))
+            }
+            (Cells::Strings(strings), Value::Missing(_)) => strings.push(None),
+            (_, value) => {
+                return Err(Error::Export(format!(
+                    "Value {:?} does not match column {}",
+                    value, column.name
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn into_series(self, column: &Column) -> Result<Series, Error> {
+        let name = column.name.as_str().into();
+        let series = match (self, data_type(column)) {
+            (Cells::Strings(strings), _) => Series::new(name, strings),
+            (Cells::Numbers(numbers), DataType::Date) => {
+                let days = convert(&numbers, column, unix_days)?;
+                Series::new(name, days).cast(&DataType::Date)?
+            }
+            (Cells::Numbers(numbers), DataType::Time) => {
+                let nanoseconds = convert(&numbers, column, |seconds| {
+                    microseconds(seconds)?.checked_mul(1000)
+                })?;
+                Series::new(name, nanoseconds).cast(&DataType::Time)?
+            }
+            (Cells::Numbers(numbers), DataType::Datetime(unit, zone)) => {
+                let stamps = convert(&numbers, column, unix_microseconds)?;
+                Series::new(name, stamps).cast(&DataType::Datetime(unit, zone))?
+            }
+            (Cells::Numbers(numbers), _) => Series::new(name, numbers),
+        };
+        Ok(series)
+    }
+}
+
+/// Converts SAS numbers to Polars units, failing on values the unit cannot hold.
+fn convert<T>(
+    numbers: &[Option<f64>],
+    column: &Column,
+    unit: impl Fn(f64) -> Option<T>,
+) -> Result<Vec<Option<T>>, Error> {
+    numbers
+        .iter()
+        .map(|number| match number {
+            Some(number) => unit(*number).map(Some).ok_or_else(|| {
+                Error::Export(format!(
+                    "Number {} in column {} is out of range for {}",
+                    number,
+                    column.name,
+                    data_type(column)
+                ))
+            }),
+            None => Ok(None),
+        })
+        .collect()
+}
+
+/// Read the remaining rows of a reader into a DataFrame.
+pub fn dataframe<D: DatasetReader>(
+    reader: &mut D,
+    options: &PolarsOptions,
+) -> Result<DataFrame, Error> {
+    let schema = reader.schema().clone();
+    let indexes = match &options.columns {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                schema
+                    .column_index(name)
+                    .ok_or_else(|| Error::InvalidSchema(format!("No column named {}", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => (0..schema.columns.len()).collect(),
+    };
+    let mut cells: Vec<Cells> = indexes
+        .iter()
+        .map(|&i| match schema.columns[i].column_type {
+            ColumnType::Numeric => Cells::Numbers(Vec::new()),
+            ColumnType::Character => Cells::Strings(Vec::new()),
+        })
+        .collect();
+
+    let mut height = 0;
+    while options.n_rows.is_none_or(|n_rows| height < n_rows) {
+        let Some(mut row) = reader.read_row()? else {
+            break;
+        };
+        for (&index, cells) in indexes.iter().zip(&mut cells) {
+            let value = std::mem::replace(&mut row[index], Value::Missing(MissingValue::System));
+            cells.push(&schema.columns[index], value)?;
+        }
+        height += 1;
+    }
+
+    let columns = indexes
+        .iter()
+        .zip(cells)
+        .map(|(&index, cells)| {
+            let column = &schema.columns[index];
+            let low_cardinality = match (&cells, options.categorical_threshold) {
+                (Cells::Strings(strings), Some(threshold)) => {
+                    strings.iter().flatten().collect::<HashSet<_>>().len() <= threshold
+                }
+                _ => false,
+            };
+            let series = cells.into_series(column)?;
+            if low_cardinality {
+                Ok(series.cast(&categorical())?.into_column())
+            } else {
+                Ok(series.into_column())
+            }
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(DataFrame::new(height, columns)?)
+}
+
+/// Read a sas7bdat file into a DataFrame. Unselected columns are never
+/// decoded and reading stops after `n_rows`.
+pub fn read_sas7bdat<P: AsRef<Path>>(path: P, options: &PolarsOptions) -> Result<DataFrame, Error> {
+    let mut reader = Sas7bdatReader::open(path)?;
+    if let Some(columns) = &options.columns {
+        reader.select(columns)?;
+    }
+    let options = PolarsOptions {
+        columns: None,
+        ..options.clone()
+    };
+    dataframe(&mut reader, &options)
+}
+
+/// A lazy scan of a sas7bdat file. Polars pushes the projection and row
+/// limit of a query down into the reader.
+struct Sas7bdatScan {
+    path: PathBuf,
+    schema: SchemaRef,
+    /// The columns the scan was narrowed to, read when Polars pushes down
+    /// no projection of its own.
+    columns: Option<Vec<String>>,
+}
+
+impl AnonymousScan for Sas7bdatScan {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: AnonymousScanArgs) -> PolarsResult<DataFrame> {
+        let options = PolarsOptions {
+            columns: scan_opts
+                .with_columns
+                .map(|names| names.iter().map(|name| name.to_string()).collect())
+                .or_else(|| self.columns.clone()),
+            n_rows: scan_opts.n_rows,
+            categorical_threshold: None,
+        };
+        read_sas7bdat(&self.path, &options).map_err(polars_error)
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> PolarsResult<SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+}
+
+/// Lazily scan a sas7bdat file. With a categorical threshold the character
+/// columns of the first `n_rows` rows, or of every row if there is no limit,
+/// are read once up front to find those of low cardinality.
+pub fn scan_sas7bdat<P: AsRef<Path>>(path: P, options: &PolarsOptions) -> Result<LazyFrame, Error> {
+    let path = path.as_ref().to_path_buf();
+    let mut reader = Sas7bdatReader::open(&path)?;
+    if let Some(columns) = &options.columns {
+        reader.select(columns)?;
+    }
+    let schema = reader.schema().clone();
+
+    let categorical_columns: Vec<String> = match options.categorical_threshold {
+        Some(_) => {
+            let names: Vec<String> = schema
+                .columns
+                .iter()
+                .filter(|column| column.column_type == ColumnType::Character)
+                .map(|column| column.name.clone())
+                .collect();
+            let sample_options = PolarsOptions {
+                columns: Some(names),
+                n_rows: options.n_rows,
+                categorical_threshold: options.categorical_threshold,
+            };
+            dataframe(&mut reader, &sample_options)?
+                .columns()
+                .iter()
+                .filter(|column| column.dtype().is_categorical())
+                .map(|column| column.name().to_string())
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let scan = Sas7bdatScan {
+        path,
+        schema: Arc::new(polars_schema(&schema)),
+        columns: options.columns.clone(),
+    };
+    let args = ScanArgsAnonymous {
+        n_rows: options.n_rows,
+        name: "SAS7BDAT SCAN",
+        ..ScanArgsAnonymous::default()
+    };
+    let mut frame = LazyFrame::anonymous_scan(Arc::new(scan), args)?;
+    if !categorical_columns.is_empty() {
+        let casts: Vec<_> = categorical_columns
+            .iter()
+            .map(|name| col(name.as_str()).cast(categorical()))
+            .collect();
+        frame = frame.with_columns(casts);
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use polars::prelude::AnyValue;
+
+    use super::*;
+    use crate::sas::{ColumnFormat, Sas7bdatWriter, WriterOptions};
+
+    const FILENAME: &str = "test/hadley.sas7bdat";
+
+    #[test]
+    fn can_map_types_like_arrow() {
+        let mut visit = Column::numeric("visit");
+        visit.format = ColumnFormat::new("DATE", 9, 0);
+        let mut time = Column::numeric("time");
+        time.format = ColumnFormat::new("TIME", 8, 0);
+        let mut stamp = Column::numeric("stamp");
+        stamp.format = ColumnFormat::new("DATETIME", 20, 0);
+        let schema = Schema::new(
+            "visits",
+            vec![
+                Column::character("site", 4),
+                visit,
+                time,
+                stamp,
+                Column::numeric("dose"),
+            ],
+        );
+
+        let mut writer =
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, WriterOptions::default()).unwrap();
+        writer
+            .write_row(&[
+                Value::from("S1"),
+                Value::Number(3653.0),
+                Value::Number(3725.5),
+                Value::Number(86_400.0),
+                Value::Missing(MissingValue::Special('A')),
+            ])
+            .unwrap();
+        let mut reader = Sas7bdatReader::new(writer.finish().unwrap()).unwrap();
+        let frame = dataframe(&mut reader, &PolarsOptions::default()).unwrap();
+
+        let types: Vec<DataType> = frame.dtypes();
+        assert_eq!(
+            types,
+            vec![
+                DataType::String,
+                DataType::Date,
+                DataType::Time,
+                DataType::Datetime(TimeUnit::Microseconds, None),
+                DataType::Float64,
+            ]
+        );
+        let row = frame.get(0).unwrap();
+        assert_eq!(row[1], AnyValue::Date(0));
+        assert_eq!(row[2], AnyValue::Time(3_725_500_000_000));
+        assert_eq!(
+            row[3],
+            AnyValue::Datetime(-315_532_800_000_000, TimeUnit::Microseconds, None)
+        );
+        assert_eq!(row[4], AnyValue::Null);
+    }
+
+    #[test]
+    fn cannot_read_times_out_of_range() {
+        let mut time = Column::numeric("time");
+        time.format = ColumnFormat::new("TIME", 8, 0);
+        let schema = Schema::new("visits", vec![time]);
+        let mut writer =
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, WriterOptions::default()).unwrap();
+        writer.write_row(&[Value::Number(1e12)]).unwrap();
+        let mut reader = Sas7bdatReader::new(writer.finish().unwrap()).unwrap();
+
+        let error = dataframe(&mut reader, &PolarsOptions::default()).unwrap_err();
+        assert!(matches!(error, Error::Export(message) if message.contains("out of range")));
+    }
+
+    #[test]
+    fn can_read_selected_columns_and_rows() {
+        let options = PolarsOptionsBuilder::default()
+            .columns(vec!["Q1".to_string(), "gender".to_string()])
+            .n_rows(3_usize)
+            .build()
+            .unwrap();
+        let frame = read_sas7bdat(FILENAME, &options).unwrap();
+
+        assert_eq!(frame.shape(), (3, 2));
+        assert_eq!(frame.get_column_names(), ["q1", "gender"]);
+        assert!(read_sas7bdat(
+            FILENAME,
+            &PolarsOptionsBuilder::default()
+                .columns(vec!["missing".to_string()])
+                .build()
+                .unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn can_make_low_cardinality_strings_categorical() {
+        let options = PolarsOptionsBuilder::default()
+            .categorical_threshold(3_usize)
+            .build()
+            .unwrap();
+        let frame = read_sas7bdat(FILENAME, &options).unwrap();
+        assert!(frame.column("gender").unwrap().dtype().is_categorical());
+
+        let options = PolarsOptionsBuilder::default()
+            .categorical_threshold(2_usize)
+            .build()
+            .unwrap();
+        let frame = read_sas7bdat(FILENAME, &options).unwrap();
+        assert_eq!(frame.column("gender").unwrap().dtype(), &DataType::String);
+    }
+
+    #[test]
+    fn can_push_projection_and_limit_into_scan() {
+        let scan = Sas7bdatScan {
+            path: PathBuf::from(FILENAME),
+            schema: Arc::new(PolarsSchema::default()),
+            columns: None,
+        };
+        let frame = scan
+            .scan(AnonymousScanArgs {
+                n_rows: Some(2),
+                with_columns: Some(Arc::from(["q4".into()])),
+                schema: scan.schema.clone(),
+                output_schema: None,
+                predicate: None,
+            })
+            .unwrap();
+        assert_eq!(frame.shape(), (2, 1));
+    }
+
+    #[test]
+    fn can_scan_lazily() {
+        let options = PolarsOptionsBuilder::default()
+            .categorical_threshold(3_usize)
+            .build()
+            .unwrap();
+        let frame = scan_sas7bdat(FILENAME, &options)
+            .unwrap()
+            .select([col("gender"), col("id")])
+            .limit(5)
+            .collect()
+            .unwrap();
+
+        assert_eq!(frame.shape(), (5, 2));
+        assert!(frame.column("gender").unwrap().dtype().is_categorical());
+        let total = scan_sas7bdat(FILENAME, &PolarsOptions::default())
+            .unwrap()
+            .collect()
+            .unwrap();
+        assert_eq!(total.shape(), (8, 7));
+    }
+
+    #[test]
+    fn can_find_categorical_columns_within_row_limit() {
+        // The first three genders are all "f"
+        let options = PolarsOptionsBuilder::default()
+            .categorical_threshold(1_usize)
+            .n_rows(3_usize)
+            .build()
+            .unwrap();
+        let frame = scan_sas7bdat(FILENAME, &options)
+            .unwrap()
+            .collect()
+            .unwrap();
+
+        assert_eq!(frame.height(), 3);
+        assert!(frame.column("gender").unwrap().dtype().is_categorical());
+    }
+
+    #[test]
+    fn can_scan_selected_columns_without_a_projection() {
+        let options = PolarsOptionsBuilder::default()
+            .columns(vec!["id".to_string(), "gender".to_string()])
+            .build()
+            .unwrap();
+        let lazy = scan_sas7bdat(FILENAME, &options).unwrap();
+        let mut lazy_for_schema = lazy.clone();
+        let schema = lazy_for_schema.collect_schema().unwrap();
+        let frame = lazy.collect().unwrap();
+
+        assert_eq!(frame.shape(), (8, 2));
+        assert_eq!(frame.schema().as_ref(), schema.as_ref());
+    }
+}
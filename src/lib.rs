@@ -1,3 +1,4 @@
+pub mod export;
 pub mod sas;
 pub mod traits;
 pub mod sas_reader;
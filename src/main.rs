@@ -1,3 +1,7 @@
-fn main() {
-    println!("Hello, world!");
+mod cli;
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    cli::main()
 }
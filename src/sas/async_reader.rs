@@ -0,0 +1,239 @@
+use std::io::SeekFrom;
+
+use futures_util::stream::{self, Stream};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::sas::decoder::{Decoder, Metadata, RawRow};
+use crate::sas::errors::Error;
+use crate::sas::properties::SasProperties;
+use crate::sas::reader::ReaderOptions;
+use crate::sas::schema::{Row, Schema};
+use crate::sas::{Encoding, PageBinary, SasConstants, SasHeaderBinary};
+
+/// Reads the schema and rows of a sas7bdat file from an async source,
+/// without blocking the executor.
+pub struct AsyncSas7bdatReader<R: AsyncRead + AsyncSeek + Unpin> {
+    source: R,
+    decoder: Decoder,
+    next_page: usize,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncSas7bdatReader<R> {
+    pub async fn new(source: R) -> Result<Self, Error> {
+        AsyncSas7bdatReader::with_options(source, ReaderOptions::default()).await
+    }
+
+    pub async fn with_options(mut source: R, options: ReaderOptions) -> Result<Self, Error> {
+        let header = read_header(&mut source, options.encoding).await?;
+        let properties = SasProperties::from_header_with_encoding(&header, options.encoding)?;
+
+        let mut metadata = Metadata::default();
+        for index in 0..properties.page_count {
+            let page = read_page(&mut source, &properties, index).await?;
+            if metadata.add_page(&page, &properties.layout)? {
+                break;
+            }
+        }
+        Ok(AsyncSas7bdatReader {
+            source,
+            decoder: Decoder::new(header, metadata, options)?,
+            next_page: 0,
+        })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.decoder.schema
+    }
+
+    pub fn header(&self) -> &SasHeaderBinary {
+        &self.decoder.header
+    }
+
+    pub fn properties(&self) -> &SasProperties {
+        &self.decoder.properties
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Keep only the named columns, in the given order.
+    pub fn select<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), Error> {
+        self.decoder.select(names)
+    }
+
+    /// Read the next row, or `None` once every row has been read.
+    pub async fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        loop {
+            if self.decoder.is_finished() {
+                return Ok(None);
+            }
+            if let Some(row) = self.decoder.next_row()? {
+                return Ok(Some(row));
+            }
+            if self.next_page >= self.decoder.properties.page_count {
+                return Err(self.decoder.truncated(self.decoder.rows_read()));
+            }
+
+            let page =
+                read_page(&mut self.source, &self.decoder.properties, self.next_page).await?;
+            self.next_page += 1;
+            self.decoder.load_rows(&page)?;
+        }
+    }
+
+    /// Read the next row without decoding it.
+    pub async fn read_raw_row(&mut self) -> Result<Option<RawRow<'_>>, Error> {
+        while !self.decoder.is_finished() && !self.decoder.has_queued_rows() {
+            if self.next_page >= self.decoder.properties.page_count {
+                return Err(self.decoder.truncated(self.decoder.rows_read()));
+            }
+            let page =
+                read_page(&mut self.source, &self.decoder.properties, self.next_page).await?;
+            self.next_page += 1;
+            self.decoder.load_rows(&page)?;
+        }
+        Ok(self.decoder.next_raw_row())
+    }
+
+    /// The remaining rows as a stream, which ends after the first error.
+    pub fn rows(self) -> impl Stream<Item = Result<Row, Error>> {
+        stream::try_unfold(self, |mut reader| async move {
+            Ok(reader.read_row().await?.map(|row| (row, reader)))
+        })
+    }
+
+    /// The remaining rows in batches of up to `batch_size`.
+    pub fn batches(self, batch_size: usize) -> impl Stream<Item = Result<Vec<Row>, Error>> {
+        let batch_size = batch_size.max(1);
+        stream::try_unfold(self, move |mut reader| async move {
+            let mut batch = Vec::with_capacity(batch_size);
+            while batch.len() < batch_size {
+                match reader.read_row().await? {
+                    Some(row) => batch.push(row),
+                    None => break,
+                }
+            }
+            Ok((!batch.is_empty()).then_some((batch, reader)))
+        })
+    }
+
+    /// The remaining rows as Arrow record batches.
+    #[cfg(feature = "arrow")]
+    pub fn record_batches(
+        self,
+        batch_size: usize,
+    ) -> impl Stream<Item = Result<arrow::record_batch::RecordBatch, Error>> {
+        use futures_util::TryStreamExt;
+
+        let schema = std::sync::Arc::new(crate::export::arrow::arrow_schema(self.schema()));
+        self.batches(batch_size).and_then(move |rows| {
+            let schema = schema.clone();
+            async move { crate::export::arrow::record_batch(schema, &rows) }
+        })
+    }
+}
+
+/// Read the file header: the fixed part first, then the rest once its
+/// length is known.
+async fn read_header<R: AsyncRead + AsyncSeek + Unpin>(
+    source: &mut R,
+    encoding: Option<Encoding>,
+) -> Result<SasHeaderBinary, Error> {
+    let constants = SasConstants::new();
+    source.seek(SeekFrom::Start(0)).await?;
+
+    let mut bytes = vec![0_u8; constants.file_header_size_32_bit as usize];
+    source
+        .read_exact(&mut bytes)
+        .await
+        .map_err(|error| Error::InvalidHeader(format!("File is too short: {}", error)))?;
+
+    let properties =
+        SasProperties::from_header_with_encoding(&SasHeaderBinary::new(&bytes), encoding)?;
+    if properties.header_length > bytes.len() {
+        let mut rest = vec![0_u8; properties.header_length - bytes.len()];
+        source
+            .read_exact(&mut rest)
+            .await
+            .map_err(|error| Error::InvalidHeader(format!("File is too short: {}", error)))?;
+        bytes.extend(rest);
+    }
+    Ok(SasHeaderBinary::new(&bytes))
+}
+
+async fn read_page<R: AsyncRead + AsyncSeek + Unpin>(
+    source: &mut R,
+    properties: &SasProperties,
+    index: usize,
+) -> Result<PageBinary, Error> {
+    let offset = properties.page_offset(index)?;
+    source.seek(SeekFrom::Start(offset)).await?;
+
+    let mut bytes = vec![0_u8; properties.page_size];
+    source
+        .read_exact(&mut bytes)
+        .await
+        .map_err(|error| Error::InvalidPage(format!("Could not read page {}: {}", index, error)))?;
+    PageBinary::new(&bytes, &properties.layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::pin::pin;
+
+    use futures_util::TryStreamExt;
+
+    use super::*;
+    use crate::sas::Sas7bdatReader;
+    use crate::traits::DatasetReader;
+
+    const FILENAME: &str = "test/hadley.sas7bdat";
+
+    #[tokio::test]
+    async fn can_read_rows_like_the_blocking_reader() {
+        let file = tokio::fs::File::open(FILENAME).await.unwrap();
+        let reader = AsyncSas7bdatReader::new(file).await.unwrap();
+        assert_eq!(reader.schema().name, "HADLEY");
+
+        let rows: Vec<Row> = reader.rows().try_collect().await.unwrap();
+        let mut blocking = Sas7bdatReader::open(FILENAME).unwrap();
+        let expected: Vec<Row> = blocking.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows, expected);
+    }
+
+    #[tokio::test]
+    async fn can_stream_batches_of_selected_columns() {
+        let bytes = std::fs::read(FILENAME).unwrap();
+        let mut reader = AsyncSas7bdatReader::new(Cursor::new(bytes)).await.unwrap();
+        reader.select(&["id"]).unwrap();
+
+        let mut batches = pin!(reader.batches(3));
+        let mut sizes = Vec::new();
+        while let Some(batch) = batches.try_next().await.unwrap() {
+            sizes.push(batch.len());
+            assert_eq!(batch[0].len(), 1);
+        }
+        assert_eq!(sizes, vec![3, 3, 2]);
+    }
+
+    #[tokio::test]
+    async fn cannot_read_truncated_file() {
+        let bytes = std::fs::read(FILENAME).unwrap();
+        let truncated = Cursor::new(bytes[..70000].to_vec());
+        assert!(AsyncSas7bdatReader::new(truncated).await.is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[tokio::test]
+    async fn can_stream_record_batches() {
+        let bytes = std::fs::read(FILENAME).unwrap();
+        let reader = AsyncSas7bdatReader::new(Cursor::new(bytes)).await.unwrap();
+        let batches: Vec<_> = reader.record_batches(5).try_collect().await.unwrap();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].num_rows(), 3);
+        assert_eq!(batches[0].num_columns(), 7);
+    }
+}
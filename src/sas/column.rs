@@ -0,0 +1,245 @@
+use std::fmt;
+
+use derive_builder::Builder;
+
+use crate::sas::format::SasFormat;
+use crate::sas::Value;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColumnType {
+    Numeric,
+    Character,
+}
+
+impl ColumnType {
+    pub fn from_u8(value: u8) -> Result<Self, String> {
+        match value {
+            1 => Ok(ColumnType::Numeric),
+            2 => Ok(ColumnType::Character),
+            _ => Err(format!(
+                "Unknown column type code from binary: {}. Expected either 1 for numeric or 2 for character.",
+                value
+            )),
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            ColumnType::Numeric => 1,
+            ColumnType::Character => 2,
+        }
+    }
+}
+
+/// The format attached to a column, as stored in the dataset: a name such as
+/// `DOLLAR` plus the width and number of decimals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnFormat {
+    pub name: String,
+    pub width: u16,
+    pub decimals: u16,
+}
+
+impl ColumnFormat {
+    pub fn new(name: &str, width: u16, decimals: u16) -> Self {
+        ColumnFormat {
+            name: name.to_uppercase(),
+            width,
+            decimals,
+        }
+    }
+
+    /// Parse a format written the way it appears in SAS code, e.g.
+    /// `DOLLAR12.2`, `$CHAR10.`, `DATE9.`, `8.2` or `BEST.`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let body = text.strip_suffix('.').unwrap_or(text);
+        let (body, decimals) = match body.rsplit_once('.') {
+            Some((body, decimals)) => (body, decimals),
+            None => (body, ""),
+        };
+
+        let name_end = body
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_ascii_digit())
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(body.len());
+        let (name, width) = body.split_at(name_end);
+
+        let valid_name = name
+            .chars()
+            .enumerate()
+            .all(|(i, c)| c.is_ascii_alphanumeric() || c == '_' || (c == '$' && i == 0));
+        if !valid_name || (name.is_empty() && width.is_empty()) {
+            return Err(format!("Invalid SAS format: {}", text));
+        }
+
+        let width = match width {
+            "" => 0,
+            width => width
+                .parse()
+                .map_err(|_| format!("Invalid width in SAS format: {}", text))?,
+        };
+        let decimals = match decimals {
+            "" => 0,
+            decimals => decimals
+                .parse()
+                .map_err(|_| format!("Invalid decimals in SAS format: {}", text))?,
+        };
+
+        Ok(ColumnFormat::new(name, width, decimals))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.width == 0 && self.decimals == 0
+    }
+}
+
+impl fmt::Display for ColumnFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.width > 0 {
+            write!(f, "{}", self.width)?;
+        }
+        write!(f, ".")?;
+        if self.decimals > 0 {
+            write!(f, "{}", self.decimals)?;
+        }
+        Ok(())
+    }
+}
+
+/// Metadata describing a single column (variable) of a dataset.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into))]
+pub struct Column {
+    pub name: String,
+    #[builder(default)]
+    pub label: String,
+    pub column_type: ColumnType,
+    /// Storage width in bytes.
+    pub length: usize,
+    #[builder(default)]
+    pub format: ColumnFormat,
+}
+
+impl Column {
+    pub fn numeric(name: &str) -> Self {
+        Column {
+            name: name.to_string(),
+            label: String::new(),
+            column_type: ColumnType::Numeric,
+            length: 8,
+            format: ColumnFormat::default(),
+        }
+    }
+
+    pub fn character(name: &str, length: usize) -> Self {
+        Column {
+            name: name.to_string(),
+            label: String::new(),
+            column_type: ColumnType::Character,
+            length,
+            format: ColumnFormat::default(),
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.column_type == ColumnType::Numeric
+    }
+
+    pub fn sas_format(&self) -> SasFormat {
+        SasFormat::for_column(self)
+    }
+
+    /// Render a value the way SAS would print it with this column's format.
+    pub fn render(&self, value: &Value) -> String {
+        self.sas_format().render(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_type_from_u8() {
+        assert_eq!(ColumnType::from_u8(1), Ok(ColumnType::Numeric));
+        assert_eq!(ColumnType::from_u8(2), Ok(ColumnType::Character));
+        assert!(ColumnType::from_u8(3).is_err());
+        assert_eq!(ColumnType::Character.to_u8(), 2);
+    }
+
+    #[test]
+    fn can_parse_named_format() {
+        let format = ColumnFormat::parse("dollar12.2").unwrap();
+        assert_eq!(format, ColumnFormat::new("DOLLAR", 12, 2));
+    }
+
+    #[test]
+    fn can_parse_format_without_width() {
+        let format = ColumnFormat::parse("BEST.").unwrap();
+        assert_eq!(format, ColumnFormat::new("BEST", 0, 0));
+    }
+
+    #[test]
+    fn can_parse_unnamed_format() {
+        let format = ColumnFormat::parse("8.2").unwrap();
+        assert_eq!(format, ColumnFormat::new("", 8, 2));
+    }
+
+    #[test]
+    fn can_parse_character_format() {
+        let format = ColumnFormat::parse("$CHAR10.").unwrap();
+        assert_eq!(format, ColumnFormat::new("$CHAR", 10, 0));
+    }
+
+    #[test]
+    fn can_parse_format_with_digits_in_name() {
+        let format = ColumnFormat::parse("E8601DA10.").unwrap();
+        assert_eq!(format, ColumnFormat::new("E8601DA", 10, 0));
+
+        let format = ColumnFormat::parse("E8601DT.").unwrap();
+        assert_eq!(format, ColumnFormat::new("E8601DT", 0, 0));
+    }
+
+    #[test]
+    fn cannot_parse_invalid_format() {
+        assert!(ColumnFormat::parse("").is_err());
+        assert!(ColumnFormat::parse("DOL-LAR8.").is_err());
+    }
+
+    #[test]
+    fn can_display_column_format() {
+        assert_eq!(ColumnFormat::new("DOLLAR", 12, 2).to_string(), "DOLLAR12.2");
+        assert_eq!(ColumnFormat::new("DATE", 9, 0).to_string(), "DATE9.");
+        assert_eq!(ColumnFormat::new("BEST", 0, 0).to_string(), "BEST.");
+    }
+
+    #[test]
+    fn can_build_column() {
+        let column = ColumnBuilder::default()
+            .name("price")
+            .label("Unit price")
+            .column_type(ColumnType::Numeric)
+            .length(8_usize)
+            .format(ColumnFormat::new("DOLLAR", 10, 2))
+            .build()
+            .unwrap();
+
+        assert_eq!(column.name, "price");
+        assert_eq!(column.label, "Unit price");
+        assert!(column.is_numeric());
+        assert_eq!(column.render(&Value::Number(1234.5)), " $1,234.50");
+    }
+
+    #[test]
+    fn can_make_character_column() {
+        let column = Column::character("name", 20);
+        assert_eq!(column.column_type, ColumnType::Character);
+        assert_eq!(column.length, 20);
+        assert!(column.format.is_empty());
+    }
+}
@@ -1,10 +1,17 @@
 use crate::sas::errors::Error;
 
+/// Literals stored in the column text of compressed datasets.
+pub const RLE_SIGNATURE: &str = "SASYZCRL";
+pub const RDC_SIGNATURE: &str = "SASYZCR2";
+
+/// How a subheader or row is compressed. Pointers flag RDC rows the same
+/// way as RLE rows, so `Rdc` is only known from the compression literal.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Compression {
-    None = 0,
-    Truncated = 1,
-    Rle = 4,
+    None,
+    Truncated,
+    Rle,
+    Rdc,
 }
 
 impl Compression {
@@ -16,6 +23,15 @@ impl Compression {
             _ => Err(Error::InvalidCompression(value)),
         }
     }
+
+    /// The flag stored in a subheader pointer.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Truncated => 1,
+            Compression::Rle | Compression::Rdc => 4,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -46,6 +62,11 @@ mod tests {
         assert_eq!(compression1, compression2);
     }
 
+    #[test]
+    fn test_rdc_compression_has_the_rle_flag() {
+        assert_eq!(Compression::Rdc.to_u8(), Compression::Rle.to_u8());
+    }
+
     #[test]
     fn test_invalid_compression() {
         let compression = Compression::from_u8(2);
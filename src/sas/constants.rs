@@ -4,6 +4,9 @@ pub struct SasConstants {
     pub file_header_size_32_bit: u32,
     pub file_header_size_64_bit: u32,
     pub default_page_size: u32,
+    /// Largest header or page the readers allocate; bigger sizes are
+    /// treated as corrupt.
+    pub max_page_size: u32,
 }
 
 impl SasConstants {
@@ -19,6 +22,7 @@ impl SasConstants {
             file_header_size_32_bit: 1024,
             file_header_size_64_bit: 8192,
             default_page_size: 4096,
+            max_page_size: 64 * 1024 * 1024,
         }
     }
 }
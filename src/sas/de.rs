@@ -0,0 +1,553 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::value::StrDeserializer;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::sas::errors::Error;
+use crate::sas::{Column, MissingValue, SasFormat, Schema, Value};
+use crate::traits::DatasetReader;
+
+/// Name the cell deserializer recognises to hand over the missing value tag.
+const MAYBE_MISSING: &str = "$sas_reader_2000::MaybeMissing";
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error::Deserialize(message.to_string())
+    }
+}
+
+/// A field that keeps which missing value it held, e.g. `.A` as
+/// `MaybeMissing::Missing(MissingValue::Special('A'))`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaybeMissing<T> {
+    Value(T),
+    Missing(MissingValue),
+}
+
+impl<'de, T: de::Deserialize<'de>> de::Deserialize<'de> for MaybeMissing<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MaybeMissingVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: de::Deserialize<'de>> Visitor<'de> for MaybeMissingVisitor<T> {
+            type Value = MaybeMissing<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a value or a missing value")
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(MaybeMissing::Missing(MissingValue::System))
+            }
+
+            fn visit_char<E: de::Error>(self, tag: char) -> Result<Self::Value, E> {
+                MissingValue::special(tag)
+                    .map(MaybeMissing::Missing)
+                    .map_err(E::custom)
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error> {
+                T::deserialize(deserializer).map(MaybeMissing::Value)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(MAYBE_MISSING, MaybeMissingVisitor(PhantomData))
+    }
+}
+
+/// Deserializes one cell. Numeric cells with a date, time or datetime
+/// format read as ISO text when a string is asked for, so the `chrono`
+/// types parse them directly.
+struct CellDeserializer<'a> {
+    value: &'a Value,
+    column: &'a Column,
+}
+
+impl CellDeserializer<'_> {
+    fn error(&self, message: impl fmt::Display) -> Error {
+        Error::Deserialize(format!("Column {}: {}", self.column.name, message))
+    }
+
+    /// Blank character cells are missing in SAS.
+    fn is_missing(&self) -> bool {
+        match self.value {
+            Value::Missing(_) => true,
+            Value::Number(number) => number.is_nan(),
+            Value::String(text) => text.trim().is_empty(),
+            Value::Bytes(bytes) => bytes.iter().all(|&b| b == b' '),
+        }
+    }
+
+    fn number(&self) -> Result<f64, Error> {
+        match self.value {
+            Value::Number(number) if !number.is_nan() => Ok(*number),
+            Value::Number(_) | Value::Missing(_) => Err(self.error("missing value")),
+            Value::String(_) | Value::Bytes(_) => {
+                let text = self.value.to_text().unwrap_or_default();
+                text.trim()
+                    .parse()
+                    .map_err(|_| self.error(format!("{:?} is not a number", text)))
+            }
+        }
+    }
+
+    fn integer(&self) -> Result<i64, Error> {
+        let number = self.number()?;
+        if number.fract() != 0.0 || number.abs() > i64::MAX as f64 {
+            return Err(self.error(format!("{} is not an integer", number)));
+        }
+        Ok(number as i64)
+    }
+
+    fn text(&self) -> Result<String, Error> {
+        match self.value {
+            Value::String(text) => Ok(text.clone()),
+            Value::Bytes(_) => Err(self.error("not valid in the file's encoding")),
+            Value::Missing(_) => Err(self.error("missing value")),
+            Value::Number(number) => match SasFormat::iso(self.column.sas_format().kind()) {
+                Some(format) => Ok(format.render(self.value).trim().to_string()),
+                None => Ok(number.to_string()),
+            },
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident as $type:ty),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let integer = self.integer()?;
+                let value = <$type>::try_from(integer)
+                    .map_err(|_| self.error(format!("{} is out of range", integer)))?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for CellDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Number(number) if !number.is_nan() => visitor.visit_f64(*number),
+            Value::String(text) => visitor.visit_str(text),
+            Value::Bytes(bytes) => visitor.visit_bytes(bytes),
+            _ => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.number()? {
+            0.0 => visitor.visit_bool(false),
+            1.0 => visitor.visit_bool(true),
+            number => Err(self.error(format!("{} is not 0 or 1", number))),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8 as i8,
+        deserialize_i16 => visit_i16 as i16,
+        deserialize_i32 => visit_i32 as i32,
+        deserialize_i64 => visit_i64 as i64,
+        deserialize_u8 => visit_u8 as u8,
+        deserialize_u16 => visit_u16 as u16,
+        deserialize_u32 => visit_u32 as u32,
+        deserialize_u64 => visit_u64 as u64,
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f32(self.number()? as f32)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(self.number()?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let text = self.text()?;
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(self.error(format!("{:?} is not a single character", text))),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.text()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.text()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_missing() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match (name, self.value) {
+            (MAYBE_MISSING, Value::Missing(MissingValue::Special(tag))) => visitor.visit_char(*tag),
+            (MAYBE_MISSING, _) if self.is_missing() => visitor.visit_none(),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let text = self.text()?;
+        let variant: StrDeserializer<'_, Error> = text.trim().into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Deserializes a row as a struct or map keyed by column name, or as a
+/// sequence of cells. Struct fields match columns case-insensitively.
+struct RowDeserializer<'a> {
+    schema: &'a Schema,
+    row: &'a [Value],
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let entries = self
+            .schema
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| (column.name.as_str(), index))
+            .collect();
+        visitor.visit_map(RowAccess::new(self, entries))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let entries = fields
+            .iter()
+            .filter_map(|field| self.schema.column_index(field).map(|index| (*field, index)))
+            .collect();
+        visitor.visit_map(RowAccess::new(self, entries))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(RowAccess::new(self, Vec::new()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct enum identifier ignored_any
+    }
+}
+
+/// Walks either the chosen (key, column index) entries or every cell.
+struct RowAccess<'a> {
+    row: RowDeserializer<'a>,
+    entries: Vec<(&'a str, usize)>,
+    position: usize,
+}
+
+impl<'a> RowAccess<'a> {
+    fn new(row: RowDeserializer<'a>, entries: Vec<(&'a str, usize)>) -> Self {
+        RowAccess {
+            row,
+            entries,
+            position: 0,
+        }
+    }
+
+    fn cell(&self, index: usize) -> CellDeserializer<'a> {
+        CellDeserializer {
+            value: &self.row.row[index],
+            column: &self.row.schema.columns[index],
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for RowAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.get(self.position) {
+            Some((key, _)) => seed.deserialize(key.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (_, index) = self.entries[self.position];
+        self.position += 1;
+        seed.deserialize(self.cell(index))
+    }
+}
+
+impl<'de> SeqAccess<'de> for RowAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.position >= self.row.row.len() {
+            return Ok(None);
+        }
+        let cell = self.cell(self.position);
+        self.position += 1;
+        seed.deserialize(cell).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.row.len() - self.position)
+    }
+}
+
+/// Deserialize a row of the given schema.
+pub fn from_row<T: DeserializeOwned>(schema: &Schema, row: &[Value]) -> Result<T, Error> {
+    T::deserialize(RowDeserializer { schema, row })
+}
+
+/// Iterator over the remaining rows of a reader, deserialized as `T`.
+/// Stops after the first read error; a row that does not deserialize is
+/// reported with its number and reading continues.
+pub struct DeserializeRows<'a, D: DatasetReader, T> {
+    reader: &'a mut D,
+    row_number: usize,
+    failed: bool,
+    target: PhantomData<T>,
+}
+
+impl<'a, D: DatasetReader, T> DeserializeRows<'a, D, T> {
+    pub fn new(reader: &'a mut D) -> Self {
+        DeserializeRows {
+            reader,
+            row_number: 0,
+            failed: false,
+            target: PhantomData,
+        }
+    }
+}
+
+impl<D: DatasetReader, T: DeserializeOwned> Iterator for DeserializeRows<'_, D, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let row = match self.reader.read_row() {
+            Ok(row) => row?,
+            Err(error) => {
+                self.failed = true;
+                return Some(Err(error));
+            }
+        };
+        self.row_number += 1;
+        Some(
+            from_row(self.reader.schema(), &row).map_err(|error| match error {
+                Error::Deserialize(message) => {
+                    Error::Deserialize(format!("Row {}: {}", self.row_number, message))
+                }
+                error => error,
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::sas::{ColumnFormat, Sas7bdatReader};
+
+    fn schema() -> Schema {
+        let mut visit = Column::numeric("VISIT");
+        visit.format = ColumnFormat::new("DATE", 9, 0);
+        let mut time = Column::numeric("Time");
+        time.format = ColumnFormat::new("TIME", 8, 0);
+        let mut stamp = Column::numeric("stamp");
+        stamp.format = ColumnFormat::new("DATETIME", 20, 0);
+        Schema::new(
+            "visits",
+            vec![
+                Column::character("Name", 8),
+                visit,
+                time,
+                stamp,
+                Column::numeric("dose"),
+                Column::numeric("count"),
+            ],
+        )
+    }
+
+    fn row(dose: Value) -> Vec<Value> {
+        vec![
+            Value::from("Ann"),
+            Value::Number(59.0),
+            Value::Number(3725.0),
+            Value::Number(86_400.0 + 61.0),
+            dose,
+            Value::Number(3.0),
+        ]
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Visit {
+        name: String,
+        visit: NaiveDate,
+        time: NaiveTime,
+        stamp: NaiveDateTime,
+        dose: Option<f64>,
+        count: u8,
+        extra: Option<String>,
+    }
+
+    #[test]
+    fn can_deserialize_struct_case_insensitively() {
+        let visit: Visit = from_row(&schema(), &row(Value::Number(2.5))).unwrap();
+        assert_eq!(
+            visit,
+            Visit {
+                name: "Ann".to_string(),
+                visit: NaiveDate::from_ymd_opt(1960, 2, 29).unwrap(),
+                time: NaiveTime::from_hms_opt(1, 2, 5).unwrap(),
+                stamp: NaiveDate::from_ymd_opt(1960, 1, 2)
+                    .unwrap()
+                    .and_hms_opt(0, 1, 1)
+                    .unwrap(),
+                dose: Some(2.5),
+                count: 3,
+                extra: None,
+            }
+        );
+
+        let missing = row(Value::Missing(MissingValue::Special('A')));
+        let visit: Visit = from_row(&schema(), &missing).unwrap();
+        assert_eq!(visit.dose, None);
+    }
+
+    #[test]
+    fn can_keep_special_missing_tags() {
+        #[derive(Deserialize)]
+        struct Dose {
+            dose: MaybeMissing<f64>,
+        }
+
+        let dose = |value: Value| from_row::<Dose>(&schema(), &row(value)).unwrap().dose;
+        assert_eq!(dose(Value::Number(1.0)), MaybeMissing::Value(1.0));
+        assert_eq!(
+            dose(Value::Missing(MissingValue::System)),
+            MaybeMissing::Missing(MissingValue::System)
+        );
+        assert_eq!(
+            dose(Value::Missing(MissingValue::Special('Z'))),
+            MaybeMissing::Missing(MissingValue::Special('Z'))
+        );
+    }
+
+    #[test]
+    fn can_deserialize_maps_tuples_and_enums() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Name {
+            Ann,
+            Bob,
+        }
+
+        let map: HashMap<String, Option<String>> =
+            from_row(&schema(), &row(Value::Number(1.0))).unwrap();
+        assert_eq!(map["VISIT"], Some("1960-02-29".to_string()));
+
+        let tuple: (Name, f64) = from_row(&schema(), &row(Value::Number(1.0))).unwrap();
+        assert_eq!(tuple, (Name::Ann, 59.0));
+    }
+
+    #[test]
+    fn cannot_deserialize_mismatched_cells() {
+        #[derive(Debug, Deserialize)]
+        struct Dose {
+            #[allow(dead_code)]
+            dose: i32,
+        }
+
+        let error = from_row::<Dose>(&schema(), &row(Value::Number(1.5))).unwrap_err();
+        assert_eq!(
+            error,
+            Error::Deserialize("Column dose: 1.5 is not an integer".to_string())
+        );
+        assert!(from_row::<Dose>(&schema(), &row(Value::Missing(MissingValue::System))).is_err());
+    }
+
+    #[test]
+    fn can_deserialize_rows_from_reader() {
+        #[derive(Debug, Deserialize)]
+        struct Response {
+            id: u32,
+            gender: Option<char>,
+            q4: Option<f64>,
+        }
+
+        let mut reader = Sas7bdatReader::open("test/hadley.sas7bdat").unwrap();
+        let responses: Vec<Response> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(responses.len(), 8);
+        assert_eq!(responses[0].id, 1);
+        assert_eq!(responses[0].gender, Some('f'));
+        assert_eq!(responses[3].gender, None);
+        assert!(responses.iter().all(|response| response.q4.is_some()));
+    }
+}
@@ -0,0 +1,625 @@
+use std::collections::VecDeque;
+
+use chrono::NaiveDateTime;
+
+use crate::sas::compression::{RDC_SIGNATURE, RLE_SIGNATURE};
+use crate::sas::errors::Error;
+use crate::sas::layout::Layout;
+use crate::sas::numeric::{decode_numeric, gather_numeric, gather_numeric_scalar, NumericColumn};
+use crate::sas::properties::SasProperties;
+use crate::sas::reader::{InvalidBytes, ReaderOptions};
+use crate::sas::schema::{Row, Schema};
+use crate::sas::subheader::{SubheaderSignature, TextRef};
+use crate::sas::{rdc, rle};
+use crate::sas::{
+    Column, ColumnFormat, ColumnType, Compression, Endianness, PageBinary, PageType,
+    SasHeaderBinary, Value,
+};
+
+/// Where a column's cells sit within a row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ColumnSpan {
+    offset: usize,
+    length: usize,
+    column_type: ColumnType,
+}
+
+/// The raw metadata subheaders gathered from the first pages of a file.
+#[derive(Default)]
+pub(crate) struct Metadata {
+    subheaders: Vec<(SubheaderSignature, Vec<u8>)>,
+    has_row_size: bool,
+    has_column_size: bool,
+}
+
+impl Metadata {
+    /// Collect the metadata subheaders of a page. Returns true once no more
+    /// pages are needed: this page holds rows and both size subheaders
+    /// have been seen.
+    pub fn add_page(&mut self, page: &PageBinary, layout: &Layout) -> Result<bool, Error> {
+        let page_type = page.page_type();
+        let mut has_rows = page_type.has_rows();
+
+        if page_type.has_subheaders() {
+            for pointer in page.pointers(layout)? {
+                if pointer.is_empty() {
+                    continue;
+                }
+                if pointer.compression != Compression::None {
+                    has_rows = true;
+                    continue;
+                }
+                let bytes = page.subheader(&pointer)?;
+                let signature = SubheaderSignature::read(bytes, layout)?;
+                if pointer.is_compressed_data && !signature.is_recognized() {
+                    has_rows = true;
+                    continue;
+                }
+                match signature {
+                    SubheaderSignature::RowSize => self.has_row_size = true,
+                    SubheaderSignature::ColumnSize => self.has_column_size = true,
+                    _ => {}
+                }
+                self.subheaders.push((signature, bytes.to_vec()));
+            }
+        }
+
+        Ok(has_rows && self.has_row_size && self.has_column_size)
+    }
+}
+
+/// A row on a page, before any decompression.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum RowSlot<'a> {
+    Stored(&'a [u8]),
+    Compressed(&'a [u8]),
+}
+
+/// A row as stored, with its cells left undecoded. Character cells keep
+/// their trailing blanks and any bytes that are not valid in the encoding.
+#[derive(Debug, Clone, Copy)]
+pub struct RawRow<'a> {
+    bytes: &'a [u8],
+    spans: &'a [ColumnSpan],
+    endianness: Endianness,
+}
+
+impl<'a> RawRow<'a> {
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The whole row, every column included whatever was selected.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The bytes of a cell exactly as stored. Numeric cells are the stored
+    /// width of the float, in the file's byte order.
+    pub fn cell(&self, index: usize) -> Option<&'a [u8]> {
+        let span = self.spans.get(index)?;
+        self.bytes.get(span.offset..span.offset + span.length)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        (0..self.len()).filter_map(|index| self.cell(index))
+    }
+
+    /// Decode the numbers but keep each character cell whole as
+    /// [`Value::Bytes`], so the row can be written back unchanged.
+    pub fn to_row(&self) -> Result<Row, Error> {
+        self.spans
+            .iter()
+            .map(|span| {
+                let cell = self
+                    .bytes
+                    .get(span.offset..span.offset + span.length)
+                    .ok_or(Error::OutOfBounds(span.offset, span.length))?;
+                match span.column_type {
+                    ColumnType::Numeric => decode_numeric(cell, self.endianness),
+                    ColumnType::Character => Ok(Value::Bytes(cell.to_vec())),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Turns pages into rows once the metadata is known. It does no I/O, so
+/// readers over any kind of source can share it.
+pub(crate) struct Decoder {
+    pub header: SasHeaderBinary,
+    pub properties: SasProperties,
+    pub schema: Schema,
+    spans: Vec<ColumnSpan>,
+    compression_literal: String,
+    invalid_bytes: InvalidBytes,
+    pending_rows: VecDeque<Vec<u8>>,
+    raw_row: Vec<u8>,
+    rows_read: usize,
+}
+
+impl Decoder {
+    pub fn new(
+        header: SasHeaderBinary,
+        metadata: Metadata,
+        options: ReaderOptions,
+    ) -> Result<Self, Error> {
+        let properties = SasProperties::from_header_with_encoding(&header, options.encoding)?;
+        let mut decoder = Decoder {
+            header,
+            properties,
+            schema: Schema::new("", Vec::new()),
+            spans: Vec::new(),
+            compression_literal: String::new(),
+            invalid_bytes: options.invalid_bytes,
+            pending_rows: VecDeque::new(),
+            raw_row: Vec::new(),
+            rows_read: 0,
+        };
+        decoder.parse_metadata(metadata)?;
+        Ok(decoder)
+    }
+
+    /// Keep only the named columns, in the given order, so that the other
+    /// cells of each row are never decoded.
+    pub fn select<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), Error> {
+        let indexes = names
+            .iter()
+            .map(|name| {
+                self.schema.column_index(name.as_ref()).ok_or_else(|| {
+                    Error::InvalidSchema(format!("No column named {}", name.as_ref()))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.spans = indexes.iter().map(|&i| self.spans[i]).collect();
+        self.schema.columns = indexes
+            .iter()
+            .map(|&i| self.schema.columns[i].clone())
+            .collect();
+        Ok(())
+    }
+
+    /// Name, offset and length of each character column within a row.
+    pub fn character_spans(&self) -> Vec<(String, usize, usize)> {
+        self.schema
+            .columns
+            .iter()
+            .zip(&self.spans)
+            .filter(|(_, span)| span.column_type == ColumnType::Character)
+            .map(|(column, span)| (column.name.clone(), span.offset, span.length))
+            .collect()
+    }
+
+    fn layout(&self) -> Layout {
+        self.properties.layout
+    }
+
+    /// Whether every row the file declares has been handed out.
+    pub fn is_finished(&self) -> bool {
+        self.rows_read >= self.properties.row_count
+    }
+
+    /// Decode the next queued row, if any page loaded so far still has one.
+    pub fn next_row(&mut self) -> Result<Option<Row>, Error> {
+        if self.is_finished() {
+            return Ok(None);
+        }
+        match self.pending_rows.pop_front() {
+            Some(bytes) => {
+                self.rows_read += 1;
+                self.decode_row(&bytes).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Whether rows from the pages loaded so far are still queued.
+    pub fn has_queued_rows(&self) -> bool {
+        !self.pending_rows.is_empty()
+    }
+
+    /// Hand out the next queued row without decoding it.
+    pub fn next_raw_row(&mut self) -> Option<RawRow<'_>> {
+        if self.is_finished() {
+            return None;
+        }
+        self.raw_row = self.pending_rows.pop_front()?;
+        self.rows_read += 1;
+        Some(RawRow {
+            bytes: &self.raw_row,
+            spans: &self.spans,
+            endianness: self.properties.endianness(),
+        })
+    }
+
+    pub fn rows_read(&self) -> usize {
+        self.rows_read
+    }
+
+    /// The error for a file whose pages run out before its rows do.
+    pub fn truncated(&self, rows: usize) -> Error {
+        Error::InvalidPage(format!(
+            "Expected {} rows but the file ends after {}",
+            self.properties.row_count, rows
+        ))
+    }
+
+    fn parse_metadata(&mut self, metadata: Metadata) -> Result<(), Error> {
+        let layout = self.layout();
+        let int_len = layout.int_len();
+        let signature_len = layout.signature_len();
+
+        // Other subheaders refer into the text subheaders, so gather them first.
+        let texts: Vec<&[u8]> = metadata
+            .subheaders
+            .iter()
+            .filter(|(signature, _)| *signature == SubheaderSignature::ColumnText)
+            .map(|(_, bytes)| &bytes[signature_len.min(bytes.len())..])
+            .collect();
+        let encoding = self.properties.encoding;
+        let text = |text_ref: TextRef| -> Result<String, Error> {
+            if text_ref.is_empty() {
+                return Ok(String::new());
+            }
+            let blob = texts.get(text_ref.index as usize).ok_or_else(|| {
+                Error::InvalidSubheader(format!("No column text subheader {}", text_ref.index))
+            })?;
+            let start = text_ref.offset as usize;
+            let bytes = blob
+                .get(start..start + text_ref.length as usize)
+                .ok_or(Error::OutOfBounds(start, text_ref.length as usize))?;
+            Ok(encoding.decode(trim_padding(bytes, encoding.blank())))
+        };
+
+        let mut column_count = None;
+        let mut names = Vec::new();
+        let mut spans = Vec::new();
+        let mut formats = Vec::new();
+
+        for (signature, bytes) in &metadata.subheaders {
+            let len = bytes.len();
+            match signature {
+                SubheaderSignature::RowSize => {
+                    if len < if layout.u64 { 250 } else { 190 } {
+                        return Err(Error::InvalidSubheader(format!(
+                            "Row size subheader is only {} bytes",
+                            len
+                        )));
+                    }
+                    let properties = &mut self.properties;
+                    properties.row_length = layout.read_int(bytes, 5 * int_len)? as usize;
+                    properties.row_count = layout.read_int(bytes, 6 * int_len)? as usize;
+                    properties.mix_page_row_count = layout.read_int(bytes, 15 * int_len)? as usize;
+                    properties.file_label = text(TextRef::read(bytes, len - 130, &layout)?)?;
+                    self.compression_literal = text(TextRef::read(bytes, len - 118, &layout)?)?;
+                }
+                SubheaderSignature::ColumnSize => {
+                    column_count = Some(layout.read_int(bytes, int_len)? as usize);
+                }
+                SubheaderSignature::ColumnName => {
+                    check_remainder(bytes, &layout)?;
+                    let count = len.saturating_sub(12 + 2 * signature_len) / 8;
+                    for i in 0..count {
+                        let offset = signature_len + 8 + i * 8;
+                        names.push(TextRef::read(bytes, offset, &layout)?);
+                    }
+                }
+                SubheaderSignature::ColumnAttributes => {
+                    check_remainder(bytes, &layout)?;
+                    let entry_len = int_len + 8;
+                    let count = len.saturating_sub(12 + 2 * signature_len) / entry_len;
+                    for i in 0..count {
+                        let offset = signature_len + 8 + i * entry_len;
+                        let type_code = *bytes
+                            .get(offset + int_len + 6)
+                            .ok_or(Error::OutOfBounds(offset + int_len + 6, 1))?;
+                        spans.push(ColumnSpan {
+                            offset: layout.read_int(bytes, offset)? as usize,
+                            length: layout.read_u32(bytes, offset + int_len)? as usize,
+                            column_type: ColumnType::from_u8(type_code)
+                                .map_err(Error::InvalidSubheader)?,
+                        });
+                    }
+                }
+                SubheaderSignature::ColumnFormat => {
+                    let (format_offset, label_offset) =
+                        if layout.u64 { (46, 52) } else { (34, 40) };
+                    let format_name = text(TextRef::read(bytes, format_offset, &layout)?)?;
+                    let format = ColumnFormat::new(
+                        &format_name,
+                        layout.read_u16(bytes, 3 * int_len)?,
+                        layout.read_u16(bytes, 3 * int_len + 2)?,
+                    );
+                    let label = text(TextRef::read(bytes, label_offset, &layout)?)?;
+                    formats.push((format, label));
+                }
+                _ => {}
+            }
+        }
+
+        let column_count = column_count
+            .ok_or_else(|| Error::InvalidSubheader("Missing column size subheader".to_string()))?;
+        if names.len() < column_count || spans.len() < column_count {
+            return Err(Error::InvalidSubheader(format!(
+                "Expected {} columns but found {} names and {} attributes",
+                column_count,
+                names.len(),
+                spans.len()
+            )));
+        }
+
+        let mut columns = Vec::with_capacity(column_count);
+        for i in 0..column_count {
+            let span = spans[i];
+            if span.offset.saturating_add(span.length) > self.properties.row_length {
+                return Err(Error::InvalidSubheader(format!(
+                    "Column {} ends past the end of the row",
+                    i
+                )));
+            }
+            let (format, label) = formats.get(i).cloned().unwrap_or_default();
+            columns.push(Column {
+                name: text(names[i])?,
+                label,
+                column_type: span.column_type,
+                length: span.length,
+                format,
+            });
+        }
+
+        self.properties.column_count = column_count;
+        self.properties.compression = match self.compression_literal.as_str() {
+            RLE_SIGNATURE => Compression::Rle,
+            RDC_SIGNATURE => Compression::Rdc,
+            _ => Compression::None,
+        };
+        self.spans = spans[..column_count].to_vec();
+        self.schema = Schema {
+            name: self.properties.dataset_name.clone(),
+            label: self.properties.file_label.clone(),
+            columns,
+        };
+        Ok(())
+    }
+
+    /// Locate the rows held on a page, in file order: rows stored as
+    /// subheaders first, then rows after the pointer table. `rows_before`
+    /// counts the rows on earlier pages, since the last page of rows may be
+    /// only partly filled.
+    pub fn row_slots<'a>(
+        &self,
+        page: &'a PageBinary,
+        rows_before: usize,
+    ) -> Result<Vec<RowSlot<'a>>, Error> {
+        let mut slots = self.subheader_rows(page)?;
+        let block = self.block_rows(page, rows_before + slots.len())?;
+        let row_length = self.properties.row_length.max(1);
+        slots.extend(block.chunks_exact(row_length).map(RowSlot::Stored));
+        Ok(slots)
+    }
+
+    /// The rows a page stores as subheaders.
+    fn subheader_rows<'a>(&self, page: &'a PageBinary) -> Result<Vec<RowSlot<'a>>, Error> {
+        let layout = self.layout();
+        let row_length = self.properties.row_length;
+        let mut slots = Vec::new();
+        if !page.page_type().has_subheaders() {
+            return Ok(slots);
+        }
+
+        for pointer in page.pointers(&layout)? {
+            if pointer.is_empty() {
+                continue;
+            }
+            if pointer.compression != Compression::None {
+                slots.push(RowSlot::Compressed(page.subheader(&pointer)?));
+                continue;
+            }
+            let bytes = page.subheader(&pointer)?;
+            let signature = SubheaderSignature::read(bytes, &layout)?;
+            if pointer.is_compressed_data && !signature.is_recognized() {
+                if bytes.len() != row_length {
+                    return Err(Error::InvalidRow(format!(
+                        "Row subheader is {} bytes, expected {}",
+                        bytes.len(),
+                        row_length
+                    )));
+                }
+                slots.push(RowSlot::Stored(bytes));
+            }
+        }
+        Ok(slots)
+    }
+
+    /// The rows stored back to back after a page's pointer table.
+    fn block_rows<'a>(&self, page: &'a PageBinary, rows_before: usize) -> Result<&'a [u8], Error> {
+        let row_length = self.properties.row_length;
+        let remaining = self.properties.row_count.saturating_sub(rows_before);
+        let count = match page.page_type() {
+            PageType::Data => page.block_count as usize,
+            PageType::Mix => self.properties.mix_page_row_count,
+            _ => 0,
+        }
+        .min(remaining);
+
+        let start = page.row_offset(&self.layout());
+        let length = count.saturating_mul(row_length);
+        page.data
+            .get(start..start.saturating_add(length))
+            .ok_or(Error::OutOfBounds(start, length))
+    }
+
+    /// Append one numeric column of a page's rows to `out`, returning the
+    /// number of rows. Full-width cells in rows stored back to back take the
+    /// vectorized path.
+    pub fn gather_numeric(
+        &self,
+        page: &PageBinary,
+        rows_before: usize,
+        column: usize,
+        out: &mut NumericColumn,
+    ) -> Result<usize, Error> {
+        let span = self.spans[column];
+        if span.column_type != ColumnType::Numeric {
+            return Err(Error::InvalidSchema(format!(
+                "Column {} is not numeric",
+                self.schema.columns[column].name
+            )));
+        }
+        let row_length = self.properties.row_length;
+        let endianness = self.properties.endianness();
+        let gather = |rows: &[u8], out: &mut NumericColumn| {
+            if span.length == 8 {
+                gather_numeric(rows, row_length, span.offset, endianness, out)
+            } else {
+                gather_numeric_scalar(rows, row_length, span.offset, span.length, endianness, out)
+            }
+        };
+
+        let slots = self.subheader_rows(page)?;
+        for slot in &slots {
+            match *slot {
+                RowSlot::Stored(bytes) => gather(bytes, out)?,
+                RowSlot::Compressed(bytes) => gather(&self.decompress_row(bytes)?, out)?,
+            }
+        }
+        let block = self.block_rows(page, rows_before + slots.len())?;
+        gather(block, out)?;
+        Ok(slots.len() + block.len() / row_length.max(1))
+    }
+
+    /// Queue the rows held on a page.
+    pub fn load_rows(&mut self, page: &PageBinary) -> Result<(), Error> {
+        let slots = self.row_slots(page, self.rows_read + self.pending_rows.len())?;
+        for slot in slots {
+            let bytes = match slot {
+                RowSlot::Stored(bytes) => bytes.to_vec(),
+                RowSlot::Compressed(bytes) => self.decompress_row(bytes)?,
+            };
+            self.pending_rows.push_back(bytes);
+        }
+        Ok(())
+    }
+
+    /// Decode a row straight from its page, without queueing it.
+    #[cfg(feature = "rayon")]
+    pub fn decode_slot(&self, slot: RowSlot<'_>) -> Result<Row, Error> {
+        match slot {
+            RowSlot::Stored(bytes) => self.decode_row(bytes),
+            RowSlot::Compressed(bytes) => self.decode_row(&self.decompress_row(bytes)?),
+        }
+    }
+
+    fn decompress_row(&self, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.compression_literal.as_str() {
+            RLE_SIGNATURE => rle::decompress(bytes, self.properties.row_length),
+            RDC_SIGNATURE => rdc::decompress(bytes, self.properties.row_length),
+            literal => Err(Error::UnsupportedCompression(format!(
+                "Compressed rows ({:?}) are not supported",
+                literal
+            ))),
+        }
+    }
+
+    fn decode_row(&self, bytes: &[u8]) -> Result<Row, Error> {
+        let endianness = self.properties.endianness();
+        let blank = self.properties.encoding.blank();
+        self.spans
+            .iter()
+            .zip(&self.schema.columns)
+            .map(|(span, column)| {
+                let cell = bytes
+                    .get(span.offset..span.offset + span.length)
+                    .ok_or(Error::OutOfBounds(span.offset, span.length))?;
+                match span.column_type {
+                    ColumnType::Numeric => decode_numeric(cell, endianness),
+                    ColumnType::Character => self.decode_text(column, trim_padding(cell, blank)),
+                }
+            })
+            .collect()
+    }
+
+    fn decode_text(&self, column: &Column, bytes: &[u8]) -> Result<Value, Error> {
+        let encoding = self.properties.encoding;
+        if self.invalid_bytes == InvalidBytes::Replace {
+            return Ok(Value::String(encoding.decode(bytes)));
+        }
+        match encoding.try_decode(bytes) {
+            Some(text) => Ok(Value::String(text)),
+            None if self.invalid_bytes == InvalidBytes::Raw => Ok(Value::Bytes(bytes.to_vec())),
+            None => Err(Error::InvalidRow(format!(
+                "Column {} holds {:?}, which is not valid {:?}",
+                column.name,
+                String::from_utf8_lossy(bytes),
+                encoding
+            ))),
+        }
+    }
+
+    pub fn file_info(&self) -> Vec<(String, String)> {
+        let properties = &self.properties;
+        let datetime =
+            |value: Option<NaiveDateTime>| value.map(|v| v.to_string()).unwrap_or_default();
+        let mut info = vec![
+            ("format", "sas7bdat".to_string()),
+            ("created", datetime(properties.created)),
+            ("modified", datetime(properties.modified)),
+            ("encoding", format!("{:?}", properties.encoding)),
+            ("release", properties.release.clone()),
+            ("server_type", properties.server_type.clone()),
+            ("os_name", properties.os_name.clone()),
+            ("row_count", properties.row_count.to_string()),
+            ("row_length", properties.row_length.to_string()),
+            ("page_size", properties.page_size.to_string()),
+            ("page_count", properties.page_count.to_string()),
+            ("compression", format!("{:?}", properties.compression)),
+            ("u64", properties.is_u64().to_string()),
+            ("endianness", format!("{:?}", properties.endianness())),
+        ];
+        if let Some(os_type) = properties.os_type {
+            info.push(("os_type", format!("{:?}", os_type)));
+        }
+        info.into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+}
+
+fn check_remainder(bytes: &[u8], layout: &Layout) -> Result<(), Error> {
+    let signature_len = layout.signature_len();
+    let remainder = layout.read_u16(bytes, signature_len)? as usize;
+    if remainder + 4 + 2 * signature_len != bytes.len() {
+        return Err(Error::InvalidSubheader(format!(
+            "Subheader of {} bytes has remainder {}",
+            bytes.len(),
+            remainder
+        )));
+    }
+    Ok(())
+}
+
+/// Character cells and text are padded with blanks or NULs.
+fn trim_padding(bytes: &[u8], blank: u8) -> &[u8] {
+    let end = bytes
+        .iter()
+        .rposition(|&b| b != blank && b != 0)
+        .map_or(0, |i| i + 1);
+    &bytes[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_padding() {
+        assert_eq!(trim_padding(b"abc  \0\0", b' '), b"abc");
+        assert_eq!(trim_padding(b"   ", b' '), b"");
+        assert_eq!(trim_padding(b" a", b' '), b" a");
+        assert_eq!(trim_padding(&[0xC1, 0x40, 0x40], 0x40), &[0xC1]);
+    }
+}
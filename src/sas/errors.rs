@@ -1,6 +1,58 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq)]
 pub enum Error {
     InvalidCompression(u8),
+    Io(String),
+    InvalidHeader(String),
+    InvalidPage(String),
+    InvalidSubheader(String),
+    InvalidSchema(String),
+    InvalidRow(String),
+    UnsupportedCompression(String),
+    OutOfBounds(usize, usize),
+    /// A failure in one of the export formats, such as Arrow or CSV.
+    Export(String),
+    /// Every way a schema breaks the limits of the transport format.
+    TransportLimits(Vec<String>),
+    Deserialize(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidCompression(value) => write!(f, "Invalid compression code: {}", value),
+            Error::Io(message) => write!(f, "I/O error: {}", message),
+            Error::InvalidHeader(message) => write!(f, "Invalid header: {}", message),
+            Error::InvalidPage(message) => write!(f, "Invalid page: {}", message),
+            Error::InvalidSubheader(message) => write!(f, "Invalid subheader: {}", message),
+            Error::InvalidSchema(message) => write!(f, "Invalid schema: {}", message),
+            Error::InvalidRow(message) => write!(f, "Invalid row: {}", message),
+            Error::UnsupportedCompression(message) => {
+                write!(f, "Unsupported compression: {}", message)
+            }
+            Error::OutOfBounds(offset, length) => write!(
+                f,
+                "Read of {} bytes at offset {} is out of bounds",
+                length, offset
+            ),
+            Error::Export(message) => write!(f, "Export failed: {}", message),
+            Error::TransportLimits(violations) => write!(
+                f,
+                "Dataset does not fit the transport format: {}",
+                violations.join("; ")
+            ),
+            Error::Deserialize(message) => write!(f, "Could not deserialize: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -12,4 +64,16 @@ mod tests {
         let error = Error::InvalidCompression(0);
         assert_eq!(error, Error::InvalidCompression(0));
     }
+
+    #[test]
+    fn can_display_error() {
+        let error = Error::InvalidHeader("too short".to_string());
+        assert_eq!(error.to_string(), "Invalid header: too short");
+    }
+
+    #[test]
+    fn can_convert_io_error() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "eof");
+        assert_eq!(Error::from(io_error), Error::Io("eof".to_string()));
+    }
 }
@@ -0,0 +1,1043 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+
+use crate::sas::column::{Column, ColumnFormat, ColumnType};
+use crate::sas::{MissingValue, Value};
+use crate::util::time::get_sas_epoch;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// What kind of quantity a format says a numeric column holds.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FormatKind {
+    Numeric,
+    Date,
+    Time,
+    DateTime,
+    Character,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DateStyle {
+    /// `DATEw.`, e.g. `01JAN1960`.
+    Date,
+    /// `DDMMYYw.`, e.g. `31/01/1960`.
+    Ddmmyy,
+    /// `MMDDYYw.`, e.g. `01/31/1960`.
+    Mmddyy,
+    /// `YYMMDDw.`, e.g. `1960-01-31`.
+    Yymmdd,
+    /// `MONYYw.`, e.g. `JAN1960`.
+    Monyy,
+    /// `YEARw.`, e.g. `1960`.
+    Year,
+    /// `E8601DAw.` / `IS8601DAw.`, e.g. `1960-01-31`.
+    Iso,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimeStyle {
+    /// `TIMEw.d`, e.g. `13:05:09`.
+    Time,
+    /// `HHMMw.`, e.g. `13:05`.
+    Hhmm,
+    /// `E8601TMw.d` / `IS8601TMw.d`, e.g. `13:05:09`.
+    Iso,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DateTimeStyle {
+    /// `DATETIMEw.d`, e.g. `01JAN1960:13:05:09`.
+    DateTime,
+    /// `E8601DTw.d` / `IS8601DTw.d`, e.g. `1960-01-01T13:05:09`.
+    Iso,
+}
+
+/// A SAS output format, resolved from a column's format name, width and
+/// decimals, that renders values the way SAS prints them.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SasFormat {
+    Best {
+        width: usize,
+    },
+    Fixed {
+        width: usize,
+        decimals: usize,
+    },
+    Comma {
+        width: usize,
+        decimals: usize,
+    },
+    Dollar {
+        width: usize,
+        decimals: usize,
+    },
+    Percent {
+        width: usize,
+        decimals: usize,
+    },
+    ZeroPadded {
+        width: usize,
+        decimals: usize,
+    },
+    Scientific {
+        width: usize,
+    },
+    Date {
+        style: DateStyle,
+        width: usize,
+    },
+    Time {
+        style: TimeStyle,
+        width: usize,
+        decimals: usize,
+    },
+    DateTime {
+        style: DateTimeStyle,
+        width: usize,
+        decimals: usize,
+    },
+    Character {
+        width: usize,
+    },
+    /// A format this crate does not know, e.g. a user-defined one. Numeric
+    /// values are rendered with `BESTw.`.
+    Other {
+        name: String,
+        width: usize,
+        decimals: usize,
+    },
+}
+
+impl SasFormat {
+    pub fn from_column_format(format: &ColumnFormat) -> Self {
+        let name = format.name.to_uppercase();
+        let width = format.width as usize;
+        let decimals = format.decimals as usize;
+        let or_default = |default: usize| if width == 0 { default } else { width };
+
+        match name.as_str() {
+            "" if width == 0 => SasFormat::Best { width: 12 },
+            "" | "F" => SasFormat::Fixed {
+                width: or_default(12),
+                decimals,
+            },
+            "BEST" => SasFormat::Best {
+                width: or_default(12),
+            },
+            "COMMA" => SasFormat::Comma {
+                width: or_default(6),
+                decimals,
+            },
+            "DOLLAR" => SasFormat::Dollar {
+                width: or_default(6),
+                decimals,
+            },
+            "PERCENT" => SasFormat::Percent {
+                width: or_default(6),
+                decimals,
+            },
+            "Z" => SasFormat::ZeroPadded {
+                width: or_default(1),
+                decimals,
+            },
+            "E" => SasFormat::Scientific {
+                width: or_default(12),
+            },
+            "DATE" => SasFormat::Date {
+                style: DateStyle::Date,
+                width: or_default(7),
+            },
+            "DDMMYY" => SasFormat::Date {
+                style: DateStyle::Ddmmyy,
+                width: or_default(8),
+            },
+            "MMDDYY" => SasFormat::Date {
+                style: DateStyle::Mmddyy,
+                width: or_default(8),
+            },
+            "YYMMDD" => SasFormat::Date {
+                style: DateStyle::Yymmdd,
+                width: or_default(8),
+            },
+            "MONYY" => SasFormat::Date {
+                style: DateStyle::Monyy,
+                width: or_default(5),
+            },
+            "YEAR" => SasFormat::Date {
+                style: DateStyle::Year,
+                width: or_default(4),
+            },
+            "E8601DA" | "IS8601DA" => SasFormat::Date {
+                style: DateStyle::Iso,
+                width: or_default(10),
+            },
+            "TIME" => SasFormat::Time {
+                style: TimeStyle::Time,
+                width: or_default(8),
+                decimals,
+            },
+            "HHMM" => SasFormat::Time {
+                style: TimeStyle::Hhmm,
+                width: or_default(5),
+                decimals: 0,
+            },
+            "E8601TM" | "IS8601TM" => SasFormat::Time {
+                style: TimeStyle::Iso,
+                width: or_default(8),
+                decimals,
+            },
+            "DATETIME" => SasFormat::DateTime {
+                style: DateTimeStyle::DateTime,
+                width: or_default(16),
+                decimals,
+            },
+            "E8601DT" | "IS8601DT" => SasFormat::DateTime {
+                style: DateTimeStyle::Iso,
+                width: or_default(19),
+                decimals,
+            },
+            name if name.starts_with('$') => SasFormat::Character { width },
+            _ => SasFormat::Other {
+                name,
+                width: or_default(12),
+                decimals,
+            },
+        }
+    }
+
+    /// The format of a column, falling back to `BEST12.` for numeric columns
+    /// and `$w.` for character columns that have no format attached.
+    pub fn for_column(column: &Column) -> Self {
+        match (column.column_type, column.format.is_empty()) {
+            (ColumnType::Character, true) => SasFormat::Character {
+                width: column.length,
+            },
+            (ColumnType::Numeric, true) => SasFormat::Best { width: 12 },
+            _ => SasFormat::from_column_format(&column.format),
+        }
+    }
+
+    /// The ISO 8601 format for dates, times or datetimes; `None` for other
+    /// kinds of value.
+    pub fn iso(kind: FormatKind) -> Option<Self> {
+        match kind {
+            FormatKind::Date => Some(SasFormat::Date {
+                style: DateStyle::Iso,
+                width: 10,
+            }),
+            FormatKind::Time => Some(SasFormat::Time {
+                style: TimeStyle::Iso,
+                width: 16,
+                decimals: 0,
+            }),
+            FormatKind::DateTime => Some(SasFormat::DateTime {
+                style: DateTimeStyle::Iso,
+                width: 19,
+                decimals: 0,
+            }),
+            FormatKind::Numeric | FormatKind::Character => None,
+        }
+    }
+
+    pub fn kind(&self) -> FormatKind {
+        match self {
+            SasFormat::Date { .. } => FormatKind::Date,
+            SasFormat::Time { .. } => FormatKind::Time,
+            SasFormat::DateTime { .. } => FormatKind::DateTime,
+            SasFormat::Character { .. } => FormatKind::Character,
+            _ => FormatKind::Numeric,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            SasFormat::Best { width }
+            | SasFormat::Fixed { width, .. }
+            | SasFormat::Comma { width, .. }
+            | SasFormat::Dollar { width, .. }
+            | SasFormat::Percent { width, .. }
+            | SasFormat::ZeroPadded { width, .. }
+            | SasFormat::Scientific { width }
+            | SasFormat::Date { width, .. }
+            | SasFormat::Time { width, .. }
+            | SasFormat::DateTime { width, .. }
+            | SasFormat::Character { width }
+            | SasFormat::Other { width, .. } => *width,
+        }
+    }
+
+    /// Render a value the way the SAS `PUT` function would: numeric output is
+    /// right-aligned in the format width, character output is left-aligned
+    /// and padded, and values that do not fit are shown as asterisks.
+    pub fn render(&self, value: &Value) -> String {
+        match (self, value) {
+            (SasFormat::Character { width }, Value::String(text)) => render_character(text, *width),
+            (SasFormat::Character { width }, Value::Bytes(_)) => {
+                render_character(&value.to_text().unwrap_or_default(), *width)
+            }
+            (SasFormat::Character { width }, Value::Number(number)) => best(*number, *width),
+            (SasFormat::Character { width }, Value::Missing(_)) => " ".repeat(*width),
+            (_, Value::String(text)) => text.clone(),
+            (_, Value::Bytes(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+            (format, Value::Missing(missing)) => render_missing(missing, format.width()),
+            (format, Value::Number(number)) => format.render_number(*number),
+        }
+    }
+
+    fn render_number(&self, value: f64) -> String {
+        if !value.is_finite() {
+            return right_align(".", self.width());
+        }
+
+        match self {
+            SasFormat::Best { width } | SasFormat::Other { width, .. } => best(value, *width),
+            SasFormat::Fixed { width, decimals } => fixed(value, *width, *decimals),
+            SasFormat::Comma { width, decimals } => grouped(value, *width, *decimals, ""),
+            SasFormat::Dollar { width, decimals } => grouped(value, *width, *decimals, "$"),
+            SasFormat::Percent { width, decimals } => percent(value, *width, *decimals),
+            SasFormat::ZeroPadded { width, decimals } => zero_padded(value, *width, *decimals),
+            SasFormat::Scientific { width } => scientific(value, *width),
+            SasFormat::Date { style, width } => date(value, *style, *width),
+            SasFormat::Time {
+                style,
+                width,
+                decimals,
+            } => time(value, *style, *width, *decimals),
+            SasFormat::DateTime {
+                style,
+                width,
+                decimals,
+            } => datetime(value, *style, *width, *decimals),
+            SasFormat::Character { width } => best(value, *width),
+        }
+    }
+}
+
+fn right_align(text: &str, width: usize) -> String {
+    format!("{:>width$}", text, width = width)
+}
+
+fn overflow(width: usize) -> String {
+    "*".repeat(width)
+}
+
+fn fit(text: &str, width: usize) -> Option<String> {
+    if text.chars().count() <= width {
+        Some(right_align(text, width))
+    } else {
+        None
+    }
+}
+
+fn render_character(text: &str, width: usize) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    let truncated: String = text.chars().take(width).collect();
+    format!("{:<width$}", truncated, width = width)
+}
+
+fn render_missing(missing: &MissingValue, width: usize) -> String {
+    right_align(&missing.display_char().to_string(), width.max(1))
+}
+
+/// The decimal digits of `magnitude` rounded to 15 significant digits, as
+/// SAS stores and prints them, together with the power of ten of the last
+/// digit.
+fn significant_digits(magnitude: f64) -> (Vec<u8>, i32) {
+    let text = format!("{:.14e}", magnitude);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let digits = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| b - b'0')
+        .collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    (digits, exponent - 14)
+}
+
+fn increment(digits: &mut Vec<u8>) {
+    for digit in digits.iter_mut().rev() {
+        if *digit == 9 {
+            *digit = 0;
+        } else {
+            *digit += 1;
+            return;
+        }
+    }
+    digits.insert(0, 1);
+}
+
+/// Round `magnitude` half away from zero to `decimals` places and return it
+/// as a plain decimal string, e.g. `(1234.565, 2)` gives `"1234.57"`.
+fn round_fixed(magnitude: f64, decimals: usize) -> String {
+    let mut digits = if magnitude == 0.0 {
+        vec![0]
+    } else {
+        let (mut digits, exponent) = significant_digits(magnitude);
+        let shift = exponent + decimals as i32;
+        if shift >= 0 {
+            digits.extend(std::iter::repeat_n(0, shift as usize));
+        } else {
+            let dropped = (-shift) as usize;
+            if dropped > digits.len() {
+                digits = vec![0];
+            } else {
+                let kept = digits.len() - dropped;
+                let round_up = digits[kept] >= 5;
+                digits.truncate(kept);
+                if round_up {
+                    increment(&mut digits);
+                }
+            }
+        }
+        digits
+    };
+
+    while digits.len() > 1 && digits[0] == 0 {
+        digits.remove(0);
+    }
+    if digits.len() <= decimals {
+        let padding = decimals + 1 - digits.len();
+        digits.splice(0..0, std::iter::repeat_n(0, padding));
+    }
+
+    let mut text: String = digits.iter().map(|d| (b'0' + d) as char).collect();
+    if decimals > 0 {
+        text.insert(text.len() - decimals, '.');
+    }
+    text
+}
+
+fn sign(value: f64, text: &str) -> &'static str {
+    if value < 0.0 && text.bytes().any(|b| (b'1'..=b'9').contains(&b)) {
+        "-"
+    } else {
+        ""
+    }
+}
+
+fn trim_fraction(text: &str) -> &str {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        text
+    }
+}
+
+fn count_significant(text: &str) -> usize {
+    text.bytes()
+        .filter(u8::is_ascii_digit)
+        .skip_while(|b| *b == b'0')
+        .count()
+}
+
+/// `BESTw.`: the representation that shows the most significant digits in
+/// `width` characters, preferring plain notation over scientific notation.
+fn best(value: f64, width: usize) -> String {
+    let width = width.max(1);
+    if value == 0.0 {
+        return right_align("0", width);
+    }
+
+    let magnitude = value.abs();
+    let available = if value < 0.0 { width - 1 } else { width };
+    let mut plain = None;
+
+    let integer = round_fixed(magnitude, 0);
+    if integer.len() <= available {
+        let candidate = match available.saturating_sub(integer.len() + 1) {
+            0 => integer,
+            decimals => {
+                let mut decimals = decimals;
+                let mut text = round_fixed(magnitude, decimals);
+                // Rounding can carry into a new integer digit, e.g. 9.99 -> 10.0.
+                while text.len() > available && decimals > 0 {
+                    decimals -= 1;
+                    text = round_fixed(magnitude, decimals);
+                }
+                trim_fraction(&text).to_string()
+            }
+        };
+        if candidate.len() <= available {
+            plain = Some(candidate);
+        }
+    }
+
+    let scientific = best_scientific(magnitude, available);
+
+    let text = match (plain, scientific) {
+        (Some(plain), Some((scientific, precision))) => {
+            let exact = plain.parse::<f64>().ok() == format!("{:.14e}", magnitude).parse().ok();
+            if exact || count_significant(&plain) >= precision {
+                plain
+            } else {
+                scientific
+            }
+        }
+        (Some(plain), None) => plain,
+        (None, Some((scientific, _))) => scientific,
+        (None, None) => return overflow(width),
+    };
+
+    let prefix = sign(value, &text);
+    right_align(&format!("{}{}", prefix, text), width)
+}
+
+/// Scientific notation as used by `BESTw.`, e.g. `1.2345679E14`, together
+/// with the number of significant digits it can show.
+fn best_scientific(magnitude: f64, available: usize) -> Option<(String, usize)> {
+    let (_, exponent) = significant_digits(magnitude);
+    let mut exponent = exponent + 14;
+    for _ in 0..2 {
+        let suffix = format!("E{}", exponent);
+        if suffix.len() + 1 > available {
+            return None;
+        }
+        let precision = match available - suffix.len() {
+            1 => 1,
+            2 => 1,
+            room => room - 1,
+        };
+        let mantissa = magnitude / 10f64.powi(exponent);
+        let text = round_fixed(mantissa, precision - 1);
+        if text.starts_with("10") {
+            exponent += 1;
+            continue;
+        }
+        return Some((format!("{}{}", trim_fraction(&text), suffix), precision));
+    }
+    None
+}
+
+/// `w.d`, falling back to `BESTw.` when the value does not fit.
+fn fixed(value: f64, width: usize, decimals: usize) -> String {
+    let digits = round_fixed(value.abs(), decimals);
+    let text = format!("{}{}", sign(value, &digits), digits);
+    fit(&text, width).unwrap_or_else(|| best(value, width))
+}
+
+fn group_thousands(digits: &str) -> String {
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    match fraction {
+        Some(fraction) => format!("{}.{}", grouped, fraction),
+        None => grouped,
+    }
+}
+
+/// `COMMAw.d` and `DOLLARw.d`. When the width is too small SAS drops the
+/// separators and symbol before giving up on the format.
+fn grouped(value: f64, width: usize, decimals: usize, symbol: &str) -> String {
+    let digits = round_fixed(value.abs(), decimals);
+    let prefix = sign(value, &digits);
+    let text = format!("{}{}{}", prefix, symbol, group_thousands(&digits));
+    fit(&text, width).unwrap_or_else(|| fixed(value, width, decimals))
+}
+
+/// `PERCENTw.d`: the value times 100 with a percent sign. Negative values are
+/// put in parentheses, so non-negative values keep a trailing blank where
+/// the closing parenthesis would go.
+fn percent(value: f64, width: usize, decimals: usize) -> String {
+    let digits = round_fixed((value * 100.0).abs(), decimals);
+    let text = if sign(value, &digits).is_empty() {
+        format!("{}% ", digits)
+    } else {
+        format!("({}%)", digits)
+    };
+    fit(&text, width).unwrap_or_else(|| best(value, width))
+}
+
+/// `Zw.d`: `w.d` padded with leading zeros.
+fn zero_padded(value: f64, width: usize, decimals: usize) -> String {
+    let digits = round_fixed(value.abs(), decimals);
+    let prefix = sign(value, &digits);
+    if prefix.len() + digits.len() > width {
+        return best(value, width);
+    }
+    let padding = "0".repeat(width - prefix.len() - digits.len());
+    format!("{}{}{}", prefix, padding, digits)
+}
+
+/// `Ew.`: scientific notation with a sign position, `w - 7` decimals and a
+/// signed exponent of at least two digits, e.g. ` 1.257E+03` for `E10.`.
+fn scientific(value: f64, width: usize) -> String {
+    let magnitude = value.abs();
+    let mut exponent = if magnitude == 0.0 {
+        0
+    } else {
+        significant_digits(magnitude).1 + 14
+    };
+
+    for _ in 0..2 {
+        let exponent_text = format!(
+            "E{}{:02}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        );
+        let fixed_len = 2 + exponent_text.len();
+        if fixed_len > width {
+            return overflow(width);
+        }
+        let decimals = (width - fixed_len).saturating_sub(1);
+        let mantissa = if magnitude == 0.0 {
+            0.0
+        } else {
+            magnitude / 10f64.powi(exponent)
+        };
+        let digits = round_fixed(mantissa, decimals);
+        if digits.starts_with("10") {
+            exponent += 1;
+            continue;
+        }
+        let sign = if value < 0.0 { '-' } else { ' ' };
+        let text = format!("{}{}{}", sign, digits, exponent_text);
+        return fit(&text, width).unwrap_or_else(|| overflow(width));
+    }
+    overflow(width)
+}
+
+fn sas_date(days: f64) -> Option<NaiveDate> {
+    let epoch = get_sas_epoch().date();
+    epoch.checked_add_signed(Duration::try_days(days.floor() as i64)?)
+}
+
+fn sas_datetime(seconds: f64) -> Option<NaiveDateTime> {
+    let millis = (seconds * 1000.0).round();
+    if !(-1e16..=1e16).contains(&millis) {
+        return None;
+    }
+    get_sas_epoch().checked_add_signed(Duration::try_milliseconds(millis as i64)?)
+}
+
+fn month(date: &NaiveDate) -> &'static str {
+    MONTHS[date.month0() as usize]
+}
+
+fn date_text(date: &NaiveDate, style: DateStyle, width: usize) -> Option<String> {
+    let (day, month_number, year) = (date.day(), date.month(), date.year());
+    let short_year = year.rem_euclid(100);
+    let text = match (style, width) {
+        (DateStyle::Date, 5..=6) => format!("{:02}{}", day, month(date)),
+        (DateStyle::Date, 7..=8) => format!("{:02}{}{:02}", day, month(date), short_year),
+        (DateStyle::Date, 9..=10) => format!("{:02}{}{:04}", day, month(date), year),
+        (DateStyle::Date, 11..) => format!("{:02}-{}-{:04}", day, month(date), year),
+        (DateStyle::Ddmmyy | DateStyle::Mmddyy | DateStyle::Yymmdd, _) => {
+            let (first, second) = if style == DateStyle::Mmddyy {
+                (month_number, day)
+            } else {
+                (day, month_number)
+            };
+            match (style, width) {
+                (DateStyle::Yymmdd, 6..=7) => {
+                    format!("{:02}{:02}{:02}", short_year, month_number, day)
+                }
+                (DateStyle::Yymmdd, 8..=9) => {
+                    format!("{:02}-{:02}-{:02}", short_year, month_number, day)
+                }
+                (DateStyle::Yymmdd, 10..) => {
+                    format!("{:04}-{:02}-{:02}", year, month_number, day)
+                }
+                (_, 2..=3) => format!("{:02}", first),
+                (_, 4) => format!("{:02}{:02}", first, second),
+                (_, 5) => format!("{:02}/{:02}", first, second),
+                (_, 6..=7) => format!("{:02}{:02}{:02}", first, second, short_year),
+                (_, 8..=9) => format!("{:02}/{:02}/{:02}", first, second, short_year),
+                (_, 10..) => format!("{:02}/{:02}/{:04}", first, second, year),
+                _ => return None,
+            }
+        }
+        (DateStyle::Monyy, 5..=6) => format!("{}{:02}", month(date), short_year),
+        (DateStyle::Monyy, 7..) => format!("{}{:04}", month(date), year),
+        (DateStyle::Year, 2..=3) => format!("{:02}", short_year),
+        (DateStyle::Year, 4..) => format!("{:04}", year),
+        (DateStyle::Iso, 10..) => format!("{:04}-{:02}-{:02}", year, month_number, day),
+        _ => return None,
+    };
+    Some(text)
+}
+
+fn date(value: f64, style: DateStyle, width: usize) -> String {
+    sas_date(value)
+        .and_then(|date| date_text(&date, style, width))
+        .and_then(|text| fit(&text, width))
+        .unwrap_or_else(|| overflow(width))
+}
+
+fn seconds_fraction(seconds: f64, decimals: usize) -> String {
+    if decimals == 0 {
+        return String::new();
+    }
+    let fraction = round_fixed(seconds.abs().fract(), decimals);
+    let fraction = fraction.split_once('.').map(|(_, f)| f).unwrap_or("");
+    format!(".{}", fraction)
+}
+
+/// Seconds rounded to the decimals shown, so that a fraction that rounds up
+/// carries into the seconds. Without decimals the fraction is dropped.
+fn round_seconds(seconds: f64, decimals: usize) -> f64 {
+    if decimals == 0 {
+        return seconds.floor();
+    }
+    let scale = 10_f64.powi(decimals.min(9) as i32);
+    (seconds * scale).round() / scale
+}
+
+/// Hours, minutes, seconds and the fraction of a duration, to `decimals`.
+fn clock(seconds: f64, decimals: usize) -> (u64, u64, u64, String) {
+    let rounded = round_seconds(seconds.abs(), decimals);
+    let total = rounded as u64;
+    let fraction = seconds_fraction(rounded, decimals);
+    (total / 3600, total / 60 % 60, total % 60, fraction)
+}
+
+fn time(value: f64, style: TimeStyle, width: usize, decimals: usize) -> String {
+    let prefix = if value < 0.0 { "-" } else { "" };
+
+    let text = match style {
+        TimeStyle::Iso => {
+            let (hours, minutes, seconds, fraction) = clock(value, decimals);
+            format!(
+                "{}{:02}:{:02}:{:02}{}",
+                prefix, hours, minutes, seconds, fraction
+            )
+        }
+        TimeStyle::Hhmm => {
+            let (hours, minutes, _, _) = clock((value.abs() / 60.0).round() * 60.0, 0);
+            format!("{}{}:{:02}", prefix, hours, minutes)
+        }
+        TimeStyle::Time => {
+            let (hours, minutes, seconds, fraction) = clock(value, decimals);
+            let with_fraction = format!(
+                "{}{}:{:02}:{:02}{}",
+                prefix, hours, minutes, seconds, fraction
+            );
+            let (hours, minutes, seconds, _) = clock(value, 0);
+            let full = format!("{}{}:{:02}:{:02}", prefix, hours, minutes, seconds);
+            if decimals > 0 && with_fraction.len() <= width {
+                with_fraction
+            } else if full.len() <= width {
+                full
+            } else {
+                format!("{}{}:{:02}", prefix, hours, minutes)
+            }
+        }
+    };
+    fit(&text, width).unwrap_or_else(|| overflow(width))
+}
+
+fn datetime_text(
+    value: f64,
+    style: DateTimeStyle,
+    width: usize,
+    decimals: usize,
+) -> Option<String> {
+    let rounded = sas_datetime(round_seconds(value, decimals))?;
+    let fraction = seconds_fraction(rounded.nanosecond() as f64 / 1e9, decimals);
+    let parts = |moment: &NaiveDateTime| {
+        let date = moment.date();
+        let day = format!("{:02}{}", date.day(), month(&date));
+        (date, day, moment.hour(), moment.minute(), moment.second())
+    };
+
+    let text = match style {
+        DateTimeStyle::Iso => {
+            let (date, _, hour, minute, second) = parts(&rounded);
+            format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+                date.year(),
+                date.month(),
+                date.day(),
+                hour,
+                minute,
+                second,
+                fraction
+            )
+        }
+        DateTimeStyle::DateTime => {
+            if decimals > 0 && width >= 18 {
+                let (date, day, hour, minute, second) = parts(&rounded);
+                let full = format!(
+                    "{}{:04}:{:02}:{:02}:{:02}{}",
+                    day,
+                    date.year(),
+                    hour,
+                    minute,
+                    second,
+                    fraction
+                );
+                if full.len() <= width {
+                    return Some(full);
+                }
+            }
+            let moment = sas_datetime(round_seconds(value, 0))?;
+            let (date, day, hour, minute, second) = parts(&moment);
+            let short = format!("{}{:02}", day, date.year().rem_euclid(100));
+            let long = format!("{}{:04}", day, date.year());
+            match width {
+                7..=9 => short,
+                10..=12 => format!("{}:{:02}", short, hour),
+                13..=15 => format!("{}:{:02}:{:02}", short, hour, minute),
+                16..=17 => format!("{}:{:02}:{:02}:{:02}", short, hour, minute, second),
+                18.. => format!("{}:{:02}:{:02}:{:02}", long, hour, minute, second),
+                _ => return None,
+            }
+        }
+    };
+    Some(text)
+}
+
+fn datetime(value: f64, style: DateTimeStyle, width: usize, decimals: usize) -> String {
+    datetime_text(value, style, width, decimals)
+        .and_then(|text| fit(&text, width))
+        .unwrap_or_else(|| overflow(width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: &str, value: f64) -> String {
+        let format = ColumnFormat::parse(format).unwrap();
+        SasFormat::from_column_format(&format).render(&Value::Number(value))
+    }
+
+    #[test]
+    fn can_resolve_formats_from_column_format() {
+        let format = SasFormat::from_column_format(&ColumnFormat::new("DOLLAR", 10, 2));
+        assert_eq!(
+            format,
+            SasFormat::Dollar {
+                width: 10,
+                decimals: 2
+            }
+        );
+
+        let format = SasFormat::from_column_format(&ColumnFormat::new("", 0, 0));
+        assert_eq!(format, SasFormat::Best { width: 12 });
+
+        let format = SasFormat::from_column_format(&ColumnFormat::new("DATE", 0, 0));
+        assert_eq!(
+            format,
+            SasFormat::Date {
+                style: DateStyle::Date,
+                width: 7
+            }
+        );
+    }
+
+    #[test]
+    fn can_classify_formats() {
+        let kind =
+            |name: &str| SasFormat::from_column_format(&ColumnFormat::parse(name).unwrap()).kind();
+        assert_eq!(kind("DATE9."), FormatKind::Date);
+        assert_eq!(kind("MMDDYY10."), FormatKind::Date);
+        assert_eq!(kind("TIME8."), FormatKind::Time);
+        assert_eq!(kind("DATETIME20."), FormatKind::DateTime);
+        assert_eq!(kind("E8601DT."), FormatKind::DateTime);
+        assert_eq!(kind("$CHAR10."), FormatKind::Character);
+        assert_eq!(kind("COMMA10.2"), FormatKind::Numeric);
+        assert_eq!(kind("MYFMT."), FormatKind::Numeric);
+    }
+
+    #[test]
+    fn test_round_fixed_rounds_half_away_from_zero() {
+        assert_eq!(round_fixed(2.5, 0), "3");
+        assert_eq!(round_fixed(0.125, 2), "0.13");
+        assert_eq!(round_fixed(1.005, 2), "1.01");
+        assert_eq!(round_fixed(9.999, 2), "10.00");
+        assert_eq!(round_fixed(0.0004, 3), "0.000");
+        assert_eq!(round_fixed(1234.5, 0), "1235");
+    }
+
+    #[test]
+    fn test_best_format() {
+        assert_eq!(render("BEST12.", 42.0), "          42");
+        assert_eq!(render("BEST12.", 1.5), "         1.5");
+        assert_eq!(render("BEST12.", 1.0 / 3.0), "0.3333333333");
+        assert_eq!(render("BEST12.", -1.0 / 3.0), "-0.333333333");
+        assert_eq!(render("BEST12.", 0.1 + 0.2), "         0.3");
+        assert_eq!(render("BEST12.", 1234567.891), " 1234567.891");
+        assert_eq!(render("BEST12.", 123456789012345.0), "1.2345679E14");
+        assert_eq!(render("BEST12.", 1e-15), "       1E-15");
+        assert_eq!(render("BEST12.", 0.00001), "     0.00001");
+        assert_eq!(render("BEST3.", 123.456), "123");
+        assert_eq!(render("BEST.", 0.0), "           0");
+    }
+
+    #[test]
+    fn test_fixed_format() {
+        assert_eq!(render("8.2", 12.3456), "   12.35");
+        assert_eq!(render("8.2", -12.3456), "  -12.35");
+        assert_eq!(render("5.", 2.5), "    3");
+        assert_eq!(render("6.1", -0.04), "   0.0");
+        assert_eq!(render("4.2", 1234.5), "1235");
+    }
+
+    #[test]
+    fn test_comma_format() {
+        assert_eq!(render("COMMA10.2", 1234567.891), "1234567.89");
+        assert_eq!(render("COMMA12.2", 1234567.891), "1,234,567.89");
+        assert_eq!(render("COMMA10.", -1234567.0), "-1,234,567");
+        assert_eq!(render("COMMA6.", 999.0), "   999");
+    }
+
+    #[test]
+    fn test_comma_format_drops_separators_when_too_narrow() {
+        assert_eq!(render("COMMA7.", 1234567.0), "1234567");
+    }
+
+    #[test]
+    fn test_dollar_format() {
+        assert_eq!(render("DOLLAR10.2", 1234.5), " $1,234.50");
+        assert_eq!(render("DOLLAR10.2", -1234.5), "-$1,234.50");
+        assert_eq!(render("DOLLAR6.", 12.0), "   $12");
+    }
+
+    #[test]
+    fn test_percent_format() {
+        assert_eq!(render("PERCENT8.2", 0.05), "  5.00% ");
+        assert_eq!(render("PERCENT8.2", -0.05), " (5.00%)");
+        assert_eq!(render("PERCENT6.", 1.0), " 100% ");
+    }
+
+    #[test]
+    fn test_zero_padded_format() {
+        assert_eq!(render("Z5.", 42.0), "00042");
+        assert_eq!(render("Z8.2", 3.5), "00003.50");
+        assert_eq!(render("Z6.", -42.0), "-00042");
+    }
+
+    #[test]
+    fn test_scientific_format() {
+        assert_eq!(render("E10.", 1257.0), " 1.257E+03");
+        assert_eq!(render("E10.", -1257.0), "-1.257E+03");
+        assert_eq!(render("E10.", 0.000123), " 1.230E-04");
+        assert_eq!(render("E10.", 9999.9), " 1.000E+04");
+        assert_eq!(render("E12.", 0.0), " 0.00000E+00");
+    }
+
+    #[test]
+    fn test_date_formats() {
+        // 1960-01-01 is day 0, 2024-03-05 is day 23440
+        assert_eq!(render("DATE9.", 0.0), "01JAN1960");
+        assert_eq!(render("DATE7.", 23440.0), "05MAR24");
+        assert_eq!(render("DATE11.", 23440.0), "05-MAR-2024");
+        assert_eq!(render("DATE.", -1.0), "31DEC59");
+        assert_eq!(render("DDMMYY10.", 23440.0), "05/03/2024");
+        assert_eq!(render("DDMMYY8.", 23440.0), "05/03/24");
+        assert_eq!(render("MMDDYY10.", 23440.0), "03/05/2024");
+        assert_eq!(render("MMDDYY6.", 23440.0), "030524");
+        assert_eq!(render("YYMMDD10.", 23440.0), "2024-03-05");
+        assert_eq!(render("YYMMDD8.", 23440.0), "24-03-05");
+        assert_eq!(render("MONYY7.", 23440.0), "MAR2024");
+        assert_eq!(render("MONYY.", 23440.0), "MAR24");
+        assert_eq!(render("YEAR4.", 23440.0), "2024");
+        assert_eq!(render("E8601DA.", 23440.0), "2024-03-05");
+        assert_eq!(render("DATE9.", 1e12), "*********");
+    }
+
+    #[test]
+    fn test_time_formats() {
+        assert_eq!(render("TIME8.", 47109.0), "13:05:09");
+        assert_eq!(render("TIME8.", 3600.0), " 1:00:00");
+        assert_eq!(render("TIME8.", 90000.0), "25:00:00");
+        assert_eq!(render("TIME5.", 47109.0), "13:05");
+        assert_eq!(render("TIME11.2", 47109.25), "13:05:09.25");
+        assert_eq!(render("TIME11.2", 59.999), " 0:01:00.00");
+        assert_eq!(render("TIME8.", 59.999), " 0:00:59");
+        assert_eq!(render("E8601TM10.1", 3599.96), "01:00:00.0");
+        assert_eq!(render("HHMM.", 47109.0), "13:05");
+        assert_eq!(render("E8601TM.", 3600.0), "01:00:00");
+    }
+
+    #[test]
+    fn test_datetime_formats() {
+        // 2024-03-05 13:05:09 is 23440 days and 47109 seconds after the epoch
+        let moment = 23440.0 * 86400.0 + 47109.0;
+        assert_eq!(render("DATETIME.", moment), "05MAR24:13:05:09");
+        assert_eq!(render("DATETIME18.", moment), "05MAR2024:13:05:09");
+        assert_eq!(render("DATETIME20.", moment), "  05MAR2024:13:05:09");
+        assert_eq!(
+            render("DATETIME22.3", moment + 0.5),
+            "05MAR2024:13:05:09.500"
+        );
+        assert_eq!(render("DATETIME13.", moment), "05MAR24:13:05");
+        assert_eq!(render("E8601DT.", moment), "2024-03-05T13:05:09");
+        assert_eq!(
+            render("E8601DT23.3", moment + 0.25),
+            "2024-03-05T13:05:09.250"
+        );
+        assert_eq!(
+            render("DATETIME21.2", moment + 50.999),
+            "05MAR2024:13:06:00.00"
+        );
+        assert_eq!(render("DATETIME18.", moment + 0.999), "05MAR2024:13:05:09");
+    }
+
+    #[test]
+    fn test_missing_values() {
+        let format = SasFormat::from_column_format(&ColumnFormat::parse("DOLLAR8.2").unwrap());
+        assert_eq!(
+            format.render(&Value::Missing(MissingValue::System)),
+            "       ."
+        );
+        assert_eq!(
+            format.render(&Value::Missing(MissingValue::Special('A'))),
+            "       A"
+        );
+        assert_eq!(format.render(&Value::Number(f64::NAN)), "       .");
+    }
+
+    #[test]
+    fn test_character_format() {
+        let format = SasFormat::from_column_format(&ColumnFormat::parse("$5.").unwrap());
+        assert_eq!(format.render(&Value::String("abc".to_string())), "abc  ");
+        assert_eq!(
+            format.render(&Value::String("abcdefg".to_string())),
+            "abcde"
+        );
+    }
+
+    #[test]
+    fn test_format_for_column_without_format() {
+        let column = Column::numeric("x");
+        assert_eq!(column.sas_format(), SasFormat::Best { width: 12 });
+
+        let column = Column::character("name", 8);
+        assert_eq!(column.sas_format(), SasFormat::Character { width: 8 });
+        assert_eq!(column.render(&Value::String("Bob".to_string())), "Bob     ");
+    }
+
+    #[test]
+    fn test_unknown_format_renders_with_best() {
+        assert_eq!(render("MYFMT8.", 1.5), "     1.5");
+    }
+
+    #[test]
+    fn test_iso_formats() {
+        let date = SasFormat::iso(FormatKind::Date).unwrap();
+        assert_eq!(date.render(&Value::Number(0.0)), "1960-01-01");
+        let time = SasFormat::iso(FormatKind::Time).unwrap();
+        assert_eq!(time.render(&Value::Number(3725.0)).trim(), "01:02:05");
+        let datetime = SasFormat::iso(FormatKind::DateTime).unwrap();
+        assert_eq!(
+            datetime.render(&Value::Number(86_400.5)),
+            "1960-01-02T00:00:00"
+        );
+        assert_eq!(SasFormat::iso(FormatKind::Numeric), None);
+    }
+}
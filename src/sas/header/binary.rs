@@ -1,6 +1,10 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::sas::header::ebcdic::{self, DEFAULT_EBCDIC};
 use crate::sas::{Encoding, Endianness, FileType, OsMaker, OsType, SasConstants};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct SasHeaderBinary {
     pub bytes: Vec<u8>,
 }
@@ -12,12 +16,48 @@ impl SasHeaderBinary {
         }
     }
 
+    /// The bytes at `range`, or none when the header is too short to hold
+    /// all of them, so that a truncated header fails validation instead of
+    /// panicking.
+    fn field(&self, range: Range<usize>) -> &[u8] {
+        self.bytes.get(range).unwrap_or_default()
+    }
+
+    fn byte(&self, offset: usize) -> u8 {
+        self.bytes.get(offset).copied().unwrap_or_default()
+    }
+
+    /// Header text, in EBCDIC when the file comes from a mainframe.
+    fn text(&self, range: Range<usize>) -> String {
+        let text = if self.is_ebcdic_host() {
+            DEFAULT_EBCDIC.decode(self.field(range))
+        } else {
+            String::from_utf8_lossy(self.field(range)).into_owned()
+        };
+        text.trim_end_matches('\0').to_string()
+    }
+
+    /// Whether the header's text is in EBCDIC, as in files written by SAS
+    /// on z/OS. Its release field tells, since it starts with a digit and a
+    /// point.
+    pub fn is_ebcdic_host(&self) -> bool {
+        let a1 = self.get_a1() as usize;
+        let a2 = self.get_a2() as usize;
+        ebcdic::is_ebcdic_release(self.field(216 + a1 + a2..224 + a1 + a2))
+    }
+
+    /// A little-endian integer of `N` bytes at `offset`, or 0 when the
+    /// header is too short.
+    fn field_le<const N: usize>(&self, offset: usize) -> [u8; N] {
+        self.field(offset..offset + N).try_into().unwrap_or([0; N])
+    }
+
     pub fn get_magic_number_from_header(&self) -> &[u8] {
-        &self.bytes[0..32]
+        self.field(0..32)
     }
 
     pub fn get_alignment_from_header1(&self) -> u8 {
-        if self.bytes[32] == 0x33 {
+        if self.byte(32) == 0x33 {
             4
         } else {
             0
@@ -25,7 +65,7 @@ impl SasHeaderBinary {
     }
 
     pub fn get_alignment_from_header2(&self) -> u8 {
-        if self.bytes[35] == 0x33 {
+        if self.byte(35) == 0x33 {
             4
         } else {
             0
@@ -41,41 +81,47 @@ impl SasHeaderBinary {
     }
 
     pub fn get_endianness_from_header(&self) -> Result<Endianness, String> {
-        Endianness::from_u8(self.bytes[37])
+        Endianness::from_u8(self.byte(37))
     }
 
+    /// Mainframe files are told apart by their header text, since the OS
+    /// type byte only distinguishes Unix from Windows.
     pub fn get_os_type_from_header(&self) -> Result<OsType, String> {
-        let char_value = self.bytes[39] as char;
-        OsType::from_u8(char_value as u8)
+        if self.is_ebcdic_host() {
+            return Ok(OsType::ZOs);
+        }
+        OsType::from_server_type(&self.get_host_sas_server_type_from_header())
+            .map_or_else(|| OsType::from_u8(self.byte(39)), Ok)
     }
 
+    /// The encoding code at byte 70. Codes for the EBCDIC code pages are not
+    /// known, so for mainframe files this is an error and the code page has
+    /// to be given instead.
     pub fn get_character_encoding_from_header(&self) -> Result<Encoding, String> {
-        let encoding_byte = self.bytes[70];
-        Encoding::from_u8(encoding_byte)
+        if self.is_ebcdic_host() {
+            return Err(format!(
+                "Mainframe file whose EBCDIC code page is not known from code {}; \
+                 give the encoding to read it",
+                self.byte(70)
+            ));
+        }
+        Encoding::from_u8(self.byte(70))
     }
 
     pub fn get_sas_file_string_from_header(&self) -> &[u8] {
-        &self.bytes[84..92]
+        self.field(84..92)
     }
 
     pub fn get_sas_file_from_header_as_str(&self) -> String {
-        String::from_utf8_lossy(self.get_sas_file_string_from_header())
-            .trim_end_matches('\0')
-            .to_string()
+        self.text(84..92)
     }
 
     pub fn get_sas_filename_from_header(&self) -> String {
-        String::from_utf8_lossy(&self.bytes[92..156])
-            .trim_end_matches('\0')
-            .trim_end()
-            .to_string()
+        self.text(92..156).trim_end().to_string()
     }
 
     pub fn is_56_to_64_valid(&self) -> bool {
-        let positions_32_to_40 = &self.bytes[32..40];
-        let positions_56_to_64 = &self.bytes[56..64];
-
-        (0..8).all(|i| positions_32_to_40[i] == positions_56_to_64[i])
+        self.bytes.len() >= 64 && self.field(32..40) == self.field(56..64)
     }
 
     pub fn validate_sas_file(&self) -> Result<(), String> {
@@ -104,10 +150,7 @@ impl SasHeaderBinary {
     }
 
     pub fn get_raw_ascii_file_type_from_header(&self) -> Result<String, String> {
-        let positions_156_to_164 = &self.bytes[156..164];
-        let ascii_file_type = String::from_utf8_lossy(positions_156_to_164)
-            .trim_end_matches('\0')
-            .to_string();
+        let ascii_file_type = self.text(156..164);
 
         if ascii_file_type.is_empty() {
             return Err("Invalid ASCII file type".to_string());
@@ -127,9 +170,7 @@ impl SasHeaderBinary {
     }
 
     pub fn get_creation_timestamp_from_header(&self) -> f64 {
-        let ts_offset = self.get_creation_ts_offset();
-        let timestamp_bytes = &self.bytes[ts_offset..ts_offset + 8];
-        f64::from_le_bytes(timestamp_bytes.try_into().unwrap())
+        f64::from_le_bytes(self.field_le(self.get_creation_ts_offset()))
     }
 
     fn get_modification_ts_offset(&self) -> usize {
@@ -137,9 +178,7 @@ impl SasHeaderBinary {
     }
 
     pub fn get_modification_timestamp_from_header(&self) -> f64 {
-        let ts_offset = self.get_modification_ts_offset();
-        let timestamp_bytes = &self.bytes[ts_offset..ts_offset + 8];
-        f64::from_le_bytes(timestamp_bytes.try_into().unwrap())
+        f64::from_le_bytes(self.field_le(self.get_modification_ts_offset()))
     }
 
     fn get_header_len_offset(&self) -> usize {
@@ -147,27 +186,15 @@ impl SasHeaderBinary {
     }
 
     pub fn get_header_length_from_header(&self) -> usize {
-        let header_len_offset = self.get_header_len_offset();
-        let bytes = &self.bytes[header_len_offset..(header_len_offset + 4)];
-
-        i32::from_le_bytes(bytes.try_into().unwrap()) as usize
+        u32::from_le_bytes(self.field_le(self.get_header_len_offset())) as usize
     }
 
     fn get_page_size_len_offset(&self) -> usize {
         200 + self.get_a1() as usize
     }
 
-    fn get_page_size_len_slice_range(&self) -> std::ops::Range<usize> {
-        let page_size_offset = self.get_page_size_len_offset();
-        let page_size_max_offset = page_size_offset + 4;
-        page_size_offset..page_size_max_offset
-    }
-
     pub fn get_page_size_from_header(&self) -> usize {
-        let page_size_slice_range = self.get_page_size_len_slice_range();
-        let bytes = &self.bytes[page_size_slice_range];
-
-        i32::from_le_bytes(bytes.try_into().unwrap()) as usize
+        u32::from_le_bytes(self.field_le(self.get_page_size_len_offset())) as usize
     }
 
     fn get_page_count_len_min_offset(&self) -> usize {
@@ -175,10 +202,10 @@ impl SasHeaderBinary {
     }
 
     fn get_page_count_len(&self) -> usize {
-        match self.get_a2() {
-            0 => 4,
-            4 => 8,
-            _ => panic!("Invalid a2 value: {}", self.get_a2()),
+        if self.get_a2() == 4 {
+            8
+        } else {
+            4
         }
     }
 
@@ -191,17 +218,9 @@ impl SasHeaderBinary {
     /// The integer ranges from 204 + `a1` to 204 + `a1` + `a2`.
     pub fn get_page_count_from_header(&self) -> usize {
         let pc_min_offset = self.get_page_count_len_min_offset();
-        let pc_len = self.get_page_count_len();
-        let bytes = &self.bytes[pc_min_offset..pc_min_offset + pc_len];
-
-        match pc_len {
-            4 => i32::from_le_bytes(bytes.try_into().unwrap()) as usize,
-            8 => i64::from_le_bytes(bytes.try_into().unwrap()) as usize,
-            _ => panic!(
-                "Invalid page count length: got {}, expected 4 or 8.\nself.get_a2(): {}",
-                pc_len,
-                self.get_a2()
-            ),
+        match self.get_page_count_len() {
+            8 => u64::from_le_bytes(self.field_le(pc_min_offset)) as usize,
+            _ => u32::from_le_bytes(self.field_le(pc_min_offset)) as usize,
         }
     }
 
@@ -210,10 +229,7 @@ impl SasHeaderBinary {
         let a2 = self.get_a2() as usize;
         let sas_release_offset = 216 + a1 + a2;
 
-        let sas_release_bytes = &self.bytes[sas_release_offset..sas_release_offset + 8];
-        String::from_utf8_lossy(sas_release_bytes)
-            .trim_end_matches('\0')
-            .to_string()
+        self.text(sas_release_offset..sas_release_offset + 8)
     }
 
     pub fn get_host_sas_server_type_from_header(&self) -> String {
@@ -221,11 +237,7 @@ impl SasHeaderBinary {
         let a2 = self.get_a2() as usize;
         let host_sas_server_type_offset = 224 + a1 + a2;
 
-        let host_sas_server_type_bytes =
-            &self.bytes[host_sas_server_type_offset..host_sas_server_type_offset + 16];
-        String::from_utf8_lossy(host_sas_server_type_bytes)
-            .trim_end_matches('\0')
-            .to_string()
+        self.text(host_sas_server_type_offset..host_sas_server_type_offset + 16)
     }
 
     pub fn get_os_version_number_from_header(&self) -> Option<String> {
@@ -235,12 +247,12 @@ impl SasHeaderBinary {
         let os_version_number_len = 16;
         let os_version_number_end = os_version_number_start + os_version_number_len;
 
-        let os_version_number_bytes = &self.bytes[os_version_number_start..os_version_number_end];
-        let os_version_number = String::from_utf8_lossy(os_version_number_bytes)
-            .trim_end_matches('\0')
-            .to_string();
+        let os_version_number = self.text(os_version_number_start..os_version_number_end);
 
-        if self.get_os_type_from_header().unwrap() != OsType::Unix {
+        if !matches!(
+            self.get_os_type_from_header(),
+            Ok(OsType::Unix | OsType::ZOs)
+        ) {
             None
         } else {
             Some(os_version_number)
@@ -254,20 +266,12 @@ impl SasHeaderBinary {
         let os_version_len = 16;
         let os_version_end = os_version_start + os_version_len;
 
-        let os_version_bytes = &self.bytes[os_version_start..os_version_end];
-        let os_version_str = String::from_utf8_lossy(os_version_bytes)
-            .trim_end_matches('\0')
-            .to_string();
+        let os_version_str = self.text(os_version_start..os_version_end);
 
-        let os_version = OsMaker::from_ascii(os_version_str);
-
-        if self.get_os_type_from_header().unwrap() != OsType::Unix {
+        if self.get_os_type_from_header() != Ok(OsType::Unix) {
             None
         } else {
-            match os_version {
-                Ok(os_version) => Some(os_version),
-                Err(_) => None,
-            }
+            OsMaker::from_ascii(os_version_str).ok()
         }
     }
 }
@@ -301,6 +305,26 @@ mod tests {
         assert_eq!(header.bytes, bytes);
     }
 
+    #[test]
+    fn can_read_truncated_header_without_panicking() {
+        let bytes = include_bytes!("../../../test/hadley.sas7bdat");
+        let header = SasHeaderBinary::new(&bytes[..50]);
+
+        assert_eq!(header.get_magic_number_from_header(), &bytes[..32]);
+        assert_eq!(header.get_sas_file_from_header_as_str(), "");
+        assert!(!header.is_56_to_64_valid());
+        assert_eq!(header.get_page_size_from_header(), 0);
+        assert_eq!(header.get_page_count_from_header(), 0);
+        assert_eq!(header.get_creation_timestamp_from_header(), 0.0);
+        assert_eq!(header.get_sas_release_from_header(), "");
+        assert_eq!(header.get_os_maker_or_version_from_header(), None);
+        assert!(header.get_ascii_file_type_from_header().is_err());
+        assert_eq!(
+            header.validate_sas_file(),
+            Err("Invalid SAS file".to_string())
+        );
+    }
+
     #[test]
     fn can_validate_sas_file() {
         let header = header_from_test_file();
@@ -372,6 +396,28 @@ mod tests {
         assert_eq!(endianness_from_bytes, endianness_from_header);
     }
 
+    #[test]
+    fn can_detect_mainframe_headers() {
+        let fixture = crate::sas::SasFixtureBuilder::default()
+            .encoding(Encoding::Ibm1047)
+            .build()
+            .unwrap();
+        let bytes = fixture.to_bytes().unwrap();
+        let header = SasHeaderBinary::new(&bytes[..8192]);
+
+        assert!(header.is_ebcdic_host());
+        assert!(header.validate_sas_file().is_ok());
+        assert_eq!(header.get_os_type_from_header(), Ok(OsType::ZOs));
+        assert!(header.get_character_encoding_from_header().is_err());
+        assert_eq!(header.get_sas_release_from_header(), "9.0401M0");
+        assert_eq!(
+            header.get_host_sas_server_type_from_header().trim_end(),
+            "z/OS"
+        );
+        assert_eq!(header.get_sas_filename_from_header(), "FIXTURE");
+        assert!(!header_from_test_file().is_ebcdic_host());
+    }
+
     #[test]
     fn can_get_os_type_from_header_when_unix() {
         let mut bytes = vec![0_u8; 8192];
@@ -826,6 +872,22 @@ mod tests {
         test_os_version_number(4, 4);
     }
 
+    #[test]
+    fn can_read_unknown_os_type_and_maker_without_panicking() {
+        let mut bytes = vec![0_u8; 8192];
+        fix_bytes_for_a1_4(bytes.as_mut_slice());
+        fix_bytes_for_a2_4(bytes.as_mut_slice());
+        bytes[39] = b'1';
+        bytes[264..267].copy_from_slice(b"XYZ");
+
+        let header = SasHeaderBinary::new(&bytes);
+        assert_eq!(header.get_os_maker_or_version_from_header(), None);
+
+        bytes[39] = b'9';
+        let header = SasHeaderBinary::new(&bytes);
+        assert!(header.get_os_type_from_header().is_err());
+    }
+
     #[test]
     fn can_get_os_maker_or_version_from_header() {
         let mut bytes = vec![0_u8; 8192];
@@ -834,6 +896,7 @@ mod tests {
 
         fix_bytes_for_a1_4(bytes.as_mut_slice());
         fix_bytes_for_a2_4(bytes.as_mut_slice());
+        bytes[39] = b'1'; // Unix
 
         let start: usize = 256 + a1 as usize + a2 as usize;
         let end: usize = start + 16;
@@ -1,3 +1,7 @@
+use std::str::FromStr;
+
+use crate::sas::header::ebcdic;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Encoding {
     Utf8,
@@ -71,9 +75,39 @@ pub enum Encoding {
     Maccyrillic,
     Macromania,
     ShiftJisx0213,
+    Ibm037,
+    Ibm273,
+    Ibm500,
+    Ibm1047,
+    Ibm1140,
 }
 
 impl Encoding {
+    /// The mainframe code pages. Their header codes are not known, so files
+    /// written in them are recognized by their header text instead, see
+    /// [`SasHeaderBinary::is_ebcdic_host`](crate::sas::SasHeaderBinary::is_ebcdic_host),
+    /// and read only when told which one they are in.
+    pub const EBCDIC: [Encoding; 5] = [
+        Encoding::Ibm037,
+        Encoding::Ibm273,
+        Encoding::Ibm500,
+        Encoding::Ibm1047,
+        Encoding::Ibm1140,
+    ];
+
+    pub fn is_ebcdic(&self) -> bool {
+        Encoding::EBCDIC.contains(self)
+    }
+
+    /// The blank that pads character cells.
+    pub fn blank(&self) -> u8 {
+        if self.is_ebcdic() {
+            0x40
+        } else {
+            b' '
+        }
+    }
+
     pub fn from_u8(value: u8) -> Result<Self, String> {
         let output = match value {
             0 => Encoding::Windows1252,
@@ -155,10 +189,231 @@ impl Encoding {
         };
         Ok(output)
     }
+
+    /// The code stored at byte 70 of the header for this encoding, or `None`
+    /// for the EBCDIC code pages, whose codes are not known.
+    pub fn to_u8(&self) -> Option<u8> {
+        let code = match self {
+            Encoding::Windows1252 => 62,
+            Encoding::Utf8 => 20,
+            Encoding::UsAscii => 28,
+            Encoding::Iso8859_1 => 29,
+            Encoding::Iso8859_2 => 30,
+            Encoding::Iso8859_3 => 31,
+            Encoding::Iso8859_4 => 32,
+            Encoding::Iso8859_5 => 33,
+            Encoding::Iso8859_6 => 34,
+            Encoding::Iso8859_7 => 35,
+            Encoding::Iso8859_8 => 36,
+            Encoding::Iso8859_9 => 37,
+            Encoding::Iso8859_11 => 39,
+            Encoding::Iso8859_15 => 40,
+            Encoding::Cp437 => 41,
+            Encoding::Cp850 => 42,
+            Encoding::Cp852 => 43,
+            Encoding::Cp857 => 44,
+            Encoding::Cp858 => 45,
+            Encoding::Cp862 => 46,
+            Encoding::Cp864 => 47,
+            Encoding::Cp865 => 48,
+            Encoding::Cp866 => 49,
+            Encoding::Cp869 => 50,
+            Encoding::Cp874 => 51,
+            Encoding::Cp921 => 52,
+            Encoding::Cp922 => 53,
+            Encoding::Cp1129 => 54,
+            Encoding::Cp720 => 55,
+            Encoding::Cp737 => 56,
+            Encoding::Cp775 => 57,
+            Encoding::Cp860 => 58,
+            Encoding::Cp863 => 59,
+            Encoding::Windows1250 => 60,
+            Encoding::Windows1251 => 61,
+            Encoding::Windows1253 => 63,
+            Encoding::Windows1254 => 64,
+            Encoding::Windows1255 => 65,
+            Encoding::Windows1256 => 66,
+            Encoding::Windows1257 => 67,
+            Encoding::Windows1258 => 68,
+            Encoding::Macroman => 69,
+            Encoding::Macarabic => 70,
+            Encoding::Machebrew => 71,
+            Encoding::Macgreek => 72,
+            Encoding::Macthai => 73,
+            Encoding::Macturkish => 75,
+            Encoding::Macukraine => 76,
+            Encoding::Cp950 => 118,
+            Encoding::EucTw => 119,
+            Encoding::Big5 => 123,
+            Encoding::Gb18030 => 125,
+            Encoding::Windows936 => 126,
+            Encoding::Cp1381 => 128,
+            Encoding::EucJp => 134,
+            Encoding::Cp949 => 136,
+            Encoding::Cp942 => 137,
+            Encoding::Cp932 => 138,
+            Encoding::EucKr => 140,
+            Encoding::Maciceland => 163,
+            Encoding::Iso2022Jp => 167,
+            Encoding::Iso2022Kr => 168,
+            Encoding::Iso2022Cn => 169,
+            Encoding::Iso2022CnExt => 172,
+            Encoding::Iso8859_14 => 227,
+            Encoding::Iso8859_13 => 242,
+            Encoding::Maccroatian => 245,
+            Encoding::Maccyrillic => 246,
+            Encoding::Macromania => 247,
+            Encoding::ShiftJisx0213 => 248,
+            Encoding::SasDefaultStringEncoding => 0,
+            Encoding::Ibm037
+            | Encoding::Ibm273
+            | Encoding::Ibm500
+            | Encoding::Ibm1047
+            | Encoding::Ibm1140 => return None,
+        };
+        Some(code)
+    }
+
+    fn to_encoding_rs(self) -> Option<&'static encoding_rs::Encoding> {
+        let encoding = match self {
+            Encoding::Utf8 => encoding_rs::UTF_8,
+            Encoding::UsAscii | Encoding::Windows1252 | Encoding::SasDefaultStringEncoding => {
+                encoding_rs::WINDOWS_1252
+            }
+            Encoding::Iso8859_2 => encoding_rs::ISO_8859_2,
+            Encoding::Iso8859_3 => encoding_rs::ISO_8859_3,
+            Encoding::Iso8859_4 => encoding_rs::ISO_8859_4,
+            Encoding::Iso8859_5 => encoding_rs::ISO_8859_5,
+            Encoding::Iso8859_6 => encoding_rs::ISO_8859_6,
+            Encoding::Iso8859_7 => encoding_rs::ISO_8859_7,
+            Encoding::Iso8859_8 => encoding_rs::ISO_8859_8,
+            Encoding::Iso8859_9 => encoding_rs::WINDOWS_1254,
+            Encoding::Iso8859_11 | Encoding::Cp874 => encoding_rs::WINDOWS_874,
+            Encoding::Iso8859_13 => encoding_rs::ISO_8859_13,
+            Encoding::Iso8859_14 => encoding_rs::ISO_8859_14,
+            Encoding::Iso8859_15 => encoding_rs::ISO_8859_15,
+            Encoding::Cp866 => encoding_rs::IBM866,
+            Encoding::Windows1250 => encoding_rs::WINDOWS_1250,
+            Encoding::Windows1251 => encoding_rs::WINDOWS_1251,
+            Encoding::Windows1253 => encoding_rs::WINDOWS_1253,
+            Encoding::Windows1254 => encoding_rs::WINDOWS_1254,
+            Encoding::Windows1255 => encoding_rs::WINDOWS_1255,
+            Encoding::Windows1256 => encoding_rs::WINDOWS_1256,
+            Encoding::Windows1257 => encoding_rs::WINDOWS_1257,
+            Encoding::Windows1258 => encoding_rs::WINDOWS_1258,
+            Encoding::Macroman => encoding_rs::MACINTOSH,
+            Encoding::Maccyrillic | Encoding::Macukraine => encoding_rs::X_MAC_CYRILLIC,
+            Encoding::Cp950 | Encoding::Big5 => encoding_rs::BIG5,
+            Encoding::Gb18030 => encoding_rs::GB18030,
+            Encoding::Windows936 | Encoding::Cp1381 => encoding_rs::GBK,
+            Encoding::EucJp => encoding_rs::EUC_JP,
+            Encoding::Cp932 | Encoding::Cp942 | Encoding::ShiftJisx0213 => encoding_rs::SHIFT_JIS,
+            Encoding::EucKr | Encoding::Cp949 => encoding_rs::EUC_KR,
+            Encoding::Iso2022Jp => encoding_rs::ISO_2022_JP,
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    /// Decode bytes stored in this encoding. Code pages without a decoder
+    /// fall back to Windows-1252, which agrees with them on ASCII.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        if *self == Encoding::Iso8859_1 {
+            return bytes.iter().map(|&b| b as char).collect();
+        }
+        if let Some(table) = ebcdic::table(*self) {
+            return ebcdic::decode(table, bytes);
+        }
+
+        let encoding = self.to_encoding_rs().unwrap_or(encoding_rs::WINDOWS_1252);
+        encoding.decode_without_bom_handling(bytes).0.into_owned()
+    }
+
+    /// Decode bytes stored in this encoding, or `None` if they hold a
+    /// sequence the encoding does not define. Code pages without a decoder
+    /// decode as Windows-1252, as in [`Encoding::decode`].
+    pub fn try_decode(&self, bytes: &[u8]) -> Option<String> {
+        if *self == Encoding::Iso8859_1 || self.is_ebcdic() {
+            return Some(self.decode(bytes));
+        }
+
+        let encoding = self.to_encoding_rs().unwrap_or(encoding_rs::WINDOWS_1252);
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        (!had_errors).then(|| text.into_owned())
+    }
+
+    /// Whether bytes stored in this encoding decode without replacement
+    /// characters. Code pages without a decoder are given the benefit of the
+    /// doubt.
+    pub fn is_decodable(&self, bytes: &[u8]) -> bool {
+        match self.to_encoding_rs() {
+            Some(encoding) if *self != Encoding::Iso8859_1 => {
+                !encoding.decode_without_bom_handling(bytes).1
+            }
+            _ => true,
+        }
+    }
+
+    /// Encode text in this encoding, failing on characters it cannot
+    /// represent rather than substituting them.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let unmappable = || format!("Cannot encode {:?} as {:?}", text, self);
+
+        match self {
+            Encoding::Iso8859_1 => text
+                .chars()
+                .map(|c| u8::try_from(c as u32).map_err(|_| unmappable()))
+                .collect(),
+            Encoding::UsAscii if !text.is_ascii() => Err(unmappable()),
+            _ if self.is_ebcdic() => ebcdic::table(*self)
+                .and_then(|table| ebcdic::encode(table, text))
+                .ok_or_else(unmappable),
+            _ => {
+                let encoding = self.to_encoding_rs().ok_or_else(unmappable)?;
+                let (bytes, _, had_errors) = encoding.encode(text);
+                if had_errors {
+                    Err(unmappable())
+                } else {
+                    Ok(bytes.into_owned())
+                }
+            }
+        }
+    }
 }
 
-#[cfg(test)]
+impl FromStr for Encoding {
+    type Err = String;
+
+    /// Parse a name such as `utf-8`, `Windows1252` or `iso-8859-1`, ignoring
+    /// case and punctuation. `latin1` and `ascii` are accepted too, and SAS
+    /// names such as `ebcdic273` and `open_ed-1047` for the EBCDIC pages.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let wanted = match normalize(name).as_str() {
+            "latin1" => "iso88591".to_string(),
+            "ascii" => "usascii".to_string(),
+            other => match other
+                .strip_prefix("ebcdic")
+                .or_else(|| other.strip_prefix("opened"))
+            {
+                Some(page) => format!("ibm{}", page),
+                None => other.to_string(),
+            },
+        };
+        (0..=u8::MAX)
+            .filter_map(|code| Encoding::from_u8(code).ok())
+            .chain(Encoding::EBCDIC)
+            .find(|encoding| normalize(&format!("{:?}", encoding)) == wanted)
+            .ok_or_else(|| format!("Unknown encoding {:?}", name))
+    }
+}
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -181,6 +436,88 @@ mod tests {
         assert_eq!(encoding, Ok(Encoding::Iso8859_1));
     }
 
+    #[test]
+    fn can_round_trip_encoding_codes() {
+        for code in [0_u8, 20, 28, 29, 62, 125, 138, 248] {
+            let encoding = Encoding::from_u8(code).unwrap();
+            assert_eq!(Encoding::from_u8(encoding.to_u8().unwrap()), Ok(encoding));
+        }
+    }
+
+    #[test]
+    fn can_decode_windows1252() {
+        let text = Encoding::Windows1252.decode(&[0x43, 0x61, 0x66, 0xe9, 0x80]);
+        assert_eq!(text, "Café€");
+    }
+
+    #[test]
+    fn can_decode_latin1() {
+        let text = Encoding::Iso8859_1.decode(&[0x43, 0x61, 0x66, 0xe9, 0x80]);
+        assert_eq!(text, "Café\u{80}");
+    }
+
+    #[test]
+    fn can_try_to_decode() {
+        assert_eq!(
+            Encoding::Utf8.try_decode(b"caf\xc3\xa9"),
+            Some("café".to_string())
+        );
+        assert_eq!(Encoding::Utf8.try_decode(b"caf\xe9"), None);
+        assert_eq!(
+            Encoding::Iso8859_1.try_decode(b"caf\xe9"),
+            Some("café".to_string())
+        );
+    }
+
+    #[test]
+    fn can_parse_encoding_names() {
+        assert_eq!("UTF-8".parse(), Ok(Encoding::Utf8));
+        assert_eq!("windows-1252".parse(), Ok(Encoding::Windows1252));
+        assert_eq!("ISO_8859_15".parse(), Ok(Encoding::Iso8859_15));
+        assert_eq!("latin1".parse(), Ok(Encoding::Iso8859_1));
+        assert_eq!("IBM-037".parse(), Ok(Encoding::Ibm037));
+        assert_eq!("ebcdic273".parse(), Ok(Encoding::Ibm273));
+        assert_eq!("open_ed-1047".parse(), Ok(Encoding::Ibm1047));
+        assert!("klingon".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn can_decode_and_encode_ebcdic() {
+        // "Grüße [1]" in IBM-1047
+        let bytes = [0xC7, 0x99, 0xDC, 0x59, 0x85, 0x40, 0xAD, 0xF1, 0xBD];
+        assert_eq!(Encoding::Ibm1047.decode(&bytes), "Grüße [1]");
+        assert_eq!(Encoding::Ibm1047.encode("Grüße [1]"), Ok(bytes.to_vec()));
+        assert_eq!(Encoding::Ibm037.decode(&[0xBA, 0xBB]), "[]");
+        assert_eq!(Encoding::Ibm273.decode(&[0x4A, 0xC1]), "ÄA");
+        assert_eq!(Encoding::Ibm1140.decode(&[0x9F]), "€");
+        assert_eq!(
+            Encoding::Ibm037.try_decode(&[0xFF]),
+            Some("\u{9F}".to_string())
+        );
+        assert!(Encoding::Ibm037.encode("€").is_err());
+        assert!(Encoding::EBCDIC.iter().all(|page| page.to_u8().is_none()));
+
+        for encoding in Encoding::EBCDIC {
+            let bytes: Vec<u8> = (0..=u8::MAX).collect();
+            assert_eq!(encoding.encode(&encoding.decode(&bytes)), Ok(bytes));
+        }
+    }
+
+    #[test]
+    fn can_encode_and_decode_utf8() {
+        let bytes = Encoding::Utf8.encode("Zürich").unwrap();
+        assert_eq!(bytes, "Zürich".as_bytes());
+        assert_eq!(Encoding::Utf8.decode(&bytes), "Zürich");
+    }
+
+    #[test]
+    fn cannot_encode_unmappable_characters() {
+        assert!(Encoding::Windows1252.encode("日本").is_err());
+        assert!(Encoding::UsAscii.encode("é").is_err());
+        assert!(Encoding::Iso8859_1.encode("€").is_err());
+        assert_eq!(Encoding::Iso8859_1.encode("é"), Ok(vec![0xe9]));
+    }
+
     #[test]
     fn can_get_encoding_from_invalid_code() {
         let encoding = Encoding::from_u8(250);
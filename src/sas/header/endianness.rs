@@ -38,5 +38,4 @@ mod tests {
     fn test_endianness_from_u8_panics_when_invalid_code_unwrapped() {
         Endianness::from_u8(2).unwrap();
     }
-
 }
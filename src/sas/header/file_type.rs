@@ -1,11 +1,15 @@
+use std::str::FromStr;
+
 #[derive(Debug, PartialEq)]
 pub enum FileType {
     Data,
     Catalog,
 }
 
-impl FileType {
-    pub fn from_str(value: &str) -> Result<FileType, String> {
+impl FromStr for FileType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<FileType, String> {
         match value.to_lowercase().trim() {
             "data" => Ok(FileType::Data),
             "catalog" => Ok(FileType::Catalog),
@@ -21,7 +25,6 @@ impl FileType {
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
 
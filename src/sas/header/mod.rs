@@ -1,5 +1,8 @@
+use crate::sas::errors::Error;
+
 pub mod alignment;
 pub mod binary;
+pub mod ebcdic;
 pub mod encoding;
 pub mod endianness;
 pub mod file_type;
@@ -14,7 +17,7 @@ pub use endianness::Endianness;
 pub use file_type::FileType;
 pub use os_maker::OsMaker;
 pub use os_type::OsType;
-pub use unknowns::SasHeaderUnknowns;
+pub use unknowns::{SasHeaderUnknowns, UnknownField, UnknownsSurvey};
 
 #[derive(Debug, PartialEq)]
 pub struct SasHeader {
@@ -44,9 +47,13 @@ impl SasHeader {
         &self.binary.bytes
     }
 
-    pub fn read_magic_number(&mut self) {
+    pub fn read_magic_number(&mut self) -> Result<(), Error> {
         let magic_number = self.binary.get_magic_number_from_header();
-        self.magic_number = Some(magic_number.try_into().unwrap());
+        let magic_number = magic_number.try_into().map_err(|_| {
+            Error::InvalidHeader(format!("Header is only {} bytes", self.bytes().len()))
+        })?;
+        self.magic_number = Some(magic_number);
+        Ok(())
     }
 
     pub fn read_alignment(&mut self) {
@@ -81,12 +88,13 @@ impl SasHeader {
         alignment.0.value
     }
 
-    pub fn read_endianness(&mut self) {
-        let endianness = self.binary.get_endianness_from_header();
-        match endianness {
-            Ok(endianness) => self.endianness = Some(endianness),
-            Err(e) => panic!("{}", e),
-        }
+    pub fn read_endianness(&mut self) -> Result<(), Error> {
+        let endianness = self
+            .binary
+            .get_endianness_from_header()
+            .map_err(Error::InvalidHeader)?;
+        self.endianness = Some(endianness);
+        Ok(())
     }
 
     pub fn read_unknowns(&mut self) {
@@ -96,17 +104,17 @@ impl SasHeader {
         self.unknowns = Some(header_unknowns);
     }
 
-    pub fn read_character_encoding(&mut self) {
-        let encoding = self.binary.get_character_encoding_from_header();
-        match encoding {
-            Ok(encoding) => self.encoding = Some(encoding),
-            Err(e) => panic!("{}", e),
-        }
+    pub fn read_character_encoding(&mut self) -> Result<(), Error> {
+        let encoding = self
+            .binary
+            .get_character_encoding_from_header()
+            .map_err(Error::InvalidHeader)?;
+        self.encoding = Some(encoding);
+        Ok(())
     }
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
     use crate::sas::SasConstants;
@@ -155,7 +163,7 @@ mod tests {
     fn can_read_magic_number() {
         let bytes = include_bytes!("../../../test/hadley.sas7bdat");
         let mut sas_header = SasHeader::new(bytes);
-        sas_header.read_magic_number();
+        sas_header.read_magic_number().unwrap();
 
         let magic_number = sas_header.magic_number.unwrap();
         let constants = SasConstants::new();
@@ -191,7 +199,7 @@ mod tests {
     fn can_read_endianess() {
         let bytes = include_bytes!("../../../test/hadley.sas7bdat");
         let mut sas_header = SasHeader::new(bytes);
-        sas_header.read_endianness();
+        sas_header.read_endianness().unwrap();
 
         let endianness = sas_header.endianness;
 
@@ -264,7 +272,7 @@ mod tests {
         bytes[70] = 20; // This makes the character encoding to be UTF-8
 
         let mut sas_header = SasHeader::new(bytes.as_slice());
-        sas_header.read_character_encoding();
+        sas_header.read_character_encoding().unwrap();
 
         assert_eq!(sas_header.encoding, Some(Encoding::Utf8));
     }
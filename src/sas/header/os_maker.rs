@@ -11,10 +11,10 @@ impl OsMaker {
             "SUN" => Ok(OsMaker::Sun),
             "IBM" => Ok(OsMaker::Ibm),
             "WIN" => Ok(OsMaker::Win),
-            _ => panic!(
+            _ => Err(format!(
                 "Unknown OS maker code from binary: {}. Expected either SUN, IBM, or WIN.",
                 value
-            ),
+            )),
         }
     }
 }
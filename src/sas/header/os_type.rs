@@ -1,15 +1,26 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum OsType {
     Windows,
     Unix,
+    /// IBM mainframes: z/OS and its predecessors OS/390 and MVS.
+    ZOs,
 }
 
 impl OsType {
+    /// The mainframe, from the host name SAS stores as the server type
+    /// (its `SYSSCPL`). Other hosts are told apart by the OS type byte.
+    pub fn from_server_type(server_type: &str) -> Option<Self> {
+        match server_type.trim() {
+            "z/OS" | "OS/390" | "MVS" => Some(OsType::ZOs),
+            _ => None,
+        }
+    }
+
     pub fn from_char(value: char) -> Result<Self, String> {
         match value {
             '1' => Ok(OsType::Unix),
             '2' => Ok(OsType::Windows),
-            _ => panic!("Unknown OS type code from binary: {}. Expected either 1 for Unix or 2 for Windows.", value),
+            _ => Err(format!("Unknown OS type code from binary: {}. Expected either 1 for Unix or 2 for Windows.", value)),
         }
     }
 
@@ -18,7 +29,7 @@ impl OsType {
         match ascii_value {
             '1' => Ok(OsType::Unix),
             '2' => Ok(OsType::Windows),
-            _ => panic!("Unknown OS type code from binary: {}. Expected either 1 for Unix or 2 for Windows.", ascii_value),
+            _ => Err(format!("Unknown OS type code from binary: {}. Expected either 1 for Unix or 2 for Windows.", ascii_value)),
         }
     }
 }
@@ -53,6 +64,13 @@ mod tests {
         assert_eq!(os_type, OsType::Windows);
     }
 
+    #[test]
+    fn test_os_type_from_server_type() {
+        assert_eq!(OsType::from_server_type("z/OS"), Some(OsType::ZOs));
+        assert_eq!(OsType::from_server_type("OS/390  "), Some(OsType::ZOs));
+        assert_eq!(OsType::from_server_type("Linux"), None);
+    }
+
     #[test]
     #[should_panic(
         expected = "Unknown OS type code from binary: 3. Expected either 1 for Unix or 2 for Windows."
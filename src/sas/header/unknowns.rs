@@ -1,22 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
 
-use crate::sas::Alignment;
+use crate::sas::errors::Error;
+use crate::sas::{Alignment, SasHeaderBinary, SasProperties};
 
-pub type InnerMap = HashMap<String, usize>;
-pub type UnknownsMap = HashMap<usize, InnerMap>;
-
-fn make_inner_hash_map(start: usize, end: usize, value: usize) -> InnerMap {
-    let mut map = HashMap::new();
-    map.insert("range_start".to_string(), start);
-    map.insert("range_end".to_string(), end);
-    map.insert("value".to_string(), value);
-    map
+/// A run of header bytes whose meaning is not documented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField {
+    pub range: Range<usize>,
+    pub bytes: Vec<u8>,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct SasHeaderUnknowns {
     pub bytes: Vec<u8>,
-    pub unknowns: Option<UnknownsMap>,
+    pub unknowns: Option<Vec<UnknownField>>,
 }
 
 impl SasHeaderUnknowns {
@@ -27,25 +25,24 @@ impl SasHeaderUnknowns {
         }
     }
 
-    fn insert_to_unknowns(&mut self, position: usize, range_start: usize, range_end: usize) {
-        if self.unknowns.is_none() {
-            self.unknowns = Some(HashMap::new());
-        }
-        self.unknowns.as_mut().unwrap().insert(
-            position,
-            make_inner_hash_map(range_start, range_end, self.bytes[position] as usize),
-        );
-    }
-
     fn insert_range(&mut self, range_start: usize, range_length: usize) {
-        (range_start..range_start + range_length).for_each(|x| {
-            self.insert_to_unknowns(x, range_start, range_start + range_length);
-        });
+        let range = range_start..range_start + range_length;
+        let Some(bytes) = self.bytes.get(range.clone()) else {
+            return;
+        };
+        let field = UnknownField {
+            bytes: bytes.to_vec(),
+            range,
+        };
+        self.unknowns.get_or_insert_with(Vec::new).push(field);
     }
 
+    /// Collect the unknown ranges; those past the end of a short header are
+    /// left out.
     pub fn read(&mut self) {
-        let a1 = Alignment::from_u8(self.bytes[34]).value as usize;
-        let a2 = Alignment::from_u8(self.bytes[32]).value as usize;
+        let byte = |offset: usize| self.bytes.get(offset).copied().unwrap_or_default();
+        let a1 = Alignment::from_u8(byte(35)).value as usize;
+        let a2 = Alignment::from_u8(byte(32)).value as usize;
 
         let data = [
             (33, 2),
@@ -66,18 +63,121 @@ impl SasHeaderUnknowns {
     }
 }
 
+/// Documented header fields that unknown ranges are compared against.
+pub const KNOWN_FIELDS: [&str; 4] = ["os_type", "release", "encoding", "u64"];
+
+/// One distinct value of an unknown range, with the known field values of
+/// the files it was seen in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueSummary {
+    pub bytes: Vec<u8>,
+    pub files: usize,
+    /// Distinct values of each of [`KNOWN_FIELDS`], in the same order.
+    pub known: Vec<BTreeSet<String>>,
+}
+
+/// Everything seen at one unknown range across a survey.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeSummary {
+    pub range: Range<usize>,
+    pub values: Vec<ValueSummary>,
+    /// Known fields whose value alone predicts the bytes at this range,
+    /// when the range takes more than one value.
+    pub determined_by: Vec<&'static str>,
+}
+
+/// The known field values of a file and its bytes at one unknown range.
+type Sighting<'a> = (&'a [String], &'a [u8]);
+
+/// Collects the unknown header ranges of many files, to look for what
+/// their values depend on.
+#[derive(Debug, Default)]
+pub struct UnknownsSurvey {
+    files: Vec<(Vec<String>, Vec<UnknownField>)>,
+}
+
+impl UnknownsSurvey {
+    pub fn new() -> Self {
+        UnknownsSurvey::default()
+    }
+
+    pub fn add(&mut self, header: &SasHeaderBinary) -> Result<(), Error> {
+        let properties = SasProperties::from_header(header)?;
+        let known = vec![
+            properties
+                .os_type
+                .map_or_else(|| "unknown".to_string(), |os_type| format!("{:?}", os_type)),
+            properties.release.clone(),
+            format!("{:?}", properties.encoding),
+            properties.is_u64().to_string(),
+        ];
+        let mut unknowns = SasHeaderUnknowns::new(&header.bytes);
+        unknowns.read();
+        self.files
+            .push((known, unknowns.unknowns.unwrap_or_default()));
+        Ok(())
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Tabulate each range by its distinct values, in byte order.
+    pub fn summarize(&self) -> Vec<RangeSummary> {
+        let mut ranges: BTreeMap<(usize, usize), Vec<Sighting>> = BTreeMap::new();
+        for (known, fields) in &self.files {
+            for field in fields {
+                ranges
+                    .entry((field.range.start, field.range.end))
+                    .or_default()
+                    .push((known, &field.bytes));
+            }
+        }
+
+        ranges
+            .into_iter()
+            .map(|((start, end), seen)| {
+                let mut values: BTreeMap<&[u8], ValueSummary> = BTreeMap::new();
+                for (known, bytes) in &seen {
+                    let summary = values.entry(bytes).or_insert_with(|| ValueSummary {
+                        bytes: bytes.to_vec(),
+                        files: 0,
+                        known: vec![BTreeSet::new(); KNOWN_FIELDS.len()],
+                    });
+                    summary.files += 1;
+                    for (set, value) in summary.known.iter_mut().zip(known.iter()) {
+                        set.insert(value.clone());
+                    }
+                }
+
+                let determined_by = if values.len() > 1 {
+                    (0..KNOWN_FIELDS.len())
+                        .filter(|&i| {
+                            let mut predicted: BTreeMap<&String, &[u8]> = BTreeMap::new();
+                            seen.iter().all(|(known, bytes)| {
+                                *predicted.entry(&known[i]).or_insert(bytes) == *bytes
+                            })
+                        })
+                        .map(|i| KNOWN_FIELDS[i])
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
+                RangeSummary {
+                    range: start..end,
+                    values: values.into_values().collect(),
+                    determined_by,
+                }
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_make_inner_hash_map() {
-        let map = make_inner_hash_map(1, 10, 5);
-        assert_eq!(map.get("range_start"), Some(&1));
-        assert_eq!(map.get("range_end"), Some(&10));
-        assert_eq!(map.get("value"), Some(&5));
-    }
-
     #[test]
     fn test_sas_header_unknowns_new() {
         let bytes = vec![0, 1, 2, 3, 4, 5];
@@ -87,36 +187,77 @@ mod tests {
     }
 
     #[test]
-    fn test_insert_to_unknowns() {
+    fn test_insert_range() {
         let bytes = vec![0, 1, 2, 3, 4, 5];
         let mut header = SasHeaderUnknowns::new(&bytes);
-        header.insert_to_unknowns(1, 0, 2);
-        let unknowns = header.unknowns.unwrap();
-        let inner_map = unknowns.get(&1).unwrap();
-        assert_eq!(inner_map.get("range_start"), Some(&0));
-        assert_eq!(inner_map.get("range_end"), Some(&2));
-        assert_eq!(inner_map.get("value"), Some(&(bytes[1] as usize)));
+        header.insert_range(1, 3);
+        assert_eq!(
+            header.unknowns,
+            Some(vec![UnknownField {
+                range: 1..4,
+                bytes: vec![1, 2, 3],
+            }])
+        );
     }
 
     #[test]
-    fn test_insert_range() {
-        let bytes = vec![0, 1, 2, 3, 4, 5];
+    fn test_read() {
+        let mut bytes = vec![0; 400];
+        bytes[33] = 5;
+        bytes[35] = 0x33;
+        bytes[184] = 7;
         let mut header = SasHeaderUnknowns::new(&bytes);
-        header.insert_range(1, 3);
+        header.read();
+
         let unknowns = header.unknowns.unwrap();
-        (1..4).for_each(|i| {
-            let inner_map = unknowns.get(&i).unwrap();
-            assert_eq!(inner_map.get("range_start"), Some(&1));
-            assert_eq!(inner_map.get("range_end"), Some(&4));
-            assert_eq!(inner_map.get("value"), Some(&(bytes[i] as usize)));
-        });
+        assert_eq!(unknowns.len(), 10);
+        assert_eq!(unknowns[0].range, 33..35);
+        assert_eq!(unknowns[0].bytes, vec![5, 0]);
+        assert_eq!(unknowns[7].range, 184..200);
+        assert_eq!(unknowns[7].bytes[0], 7);
     }
 
     #[test]
-    fn test_read() {
-        let bytes = vec![0; 400];
-        let mut header = SasHeaderUnknowns::new(&bytes);
+    fn test_read_short_header() {
+        let mut header = SasHeaderUnknowns::new(&[0; 50]);
         header.read();
-        assert!(header.unknowns.is_some());
+
+        let ranges: Vec<_> = header
+            .unknowns
+            .unwrap()
+            .into_iter()
+            .map(|u| u.range)
+            .collect();
+        assert_eq!(ranges, vec![33..35, 36..37, 38..39, 40..48]);
+    }
+
+    #[test]
+    fn can_survey_unknowns_across_files() {
+        let bytes = std::fs::read("test/hadley.sas7bdat").unwrap();
+        let mut other = bytes.clone();
+        // A Unix file; bytes 32..40 are repeated at 56..64.
+        for offset in [38, 62] {
+            other[offset] = 0xaa;
+            other[offset + 1] = b'1';
+        }
+
+        let mut survey = UnknownsSurvey::new();
+        for bytes in [&bytes, &bytes, &other] {
+            survey.add(&SasHeaderBinary::new(&bytes[..1024])).unwrap();
+        }
+        let summaries = survey.summarize();
+
+        assert_eq!(survey.file_count(), 3);
+        assert_eq!(summaries.len(), 10);
+        let at = |start: usize| summaries.iter().find(|s| s.range.start == start).unwrap();
+        assert_eq!(at(33).values.len(), 1);
+        assert!(at(33).determined_by.is_empty());
+
+        let byte_38 = at(38);
+        assert_eq!(byte_38.values.len(), 2);
+        assert_eq!(byte_38.values[1].bytes, vec![0xaa]);
+        assert_eq!(byte_38.values[1].files, 1);
+        assert_eq!(byte_38.determined_by, vec!["os_type"]);
+        assert!(byte_38.values[0].known[3].contains("false"));
     }
 }
@@ -0,0 +1,176 @@
+use crate::sas::errors::Error;
+use crate::sas::Endianness;
+
+/// How integers are laid out in a sas7bdat file: whether offsets and counts
+/// are 4 or 8 bytes wide (the `a2` alignment byte) and their byte order.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Layout {
+    pub u64: bool,
+    pub endianness: Endianness,
+}
+
+impl Layout {
+    pub fn new(u64: bool, endianness: Endianness) -> Self {
+        Layout { u64, endianness }
+    }
+
+    pub fn int_len(&self) -> usize {
+        if self.u64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    pub fn signature_len(&self) -> usize {
+        self.int_len()
+    }
+
+    /// Size of the page header; the page type, block count and subheader
+    /// count are its last eight bytes.
+    pub fn page_header_size(&self) -> usize {
+        if self.u64 {
+            40
+        } else {
+            24
+        }
+    }
+
+    pub fn subheader_pointer_size(&self) -> usize {
+        if self.u64 {
+            24
+        } else {
+            12
+        }
+    }
+
+    fn slice<'a, const N: usize>(&self, bytes: &'a [u8], offset: usize) -> Result<[u8; N], Error> {
+        let end = offset.checked_add(N).ok_or(Error::OutOfBounds(offset, N))?;
+        let slice: &'a [u8] = bytes
+            .get(offset..end)
+            .ok_or(Error::OutOfBounds(offset, N))?;
+        let mut array = [0_u8; N];
+        array.copy_from_slice(slice);
+        if self.endianness == Endianness::Big {
+            array.reverse();
+        }
+        Ok(array)
+    }
+
+    pub fn read_u16(&self, bytes: &[u8], offset: usize) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.slice(bytes, offset)?))
+    }
+
+    pub fn read_u32(&self, bytes: &[u8], offset: usize) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.slice(bytes, offset)?))
+    }
+
+    pub fn read_u64(&self, bytes: &[u8], offset: usize) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.slice(bytes, offset)?))
+    }
+
+    pub fn read_f64(&self, bytes: &[u8], offset: usize) -> Result<f64, Error> {
+        Ok(f64::from_le_bytes(self.slice(bytes, offset)?))
+    }
+
+    /// Read an integer that is 4 bytes wide in 32-bit files and 8 bytes wide
+    /// in 64-bit files.
+    pub fn read_int(&self, bytes: &[u8], offset: usize) -> Result<u64, Error> {
+        if self.u64 {
+            self.read_u64(bytes, offset)
+        } else {
+            self.read_u32(bytes, offset).map(u64::from)
+        }
+    }
+
+    fn put(&self, bytes: &mut [u8], offset: usize, mut value: Vec<u8>) {
+        if self.endianness == Endianness::Big {
+            value.reverse();
+        }
+        bytes[offset..offset + value.len()].copy_from_slice(&value);
+    }
+
+    pub fn write_u16(&self, bytes: &mut [u8], offset: usize, value: u16) {
+        self.put(bytes, offset, value.to_le_bytes().to_vec());
+    }
+
+    pub fn write_u32(&self, bytes: &mut [u8], offset: usize, value: u32) {
+        self.put(bytes, offset, value.to_le_bytes().to_vec());
+    }
+
+    pub fn write_u64(&self, bytes: &mut [u8], offset: usize, value: u64) {
+        self.put(bytes, offset, value.to_le_bytes().to_vec());
+    }
+
+    pub fn write_f64(&self, bytes: &mut [u8], offset: usize, value: f64) {
+        self.put(bytes, offset, value.to_le_bytes().to_vec());
+    }
+
+    pub fn write_int(&self, bytes: &mut [u8], offset: usize, value: u64) {
+        if self.u64 {
+            self.write_u64(bytes, offset, value);
+        } else {
+            self.write_u32(bytes, offset, value as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_for_64_bit_files() {
+        let layout = Layout::new(true, Endianness::Little);
+        assert_eq!(layout.int_len(), 8);
+        assert_eq!(layout.page_header_size(), 40);
+        assert_eq!(layout.subheader_pointer_size(), 24);
+    }
+
+    #[test]
+    fn test_sizes_for_32_bit_files() {
+        let layout = Layout::new(false, Endianness::Little);
+        assert_eq!(layout.int_len(), 4);
+        assert_eq!(layout.page_header_size(), 24);
+        assert_eq!(layout.subheader_pointer_size(), 12);
+    }
+
+    #[test]
+    fn can_read_little_and_big_endian_integers() {
+        let bytes = [0x01, 0x02, 0x03, 0x04];
+        let little = Layout::new(false, Endianness::Little);
+        let big = Layout::new(false, Endianness::Big);
+
+        assert_eq!(little.read_u16(&bytes, 0), Ok(0x0201));
+        assert_eq!(big.read_u16(&bytes, 0), Ok(0x0102));
+        assert_eq!(little.read_int(&bytes, 0), Ok(0x04030201));
+        assert_eq!(big.read_int(&bytes, 0), Ok(0x01020304));
+    }
+
+    #[test]
+    fn cannot_read_past_end_of_bytes() {
+        let layout = Layout::new(true, Endianness::Little);
+        assert_eq!(layout.read_int(&[0; 4], 0), Err(Error::OutOfBounds(0, 8)));
+        assert_eq!(
+            layout.read_u16(&[0; 4], usize::MAX),
+            Err(Error::OutOfBounds(usize::MAX, 2))
+        );
+    }
+
+    #[test]
+    fn can_round_trip_writes() {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let layout = Layout::new(true, endianness);
+            let mut bytes = vec![0_u8; 32];
+            layout.write_u16(&mut bytes, 0, 513);
+            layout.write_u32(&mut bytes, 2, 70_000);
+            layout.write_int(&mut bytes, 8, 1 << 40);
+            layout.write_f64(&mut bytes, 16, -1.5);
+
+            assert_eq!(layout.read_u16(&bytes, 0), Ok(513));
+            assert_eq!(layout.read_u32(&bytes, 2), Ok(70_000));
+            assert_eq!(layout.read_int(&bytes, 8), Ok(1 << 40));
+            assert_eq!(layout.read_f64(&bytes, 16), Ok(-1.5));
+        }
+    }
+}
@@ -1,14 +1,58 @@
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+pub mod column;
 pub mod compression;
 pub mod constants;
+#[cfg(feature = "serde")]
+pub mod de;
+mod decoder;
 pub mod errors;
+pub mod format;
 pub mod header;
+pub mod layout;
+pub mod numeric;
 pub mod page_binary;
 pub mod page_type;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod properties;
+pub mod rdc;
+pub mod reader;
+pub mod rle;
+pub mod schema;
+pub mod subheader;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod validate;
+pub mod value;
+pub mod writer;
+pub mod xport;
 
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncSas7bdatReader;
+pub use column::{Column, ColumnBuilder, ColumnFormat, ColumnType};
 pub use compression::Compression;
 pub use constants::SasConstants;
+#[cfg(feature = "serde")]
+pub use de::{DeserializeRows, MaybeMissing};
+pub use decoder::RawRow;
+pub use errors::Error;
+pub use format::{FormatKind, SasFormat};
 pub use header::{
     Alignment, Encoding, Endianness, FileType, OsMaker, OsType, SasHeader, SasHeaderBinary,
+    UnknownField, UnknownsSurvey,
 };
+pub use numeric::NumericColumn;
 pub use page_binary::PageBinary;
 pub use page_type::PageType;
+#[cfg(feature = "rayon")]
+pub use parallel::{ParallelOptions, ParallelOptionsBuilder, ParallelSas7bdatReader};
+pub use properties::SasProperties;
+pub use reader::{InvalidBytes, ReaderOptions, ReaderOptionsBuilder, Sas7bdatReader};
+pub use schema::{Row, Schema, SchemaBuilder};
+#[cfg(any(test, feature = "testing"))]
+pub use testing::{SasFixture, SasFixtureBuilder};
+pub use validate::{Issue, Severity};
+pub use value::{MissingValue, Value};
+pub use writer::{Sas7bdatWriter, WriterOptions, WriterOptionsBuilder};
+pub use xport::{XportReader, XportWriter};
@@ -0,0 +1,382 @@
+use crate::sas::errors::Error;
+use crate::sas::{Endianness, MissingValue, Value};
+
+/// Numeric missing values are NaNs whose fifth byte holds the complement of
+/// a tag. Two tag schemes are in use: the ASCII code of the missing value
+/// character, or 0 for `._`, 1 for `.` and 2 through 27 for `.A` to `.Z`.
+pub fn missing_value_from_bits(bits: u64) -> MissingValue {
+    let tag = !((bits >> 40) & 0xFF) as u8;
+    let tag = match tag {
+        0 => b'_',
+        2..=27 => b'A' + (tag - 2),
+        tag => tag,
+    };
+
+    match tag {
+        b'_' | b'A'..=b'Z' => MissingValue::Special(tag as char),
+        _ => MissingValue::System,
+    }
+}
+
+/// The bit pattern SAS writes for a missing value.
+pub fn missing_value_to_bits(missing: MissingValue) -> u64 {
+    let tag: u8 = match missing {
+        MissingValue::System => 1,
+        MissingValue::Special('_') => 0,
+        MissingValue::Special(tag) => (tag as u8).wrapping_sub(b'A') + 2,
+    };
+    0xFFFF_0000_0000_0000 | (u64::from(!tag) << 40)
+}
+
+/// Decode a numeric cell. Cells narrower than 8 bytes hold the most
+/// significant bytes of the double.
+pub fn decode_numeric(bytes: &[u8], endianness: Endianness) -> Result<Value, Error> {
+    let width = bytes.len();
+    if !(1..=8).contains(&width) {
+        return Err(Error::InvalidRow(format!(
+            "Numeric cells must be 1 to 8 bytes wide, got {}",
+            width
+        )));
+    }
+
+    let mut buffer = [0_u8; 8];
+    let bits = match endianness {
+        Endianness::Little => {
+            buffer[8 - width..].copy_from_slice(bytes);
+            u64::from_le_bytes(buffer)
+        }
+        Endianness::Big => {
+            buffer[..width].copy_from_slice(bytes);
+            u64::from_be_bytes(buffer)
+        }
+    };
+
+    let value = f64::from_bits(bits);
+    if value.is_nan() {
+        Ok(Value::Missing(missing_value_from_bits(bits)))
+    } else {
+        Ok(Value::Number(value))
+    }
+}
+
+/// Encode a numeric cell into `out`, keeping the most significant bytes when
+/// the cell is narrower than 8 bytes.
+pub fn encode_numeric(value: &Value, out: &mut [u8], endianness: Endianness) -> Result<(), Error> {
+    let bits = match value {
+        Value::Number(number) if number.is_nan() => missing_value_to_bits(MissingValue::System),
+        Value::Number(number) => number.to_bits(),
+        Value::Missing(missing) => missing_value_to_bits(*missing),
+        Value::String(_) | Value::Bytes(_) => {
+            return Err(Error::InvalidRow(format!(
+                "Cannot store string {:?} in a numeric column",
+                value.to_text().unwrap_or_default()
+            )))
+        }
+    };
+
+    let width = out.len();
+    match endianness {
+        Endianness::Little => out.copy_from_slice(&bits.to_le_bytes()[8 - width..]),
+        Endianness::Big => out.copy_from_slice(&bits.to_be_bytes()[..width]),
+    }
+    Ok(())
+}
+
+/// A numeric column gathered from many rows into contiguous buffers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NumericColumn {
+    /// One value per row. Missing values keep their NaN bits, so
+    /// [`missing_value_from_bits`] still tells them apart.
+    pub values: Vec<f64>,
+    pub missing: Vec<bool>,
+}
+
+impl NumericColumn {
+    pub fn with_capacity(rows: usize) -> Self {
+        NumericColumn {
+            values: Vec::with_capacity(rows),
+            missing: Vec::with_capacity(rows),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Make room for `count` more rows and return the new slots.
+    fn extend(&mut self, count: usize) -> (&mut [f64], &mut [bool]) {
+        let start = self.values.len();
+        self.values.resize(start + count, 0.0);
+        self.missing.resize(start + count, false);
+        (&mut self.values[start..], &mut self.missing[start..])
+    }
+}
+
+/// Rows gathered per block. The cells of a block are loaded first, then
+/// converted and tested together, which the compiler turns into vector code.
+const LANES: usize = 8;
+
+fn check_cell(row_length: usize, offset: usize, width: usize) -> Result<(), Error> {
+    if width == 0 || offset + width > row_length {
+        return Err(Error::OutOfBounds(offset, width));
+    }
+    Ok(())
+}
+
+fn read_bits<const BIG_ENDIAN: bool>(cell: &[u8]) -> u64 {
+    let mut buffer = [0_u8; 8];
+    buffer.copy_from_slice(cell);
+    if BIG_ENDIAN {
+        u64::from_be_bytes(buffer)
+    } else {
+        u64::from_le_bytes(buffer)
+    }
+}
+
+/// Every NaN is a missing value, whatever its tag.
+fn is_missing_bits(bits: u64) -> bool {
+    const EXPONENT: u64 = 0x7FF0_0000_0000_0000;
+    const MANTISSA: u64 = 0x000F_FFFF_FFFF_FFFF;
+    bits & EXPONENT == EXPONENT && bits & MANTISSA != 0
+}
+
+fn gather_blocks<const BIG_ENDIAN: bool>(
+    rows: &[u8],
+    row_length: usize,
+    offset: usize,
+    values: &mut [f64],
+    missing: &mut [bool],
+) {
+    let blocks = rows
+        .chunks_exact(row_length.saturating_mul(LANES))
+        .zip(values.chunks_exact_mut(LANES))
+        .zip(missing.chunks_exact_mut(LANES));
+    for ((block, values), missing) in blocks {
+        let mut bits = [0_u64; LANES];
+        for (lane, bits) in bits.iter_mut().enumerate() {
+            let start = lane * row_length + offset;
+            *bits = read_bits::<BIG_ENDIAN>(&block[start..start + 8]);
+        }
+        for lane in 0..LANES {
+            values[lane] = f64::from_bits(bits[lane]);
+            missing[lane] = is_missing_bits(bits[lane]);
+        }
+    }
+
+    let done = values.len() / LANES * LANES;
+    let rest = rows.chunks_exact(row_length).skip(done);
+    for ((row, value), missing) in rest.zip(&mut values[done..]).zip(&mut missing[done..]) {
+        let bits = read_bits::<BIG_ENDIAN>(&row[offset..offset + 8]);
+        *value = f64::from_bits(bits);
+        *missing = is_missing_bits(bits);
+    }
+}
+
+/// Append the full-width (8 byte) numeric cell at `offset` of each row in
+/// `rows`, which hold whole rows back to back, to `column`.
+pub fn gather_numeric(
+    rows: &[u8],
+    row_length: usize,
+    offset: usize,
+    endianness: Endianness,
+    column: &mut NumericColumn,
+) -> Result<(), Error> {
+    check_cell(row_length, offset, 8)?;
+    let (values, missing) = column.extend(rows.len() / row_length);
+    match endianness {
+        Endianness::Little => gather_blocks::<false>(rows, row_length, offset, values, missing),
+        Endianness::Big => gather_blocks::<true>(rows, row_length, offset, values, missing),
+    }
+    Ok(())
+}
+
+/// Like [`gather_numeric`], but one cell at a time through
+/// [`decode_numeric`], so it also takes cells narrower than 8 bytes.
+pub fn gather_numeric_scalar(
+    rows: &[u8],
+    row_length: usize,
+    offset: usize,
+    width: usize,
+    endianness: Endianness,
+    column: &mut NumericColumn,
+) -> Result<(), Error> {
+    check_cell(row_length, offset, width)?;
+    for row in rows.chunks_exact(row_length) {
+        let (value, missing) = match decode_numeric(&row[offset..offset + width], endianness)? {
+            Value::Missing(missing) => (f64::from_bits(missing_value_to_bits(missing)), true),
+            Value::Number(number) => (number, false),
+            Value::String(_) | Value::Bytes(_) => {
+                unreachable!("numeric cells decode to numbers")
+            }
+        };
+        column.values.push(value);
+        column.missing.push(missing);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_decode_full_width_number() {
+        let bytes = 1.5_f64.to_le_bytes();
+        assert_eq!(
+            decode_numeric(&bytes, Endianness::Little),
+            Ok(Value::Number(1.5))
+        );
+
+        let bytes = 1.5_f64.to_be_bytes();
+        assert_eq!(
+            decode_numeric(&bytes, Endianness::Big),
+            Ok(Value::Number(1.5))
+        );
+    }
+
+    #[test]
+    fn can_decode_truncated_number() {
+        // 3 bytes keep the sign, exponent and first 12 bits of the mantissa
+        let bytes = 1.5_f64.to_le_bytes();
+        assert_eq!(
+            decode_numeric(&bytes[5..], Endianness::Little),
+            Ok(Value::Number(1.5))
+        );
+
+        let bytes = 1.5_f64.to_be_bytes();
+        assert_eq!(
+            decode_numeric(&bytes[..3], Endianness::Big),
+            Ok(Value::Number(1.5))
+        );
+    }
+
+    #[test]
+    fn can_decode_system_missing_value() {
+        let bytes = [0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0xFF, 0xFF];
+        assert_eq!(
+            decode_numeric(&bytes, Endianness::Little),
+            Ok(Value::Missing(MissingValue::System))
+        );
+    }
+
+    #[test]
+    fn can_decode_special_missing_values() {
+        assert_eq!(
+            missing_value_from_bits(0xFFFF_FD00_0000_0000),
+            MissingValue::Special('A')
+        );
+        assert_eq!(
+            missing_value_from_bits(0xFFFF_E400_0000_0000),
+            MissingValue::Special('Z')
+        );
+        assert_eq!(
+            missing_value_from_bits(0xFFFF_FF00_0000_0000),
+            MissingValue::Special('_')
+        );
+        // ASCII scheme
+        assert_eq!(
+            missing_value_from_bits(u64::from(!b'B') << 40 | 0xFFFF_0000_0000_0000),
+            MissingValue::Special('B')
+        );
+        assert_eq!(
+            missing_value_from_bits(f64::NAN.to_bits()),
+            MissingValue::System
+        );
+    }
+
+    #[test]
+    fn can_round_trip_missing_values() {
+        let missing = [
+            MissingValue::System,
+            MissingValue::Special('_'),
+            MissingValue::Special('A'),
+            MissingValue::Special('Z'),
+        ];
+        for value in missing {
+            let bits = missing_value_to_bits(value);
+            assert!(f64::from_bits(bits).is_nan());
+            assert_eq!(missing_value_from_bits(bits), value);
+        }
+    }
+
+    #[test]
+    fn can_encode_numbers_and_missing_values() {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            for width in [3, 4, 8] {
+                let mut out = vec![0_u8; width];
+                encode_numeric(&Value::Number(-2.5), &mut out, endianness).unwrap();
+                assert_eq!(decode_numeric(&out, endianness), Ok(Value::Number(-2.5)));
+
+                let missing = Value::Missing(MissingValue::Special('Q'));
+                encode_numeric(&missing, &mut out, endianness).unwrap();
+                assert_eq!(decode_numeric(&out, endianness), Ok(missing));
+            }
+        }
+    }
+
+    #[test]
+    fn cannot_encode_string_as_number() {
+        let mut out = [0_u8; 8];
+        let result = encode_numeric(&Value::from("abc"), &mut out, Endianness::Little);
+        assert!(result.is_err());
+    }
+
+    fn rows_of(values: &[u64], endianness: Endianness) -> Vec<u8> {
+        // Each 16 byte row holds a 4 byte label, the number, then padding.
+        let mut rows = Vec::new();
+        for (i, bits) in values.iter().enumerate() {
+            rows.extend((i as u32).to_le_bytes());
+            match endianness {
+                Endianness::Little => rows.extend(bits.to_le_bytes()),
+                Endianness::Big => rows.extend(bits.to_be_bytes()),
+            }
+            rows.extend([0xAA; 4]);
+        }
+        rows
+    }
+
+    #[test]
+    fn can_gather_numbers_and_missing_values() {
+        let mut bits: Vec<u64> = (0..21).map(|i| (i as f64 * -0.75).to_bits()).collect();
+        bits[3] = missing_value_to_bits(MissingValue::System);
+        bits[9] = missing_value_to_bits(MissingValue::Special('C'));
+        bits[20] = f64::INFINITY.to_bits();
+
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let rows = rows_of(&bits, endianness);
+            let mut column = NumericColumn::default();
+            gather_numeric(&rows, 16, 4, endianness, &mut column).unwrap();
+            let mut scalar = NumericColumn::default();
+            gather_numeric_scalar(&rows, 16, 4, 8, endianness, &mut scalar).unwrap();
+
+            assert_eq!(column.len(), 21);
+            assert_eq!(column.values[2], -1.5);
+            assert_eq!(column.values[20], f64::INFINITY);
+            let missing: Vec<usize> = (0..21).filter(|&i| column.missing[i]).collect();
+            assert_eq!(missing, vec![3, 9]);
+            assert_eq!(
+                missing_value_from_bits(column.values[9].to_bits()),
+                MissingValue::Special('C')
+            );
+            assert_eq!(column.missing, scalar.missing);
+            let same = |a: f64, b: f64| a.to_bits() == b.to_bits();
+            assert!(column
+                .values
+                .iter()
+                .zip(&scalar.values)
+                .all(|(a, b)| same(*a, *b)));
+        }
+    }
+
+    #[test]
+    fn cannot_gather_cells_past_the_row() {
+        let mut column = NumericColumn::default();
+        let result = gather_numeric(&[0; 32], 16, 12, Endianness::Little, &mut column);
+        assert_eq!(result, Err(Error::OutOfBounds(12, 8)));
+        assert!(column.is_empty());
+    }
+}
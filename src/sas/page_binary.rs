@@ -1,4 +1,10 @@
+use crate::sas::errors::Error;
+use crate::sas::layout::Layout;
+use crate::sas::subheader::SubheaderPointer;
+use crate::sas::PageType;
+
 /// Represents the raw binary for a page in a sas7bdat file.
+#[derive(Debug, PartialEq)]
 pub struct PageBinary {
     pub page_type: u16,
     pub block_count: u16,
@@ -6,3 +12,113 @@ pub struct PageBinary {
     pub subheader_pointers: Vec<u8>, // Raw bytes of subheader pointers
     pub data: Vec<u8>,               // Raw bytes of the page content
 }
+
+impl PageBinary {
+    /// Split a whole page into its header fields and pointer table. `data`
+    /// keeps the whole page, since subheader offsets are relative to it.
+    pub fn new(bytes: &[u8], layout: &Layout) -> Result<Self, Error> {
+        let header_size = layout.page_header_size();
+        let page_type = layout.read_u16(bytes, header_size - 8)?;
+        let block_count = layout.read_u16(bytes, header_size - 6)?;
+        let subheader_count = layout.read_u16(bytes, header_size - 4)?;
+
+        let pointers_len = subheader_count as usize * layout.subheader_pointer_size();
+        let subheader_pointers = bytes
+            .get(header_size..header_size + pointers_len)
+            .ok_or(Error::OutOfBounds(header_size, pointers_len))?
+            .to_vec();
+
+        Ok(PageBinary {
+            page_type,
+            block_count,
+            subheader_count,
+            subheader_pointers,
+            data: bytes.to_vec(),
+        })
+    }
+
+    pub fn page_type(&self) -> PageType {
+        PageType::from_u16(self.page_type)
+    }
+
+    pub fn pointers(&self, layout: &Layout) -> Result<Vec<SubheaderPointer>, Error> {
+        (0..self.subheader_count as usize)
+            .map(|i| {
+                SubheaderPointer::read(
+                    &self.subheader_pointers,
+                    i * layout.subheader_pointer_size(),
+                    layout,
+                )
+            })
+            .collect()
+    }
+
+    pub fn subheader(&self, pointer: &SubheaderPointer) -> Result<&[u8], Error> {
+        let end = pointer
+            .offset
+            .checked_add(pointer.length)
+            .ok_or(Error::OutOfBounds(pointer.offset, pointer.length))?;
+        self.data
+            .get(pointer.offset..end)
+            .ok_or(Error::OutOfBounds(pointer.offset, pointer.length))
+    }
+
+    /// Offset of the first row on data and mix pages. Rows follow the
+    /// pointer table, aligned to 8 bytes.
+    pub fn row_offset(&self, layout: &Layout) -> usize {
+        let offset = layout.page_header_size() + self.subheader_pointers.len();
+        if offset % 8 == 4 {
+            offset + 4
+        } else {
+            offset
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sas::{Compression, Endianness};
+
+    #[test]
+    fn can_read_page_with_pointers() {
+        let layout = Layout::new(true, Endianness::Little);
+        let mut bytes = vec![0_u8; 256];
+        layout.write_u16(&mut bytes, 32, 512);
+        layout.write_u16(&mut bytes, 34, 3);
+        layout.write_u16(&mut bytes, 36, 1);
+        let pointer = SubheaderPointer {
+            offset: 200,
+            length: 16,
+            compression: Compression::None,
+            is_compressed_data: false,
+        };
+        pointer.write(&mut bytes, 40, &layout);
+
+        let page = PageBinary::new(&bytes, &layout).unwrap();
+        assert_eq!(page.page_type(), PageType::Mix);
+        assert_eq!(page.block_count, 3);
+        assert_eq!(page.pointers(&layout), Ok(vec![pointer]));
+        assert_eq!(page.subheader(&pointer).unwrap().len(), 16);
+        assert_eq!(page.row_offset(&layout), 64);
+    }
+
+    #[test]
+    fn test_row_offset_is_aligned() {
+        let layout = Layout::new(false, Endianness::Little);
+        let mut bytes = vec![0_u8; 64];
+        layout.write_u16(&mut bytes, 20, 1);
+
+        let page = PageBinary::new(&bytes, &layout).unwrap();
+        assert_eq!(page.row_offset(&layout), 40);
+    }
+
+    #[test]
+    fn cannot_read_pointer_table_past_page() {
+        let layout = Layout::new(true, Endianness::Little);
+        let mut bytes = vec![0_u8; 64];
+        layout.write_u16(&mut bytes, 36, 10);
+
+        assert!(PageBinary::new(&bytes, &layout).is_err());
+    }
+}
@@ -4,6 +4,8 @@ pub enum PageType {
     Data,
     Mix,
     AMD,
+    Meta2,
+    Comp,
     Unknown(u16),
 }
 
@@ -11,16 +13,42 @@ impl PageType {
     pub fn from_u16(value: u16) -> Self {
         match value {
             0 => PageType::Meta,
-            256 => PageType::Data,
-            512 => PageType::Mix,
+            256 | 384 => PageType::Data,
+            512 | 640 => PageType::Mix,
             1024 => PageType::AMD,
+            16384 => PageType::Meta2,
+            36864 => PageType::Comp,
             _ => PageType::Unknown(value),
         }
     }
+
+    pub fn to_u16(&self) -> u16 {
+        match self {
+            PageType::Meta => 0,
+            PageType::Data => 256,
+            PageType::Mix => 512,
+            PageType::AMD => 1024,
+            PageType::Meta2 => 16384,
+            PageType::Comp => 36864,
+            PageType::Unknown(value) => *value,
+        }
+    }
+
+    /// Pages whose subheaders may hold metadata.
+    pub fn has_subheaders(&self) -> bool {
+        matches!(
+            self,
+            PageType::Meta | PageType::Mix | PageType::AMD | PageType::Meta2
+        )
+    }
+
+    /// Pages that hold rows after their subheader pointers.
+    pub fn has_rows(&self) -> bool {
+        matches!(self, PageType::Data | PageType::Mix)
+    }
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
 
@@ -32,4 +60,19 @@ mod tests {
         assert_eq!(PageType::from_u16(1024), PageType::AMD);
         assert_eq!(PageType::from_u16(1234), PageType::Unknown(1234));
     }
+
+    #[test]
+    fn test_page_type_with_deleted_rows() {
+        assert_eq!(PageType::from_u16(384), PageType::Data);
+        assert_eq!(PageType::from_u16(640), PageType::Mix);
+    }
+
+    #[test]
+    fn can_round_trip_page_type() {
+        for value in [0, 256, 512, 1024, 16384, 36864] {
+            assert_eq!(PageType::from_u16(value).to_u16(), value);
+        }
+        assert!(PageType::Mix.has_rows());
+        assert!(!PageType::Comp.has_subheaders());
+    }
 }
@@ -0,0 +1,274 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use derive_builder::Builder;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::sas::decoder::Decoder;
+use crate::sas::errors::Error;
+use crate::sas::properties::SasProperties;
+use crate::sas::reader::{read_page, ReaderOptions};
+use crate::sas::schema::{Row, Schema};
+use crate::sas::{Sas7bdatReader, SasHeaderBinary};
+use crate::traits::{DatasetReader, ReadSeek};
+
+/// Options for decoding pages on several threads.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct ParallelOptions {
+    /// Threads to decode on. 0 shares rayon's global pool.
+    pub threads: usize,
+    /// Pages read ahead and decoded together, which bounds the memory held
+    /// at once to about this many pages of rows.
+    pub pages_in_flight: usize,
+    pub reader: ReaderOptions,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        ParallelOptions {
+            threads: 0,
+            pages_in_flight: 64,
+            reader: ReaderOptions::default(),
+        }
+    }
+}
+
+/// Reads a sas7bdat file like [`Sas7bdatReader`], but decodes the rows of
+/// several pages at once. Rows still come back in file order.
+pub struct ParallelSas7bdatReader<R: ReadSeek> {
+    source: R,
+    decoder: Decoder,
+    pool: Option<ThreadPool>,
+    pages_in_flight: usize,
+    next_page: usize,
+    /// Rows found on the pages read so far, decoded or not.
+    rows_found: usize,
+    decoded: VecDeque<Result<Row, Error>>,
+}
+
+impl ParallelSas7bdatReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P, options: ParallelOptions) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        ParallelSas7bdatReader::new(BufReader::new(file), options)
+    }
+}
+
+impl<R: ReadSeek> ParallelSas7bdatReader<R> {
+    pub fn new(source: R, options: ParallelOptions) -> Result<Self, Error> {
+        let (source, decoder) = Sas7bdatReader::with_options(source, options.reader)?.into_parts();
+        let pool = match options.threads {
+            0 => None,
+            threads => Some(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|error| Error::Io(error.to_string()))?,
+            ),
+        };
+        Ok(ParallelSas7bdatReader {
+            source,
+            decoder,
+            pool,
+            pages_in_flight: options.pages_in_flight.max(1),
+            next_page: 0,
+            rows_found: 0,
+            decoded: VecDeque::new(),
+        })
+    }
+
+    pub fn header(&self) -> &SasHeaderBinary {
+        &self.decoder.header
+    }
+
+    pub fn properties(&self) -> &SasProperties {
+        &self.decoder.properties
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Keep only the named columns, in the given order.
+    pub fn select<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), Error> {
+        self.decoder.select(names)
+    }
+
+    /// Read the next pages and decode their rows in parallel. Pages are read
+    /// and their rows located in order; a failure is queued after the rows
+    /// of the pages before it.
+    fn decode_pages(&mut self) {
+        let properties = &self.decoder.properties;
+        let last = (self.next_page + self.pages_in_flight).min(properties.page_count);
+
+        let mut pages = Vec::new();
+        let mut failure = None;
+        for index in self.next_page..last {
+            match read_page(&mut self.source, properties, index) {
+                Ok(page) => pages.push(page),
+                Err(error) => {
+                    failure = Some(error);
+                    break;
+                }
+            }
+        }
+        self.next_page = if failure.is_some() {
+            properties.page_count
+        } else {
+            last
+        };
+
+        let decoder = &self.decoder;
+        let mut slots = Vec::new();
+        for page in &pages {
+            match decoder.row_slots(page, self.rows_found) {
+                Ok(page_slots) => {
+                    self.rows_found += page_slots.len();
+                    slots.push(page_slots);
+                }
+                Err(error) => {
+                    failure = Some(error);
+                    self.next_page = properties.page_count;
+                    break;
+                }
+            }
+        }
+
+        let decode = || {
+            slots
+                .par_iter()
+                .map(|page_slots| {
+                    page_slots
+                        .iter()
+                        .map(|&slot| decoder.decode_slot(slot))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let rows = match &self.pool {
+            Some(pool) => pool.install(decode),
+            None => decode(),
+        };
+        self.decoded.extend(rows.into_iter().flatten());
+        self.decoded.extend(failure.map(Err));
+    }
+}
+
+impl<R: ReadSeek> DatasetReader for ParallelSas7bdatReader<R> {
+    fn schema(&self) -> &Schema {
+        &self.decoder.schema
+    }
+
+    fn file_info(&self) -> Vec<(String, String)> {
+        self.decoder.file_info()
+    }
+
+    fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        loop {
+            if let Some(row) = self.decoded.pop_front() {
+                return row.map(Some);
+            }
+            let row_count = self.decoder.properties.row_count;
+            if self.rows_found >= row_count {
+                return Ok(None);
+            }
+            if self.next_page >= self.decoder.properties.page_count {
+                return Err(self.decoder.truncated(self.rows_found));
+            }
+            self.decode_pages();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::sas::{
+        Column, Compression, MissingValue, Sas7bdatWriter, Value, WriterOptionsBuilder,
+    };
+
+    fn write(rows: usize, compression: Compression) -> Vec<u8> {
+        let schema = Schema::new(
+            "MANY",
+            vec![Column::numeric("id"), Column::character("name", 16)],
+        );
+        let options = WriterOptionsBuilder::default()
+            .compression(compression)
+            .build()
+            .unwrap();
+        let mut writer = Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, options).unwrap();
+        for i in 0..rows {
+            let id = if i % 7 == 0 {
+                Value::Missing(MissingValue::System)
+            } else {
+                Value::Number(i as f64)
+            };
+            writer
+                .write_row(&[id, Value::String(format!("row {}", i % 50))])
+                .unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn sequential(bytes: &[u8]) -> Vec<Row> {
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes.to_vec())).unwrap();
+        reader.rows().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn can_read_rows_in_file_order() {
+        for compression in [Compression::None, Compression::Rle] {
+            let bytes = write(5000, compression);
+            let expected = sequential(&bytes);
+            assert_eq!(expected.len(), 5000);
+
+            for (threads, pages_in_flight) in [(0_usize, 64_usize), (1, 1), (3, 2), (4, 7)] {
+                let options = ParallelOptionsBuilder::default()
+                    .threads(threads)
+                    .pages_in_flight(pages_in_flight)
+                    .build()
+                    .unwrap();
+                let mut reader =
+                    ParallelSas7bdatReader::new(Cursor::new(bytes.clone()), options).unwrap();
+                assert!(reader.properties().page_count > 10);
+                let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+                assert_eq!(rows, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn can_read_selected_columns() {
+        let mut reader =
+            ParallelSas7bdatReader::open("test/hadley.sas7bdat", ParallelOptions::default())
+                .unwrap();
+        reader.select(&["id"]).unwrap();
+        let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(rows.len(), 8);
+        assert!(rows.iter().all(|row| row.len() == 1));
+    }
+
+    #[test]
+    fn cannot_read_past_truncated_pages() {
+        let bytes = write(5000, Compression::None);
+        let options = ParallelOptionsBuilder::default()
+            .pages_in_flight(4_usize)
+            .build()
+            .unwrap();
+        let mut reader =
+            ParallelSas7bdatReader::new(Cursor::new(bytes[..bytes.len() - 100].to_vec()), options)
+                .unwrap();
+
+        let rows: Vec<Result<Row, Error>> = reader.rows().collect();
+        let (last, read) = rows.split_last().unwrap();
+        assert!(last.is_err());
+        assert!(read.iter().all(|row| row.is_ok()));
+        assert!(read.len() > 4000 && read.len() < 5000);
+    }
+}
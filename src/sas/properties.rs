@@ -0,0 +1,190 @@
+use chrono::NaiveDateTime;
+
+use crate::sas::errors::Error;
+use crate::sas::layout::Layout;
+use crate::sas::{Compression, Encoding, Endianness, OsType, SasConstants, SasHeaderBinary};
+use crate::util::time::sas_timestamp_to_datetime;
+
+/// File-level facts about a sas7bdat dataset, gathered from the file header
+/// and the row size subheader.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SasProperties {
+    pub layout: Layout,
+    pub encoding: Encoding,
+    pub os_type: Option<OsType>,
+    pub header_length: usize,
+    pub page_size: usize,
+    pub page_count: usize,
+    /// `None` when the stored timestamp is missing or out of range.
+    pub created: Option<NaiveDateTime>,
+    pub modified: Option<NaiveDateTime>,
+    pub release: String,
+    pub server_type: String,
+    pub os_version: String,
+    pub os_name: String,
+    pub dataset_name: String,
+    pub file_label: String,
+    pub row_count: usize,
+    pub row_length: usize,
+    pub mix_page_row_count: usize,
+    pub column_count: usize,
+    pub compression: Compression,
+}
+
+impl SasProperties {
+    pub fn from_header(header: &SasHeaderBinary) -> Result<Self, Error> {
+        SasProperties::from_header_with_encoding(header, None)
+    }
+
+    /// As [`SasProperties::from_header`], with `encoding` in place of the one
+    /// the header gives. Mainframe files need it, since their header does
+    /// not tell which EBCDIC code page they are in.
+    pub fn from_header_with_encoding(
+        header: &SasHeaderBinary,
+        encoding: Option<Encoding>,
+    ) -> Result<Self, Error> {
+        let bytes = &header.bytes;
+        if bytes.len() < 288 {
+            return Err(Error::InvalidHeader(format!(
+                "Header is {} bytes, expected at least 288",
+                bytes.len()
+            )));
+        }
+        header.validate_sas_file().map_err(Error::InvalidHeader)?;
+
+        let endianness = header
+            .get_endianness_from_header()
+            .map_err(Error::InvalidHeader)?;
+        let layout = Layout::new(header.get_a2() == 4, endianness);
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None => header
+                .get_character_encoding_from_header()
+                .map_err(Error::InvalidHeader)?,
+        };
+
+        let a1 = header.get_a1() as usize;
+        let a2 = header.get_a2() as usize;
+        let text = |offset: usize, length: usize| {
+            let field = &bytes[offset..offset + length];
+            let text = if header.is_ebcdic_host() {
+                encoding.decode(field)
+            } else {
+                String::from_utf8_lossy(field).into_owned()
+            };
+            text.trim_end_matches(['\0', ' ']).to_string()
+        };
+
+        let header_length = layout.read_u32(bytes, 196 + a1)? as usize;
+        let page_size = layout.read_u32(bytes, 200 + a1)? as usize;
+        let max_page_size = SasConstants::new().max_page_size as usize;
+        if header_length > max_page_size {
+            return Err(Error::InvalidHeader(format!(
+                "Header length {} is over the limit of {}",
+                header_length, max_page_size
+            )));
+        }
+        if page_size < layout.page_header_size() || page_size > max_page_size {
+            return Err(Error::InvalidHeader(format!(
+                "Page size {} is outside {}..={}",
+                page_size,
+                layout.page_header_size(),
+                max_page_size
+            )));
+        }
+
+        Ok(SasProperties {
+            layout,
+            encoding,
+            os_type: header.get_os_type_from_header().ok(),
+            header_length,
+            page_size,
+            page_count: layout.read_int(bytes, 204 + a1)? as usize,
+            created: sas_timestamp_to_datetime(layout.read_f64(bytes, 164 + a1)?),
+            modified: sas_timestamp_to_datetime(layout.read_f64(bytes, 172 + a1)?),
+            release: text(216 + a1 + a2, 8),
+            server_type: text(224 + a1 + a2, 16),
+            os_version: text(240 + a1 + a2, 16),
+            os_name: text(256 + a1 + a2, 16),
+            dataset_name: header.get_sas_filename_from_header(),
+            file_label: String::new(),
+            row_count: 0,
+            row_length: 0,
+            mix_page_row_count: 0,
+            column_count: 0,
+            compression: Compression::None,
+        })
+    }
+
+    /// Where page `index` starts in the file.
+    pub fn page_offset(&self, index: usize) -> Result<u64, Error> {
+        index
+            .checked_mul(self.page_size)
+            .and_then(|offset| offset.checked_add(self.header_length))
+            .map(|offset| offset as u64)
+            .ok_or_else(|| Error::InvalidPage(format!("Page {} is past any file", index)))
+    }
+
+    pub fn is_u64(&self) -> bool {
+        self.layout.u64
+    }
+
+    pub fn endianness(&self) -> Endianness {
+        self.layout.endianness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_from_test_file() -> SasHeaderBinary {
+        let bytes = std::fs::read("test/hadley.sas7bdat").unwrap();
+        SasHeaderBinary::new(&bytes[..1024])
+    }
+
+    #[test]
+    fn can_read_properties_from_header() {
+        let properties = SasProperties::from_header(&header_from_test_file()).unwrap();
+
+        assert!(!properties.is_u64());
+        assert_eq!(properties.endianness(), Endianness::Little);
+        assert_eq!(properties.encoding, Encoding::Windows1252);
+        assert_eq!(properties.os_type, Some(OsType::Windows));
+        assert_eq!(properties.header_length, 65536);
+        assert_eq!(properties.page_size, 65536);
+        assert_eq!(properties.page_count, 1);
+        assert_eq!(properties.release, "9.0401M1");
+        assert_eq!(properties.server_type, "X64_8PRO");
+        assert_eq!(properties.dataset_name, "HADLEY");
+    }
+
+    #[test]
+    fn cannot_read_properties_from_short_header() {
+        let header = SasHeaderBinary::new(&[0; 100]);
+        assert!(matches!(
+            SasProperties::from_header(&header),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn cannot_read_properties_with_huge_page_size() {
+        let bytes = std::fs::read("test/hadley.sas7bdat").unwrap();
+        let mut bytes = bytes[..1024].to_vec();
+        // a1 is 4 in this file
+        bytes[204..208].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = SasProperties::from_header(&SasHeaderBinary::new(&bytes)).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("Page size 4294967295 is outside"));
+    }
+
+    #[test]
+    fn cannot_locate_page_past_any_file() {
+        let properties = SasProperties::from_header(&header_from_test_file()).unwrap();
+        assert_eq!(properties.page_offset(2), Ok(65536 * 3));
+        assert!(properties.page_offset(usize::MAX / 2).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use crate::sas::errors::Error;
+
+// Commands sit in the high nibble of the byte after a set control bit.
+// Anything from 3 up copies that many bytes of earlier output.
+const INSERT_SHORT: usize = 0x0;
+const INSERT_LONG: usize = 0x1;
+const COPY_LONG: usize = 0x2;
+
+/// Longest runs and copies a single command can stand for, and how far
+/// back a copy can reach.
+const MAX_SHORT_INSERT: usize = 3 + 15;
+const MAX_LONG_INSERT: usize = 19 + 15 + 255 * 16;
+const MAX_SHORT_COPY: usize = 15;
+const MAX_LONG_COPY: usize = 16 + 255;
+const MAX_BACK_OFFSET: usize = 3 + 15 + 255 * 16;
+
+/// Most output bytes one input byte can stand for, in a three-byte long
+/// insert command of the longest run.
+const MAX_EXPANSION: usize = MAX_LONG_INSERT / 3 + 1;
+
+/// Expand a row compressed with Ross Data Compression (`COMPRESS=BINARY`).
+/// Input comes in groups led by 16 control bits, most significant first:
+/// a clear bit copies one literal byte, a set bit starts a command that
+/// inserts a run or repeats earlier output.
+pub fn decompress(input: &[u8], row_length: usize) -> Result<Vec<u8>, Error> {
+    let mut output: Vec<u8> =
+        Vec::with_capacity(row_length.min(input.len().saturating_mul(MAX_EXPANSION)));
+    let mut position = 0;
+    let next = |position: &mut usize| -> Result<usize, Error> {
+        let byte = *input
+            .get(*position)
+            .ok_or(Error::OutOfBounds(*position, 1))?;
+        *position += 1;
+        Ok(byte as usize)
+    };
+    let check_length = |output: &Vec<u8>, extra: usize| {
+        if output.len() + extra > row_length {
+            return Err(Error::InvalidRow(format!(
+                "RDC data expands past the row length of {}",
+                row_length
+            )));
+        }
+        Ok(())
+    };
+
+    while position + 2 <= input.len() {
+        let control = u16::from_be_bytes([input[position], input[position + 1]]);
+        position += 2;
+
+        for bit in 0..16 {
+            if control & (0x8000 >> bit) == 0 {
+                let Some(&byte) = input.get(position) else {
+                    break;
+                };
+                check_length(&output, 1)?;
+                output.push(byte);
+                position += 1;
+                continue;
+            }
+
+            let command_position = position;
+            let marker = next(&mut position)?;
+            let count = next(&mut position)?;
+            let (command, low) = (marker >> 4, marker & 0x0F);
+
+            let (insert, copy) = match command {
+                INSERT_SHORT => (Some((3 + low, count as u8)), None),
+                INSERT_LONG => {
+                    let byte = next(&mut position)? as u8;
+                    (Some((19 + low + count * 16, byte)), None)
+                }
+                COPY_LONG => (
+                    None,
+                    Some((3 + low + count * 16, 16 + next(&mut position)?)),
+                ),
+                _ => (None, Some((3 + low + count * 16, command))),
+            };
+
+            if let Some((len, byte)) = insert {
+                check_length(&output, len)?;
+                output.resize(output.len() + len, byte);
+            }
+            if let Some((back_offset, len)) = copy {
+                if back_offset > output.len() {
+                    return Err(Error::InvalidRow(format!(
+                        "RDC command at offset {} copies {} bytes from {} back, \
+                         with {} bytes written",
+                        command_position,
+                        len,
+                        back_offset,
+                        output.len()
+                    )));
+                }
+                check_length(&output, len)?;
+                // Copies may overlap what they write, repeating a short
+                // pattern, so go a byte at a time.
+                let start = output.len() - back_offset;
+                for index in start..start + len {
+                    output.push(output[index]);
+                }
+            }
+        }
+    }
+
+    if output.len() != row_length {
+        return Err(Error::InvalidRow(format!(
+            "RDC data expands to {} bytes, expected {}",
+            output.len(),
+            row_length
+        )));
+    }
+    Ok(output)
+}
+
+/// Compress a row with Ross Data Compression. Runs of three or more bytes
+/// become inserts and repeats of earlier bytes become copies; the rest is
+/// stored as literals.
+pub fn compress(row: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(row.len() + row.len() / 8 + 2);
+    let mut control_offset = 0;
+    let mut items = 0;
+    let mut position = 0;
+    let mut matches = MatchFinder::new(row.len());
+
+    while position < row.len() {
+        if items % 16 == 0 {
+            control_offset = output.len();
+            output.extend([0, 0]);
+        }
+        let bit = 0x8000_u16 >> (items % 16);
+        items += 1;
+
+        let byte = row[position];
+        let run = row[position..]
+            .iter()
+            .take(MAX_LONG_INSERT)
+            .take_while(|&&b| b == byte)
+            .count();
+        let (copy_len, back_offset) = matches.longest_copy(row, position);
+
+        if run < 3 && copy_len < 3 {
+            output.push(byte);
+            position += 1;
+            continue;
+        }
+
+        let control = u16::from_be_bytes([output[control_offset], output[control_offset + 1]]);
+        output[control_offset..control_offset + 2].copy_from_slice(&(control | bit).to_be_bytes());
+        if run >= copy_len {
+            if run <= MAX_SHORT_INSERT {
+                output.extend([((INSERT_SHORT << 4) | (run - 3)) as u8, byte]);
+            } else {
+                let extra = run - 19;
+                output.extend([
+                    ((INSERT_LONG << 4) | (extra & 0x0F)) as u8,
+                    (extra >> 4) as u8,
+                    byte,
+                ]);
+            }
+            position += run;
+        } else {
+            let extra = back_offset - 3;
+            let (low, count) = ((extra & 0x0F) as u8, (extra >> 4) as u8);
+            if copy_len <= MAX_SHORT_COPY {
+                output.extend([((copy_len << 4) as u8) | low, count]);
+            } else {
+                output.extend([((COPY_LONG << 4) as u8) | low, count, (copy_len - 16) as u8]);
+            }
+            position += copy_len;
+        }
+    }
+    output
+}
+
+/// Bits of the hash used to find earlier bytes with the same three-byte
+/// prefix, and how many of them are tried before settling for the best so far.
+const HASH_BITS: u32 = 12;
+const MAX_CHAIN: usize = 64;
+
+/// Finds earlier repeats through hash chains: for each three-byte prefix,
+/// the latest position it was seen at, and from each position the one
+/// before it with the same hash.
+struct MatchFinder {
+    head: Vec<usize>,
+    previous: Vec<usize>,
+    indexed: usize,
+}
+
+impl MatchFinder {
+    fn new(len: usize) -> Self {
+        MatchFinder {
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            previous: vec![usize::MAX; len],
+            indexed: 0,
+        }
+    }
+
+    fn hash(row: &[u8], position: usize) -> usize {
+        let prefix = u32::from_be_bytes([0, row[position], row[position + 1], row[position + 2]]);
+        (prefix.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// The longest earlier run of bytes that `row[position..]` repeats, as
+    /// its length and how far back it starts. A copy may run past its own
+    /// start, repeating a pattern shorter than itself.
+    fn longest_copy(&mut self, row: &[u8], position: usize) -> (usize, usize) {
+        if position + 3 > row.len() {
+            return (0, 0);
+        }
+        while self.indexed < position {
+            if self.indexed + 3 <= row.len() {
+                let hash = Self::hash(row, self.indexed);
+                self.previous[self.indexed] = self.head[hash];
+                self.head[hash] = self.indexed;
+            }
+            self.indexed += 1;
+        }
+
+        let mut best = (0, 0);
+        let mut candidate = self.head[Self::hash(row, position)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == usize::MAX || position - candidate > MAX_BACK_OFFSET {
+                break;
+            }
+            let back_offset = position - candidate;
+            if back_offset >= 3 {
+                let limit = MAX_LONG_COPY.min(row.len() - position);
+                let len = (0..limit)
+                    .take_while(|&i| row[candidate + i] == row[position + i])
+                    .count();
+                if len > best.0 {
+                    best = (len, back_offset);
+                    if len == MAX_LONG_COPY {
+                        break;
+                    }
+                }
+            }
+            candidate = self.previous[candidate];
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_decompress_literals_and_copies() {
+        // Four literals, then a copy of 3 bytes from 4 back.
+        let input = [0x08, 0x00, b'a', b'b', b'c', b'd', 0x31, 0x00];
+        assert_eq!(decompress(&input, 7).unwrap(), b"abcdabc");
+    }
+
+    #[test]
+    fn can_decompress_overlapping_copies() {
+        // Three literals, then a copy of 9 bytes from 3 back.
+        let input = [0x10, 0x00, b'a', b'b', b'c', 0x90, 0x00];
+        assert_eq!(decompress(&input, 12).unwrap(), b"abcabcabcabc");
+    }
+
+    #[test]
+    fn can_decompress_inserts() {
+        // A short insert of 5 'x', then a long insert of 19 + 1 + 32 'y'.
+        let input = [0xC0, 0x00, 0x02, b'x', 0x11, 0x02, b'y'];
+        let output = decompress(&input, 57).unwrap();
+
+        assert_eq!(&output[..5], b"xxxxx");
+        assert!(output[5..].iter().all(|&b| b == b'y'));
+    }
+
+    #[test]
+    fn can_decompress_long_copies() {
+        // Twenty literals in two groups, then a copy of 16 + 2 bytes from
+        // 3 + 0 + 16 back.
+        let mut input = vec![0x00, 0x00];
+        input.extend(b"abcdefghijklmnop");
+        input.extend([0x08, 0x00]);
+        input.extend(b"qrst");
+        input.extend([0x20, 0x01, 0x02]);
+        let output = decompress(&input, 38).unwrap();
+
+        assert_eq!(&output[20..], b"bcdefghijklmnopqrs");
+    }
+
+    #[test]
+    fn can_round_trip_compressed_rows() {
+        let mut row = b"abcabcabc".to_vec();
+        row.extend([b' '; 5000]);
+        row.extend(b"xyz");
+        row.extend([0_u8; 7]);
+        row.extend((0..=255).cycle().take(600));
+        row.extend(b"abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyz");
+
+        let compressed = compress(&row);
+        assert!(compressed.len() < row.len() / 4);
+        assert_eq!(decompress(&compressed, row.len()).unwrap(), row);
+        assert_eq!(decompress(&compress(b"ab"), 2).unwrap(), b"ab");
+    }
+
+    #[test]
+    fn can_compress_short_repeats_into_one_copy() {
+        let row = b"xyz".repeat(20);
+        let compressed = compress(&row);
+
+        // Three literals and a long copy of 57 bytes from 3 back
+        assert_eq!(compressed, [0x10, 0x00, b'x', b'y', b'z', 0x20, 0x00, 41]);
+        assert_eq!(decompress(&compressed, row.len()).unwrap(), row);
+    }
+
+    #[test]
+    fn can_round_trip_long_rows_with_distant_repeats() {
+        // Pseudo-random text with each block repeated just inside the
+        // copy window.
+        let mut state = 1_u32;
+        let mut block = Vec::new();
+        for _ in 0..MAX_BACK_OFFSET {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            block.push(b'a' + (state >> 16) as u8 % 26);
+        }
+        let row: Vec<u8> = block
+            .iter()
+            .cycle()
+            .take(block.len() * 64)
+            .copied()
+            .collect();
+
+        let compressed = compress(&row);
+        assert!(compressed.len() < row.len() / 8);
+        assert_eq!(decompress(&compressed, row.len()).unwrap(), row);
+    }
+
+    #[test]
+    fn cannot_decompress_malformed_input() {
+        // Copies from before the start of the row
+        assert!(decompress(&[0x80, 0x00, 0x30, 0x00], 3).is_err());
+        // Truncated command
+        assert!(decompress(&[0x80, 0x00, 0x10, 0x00], 19).is_err());
+        // Past the row length
+        assert!(decompress(&[0x80, 0x00, 0x0F, b'z'], 4).is_err());
+        // Short of the row length
+        assert!(decompress(&[0x00, 0x00, b'a'], 2).is_err());
+    }
+}
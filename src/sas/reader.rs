@@ -0,0 +1,526 @@
+use std::fs::File;
+use std::io::{BufReader, SeekFrom};
+use std::path::Path;
+
+use derive_builder::Builder;
+
+use crate::sas::decoder::{Decoder, Metadata, RawRow};
+use crate::sas::errors::Error;
+use crate::sas::numeric::NumericColumn;
+use crate::sas::properties::SasProperties;
+use crate::sas::schema::{Row, Schema};
+use crate::sas::{Encoding, PageBinary, SasConstants, SasHeaderBinary};
+use crate::traits::{DatasetReader, ReadSeek};
+
+/// Rows to reserve room for at most, since row counts come from the file.
+const MAX_RESERVED_ROWS: usize = 1 << 20;
+
+/// What to do with character cells that are not valid in the encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidBytes {
+    /// Fail the row.
+    Strict,
+    /// Decode with U+FFFD in place of invalid sequences.
+    Replace,
+    /// Hand the cell back as [`Value::Bytes`](crate::sas::Value::Bytes).
+    Raw,
+}
+
+/// Options for reading a sas7bdat file.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct ReaderOptions {
+    /// Decode text with this encoding instead of the one in the header, for
+    /// files written under the wrong session encoding.
+    pub encoding: Option<Encoding>,
+    /// Applies to character cells; names and labels are always replaced.
+    pub invalid_bytes: InvalidBytes,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        ReaderOptions {
+            encoding: None,
+            invalid_bytes: InvalidBytes::Replace,
+        }
+    }
+}
+
+/// Reads the schema and rows of a sas7bdat file.
+pub struct Sas7bdatReader<R: ReadSeek> {
+    source: R,
+    decoder: Decoder,
+    next_page: usize,
+}
+
+impl Sas7bdatReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Sas7bdatReader::open_with_options(path, ReaderOptions::default())
+    }
+
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: ReaderOptions,
+    ) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Sas7bdatReader::with_options(BufReader::new(file), options)
+    }
+}
+
+impl<R: ReadSeek> Sas7bdatReader<R> {
+    pub fn new(source: R) -> Result<Self, Error> {
+        Sas7bdatReader::with_options(source, ReaderOptions::default())
+    }
+
+    pub fn with_options(mut source: R, options: ReaderOptions) -> Result<Self, Error> {
+        let header = read_header(&mut source, options.encoding)?;
+        let properties = SasProperties::from_header_with_encoding(&header, options.encoding)?;
+
+        let mut metadata = Metadata::default();
+        for index in 0..properties.page_count {
+            let page = read_page(&mut source, &properties, index)?;
+            if metadata.add_page(&page, &properties.layout)? {
+                break;
+            }
+        }
+        Ok(Sas7bdatReader {
+            source,
+            decoder: Decoder::new(header, metadata, options)?,
+            next_page: 0,
+        })
+    }
+
+    pub fn header(&self) -> &SasHeaderBinary {
+        &self.decoder.header
+    }
+
+    pub fn properties(&self) -> &SasProperties {
+        &self.decoder.properties
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Keep only the named columns, in the given order, so that the other
+    /// cells of each row are never decoded.
+    pub fn select<S: AsRef<str>>(&mut self, names: &[S]) -> Result<(), Error> {
+        self.decoder.select(names)
+    }
+
+    /// Read one numeric column of every row into contiguous buffers, whatever
+    /// rows have been read so far. This is much faster than decoding whole
+    /// rows when only numbers are needed.
+    pub fn read_numeric_column(&mut self, name: &str) -> Result<NumericColumn, Error> {
+        let column = self
+            .decoder
+            .schema
+            .column_index(name)
+            .ok_or_else(|| Error::InvalidSchema(format!("No column named {}", name)))?;
+        let properties = &self.decoder.properties;
+        let mut out = NumericColumn::with_capacity(properties.row_count.min(MAX_RESERVED_ROWS));
+        let mut rows = 0;
+        for index in 0..properties.page_count {
+            if rows >= properties.row_count {
+                break;
+            }
+            let page = read_page(&mut self.source, properties, index)?;
+            rows += self.decoder.gather_numeric(&page, rows, column, &mut out)?;
+        }
+        if rows < properties.row_count {
+            return Err(self.decoder.truncated(rows));
+        }
+        Ok(out)
+    }
+
+    /// Read the next row without decoding it, for hashing cells or copying
+    /// them to a writer byte for byte. Shares its place in the file with
+    /// [`read_row`](DatasetReader::read_row).
+    pub fn read_raw_row(&mut self) -> Result<Option<RawRow<'_>>, Error> {
+        while !self.decoder.is_finished() && !self.decoder.has_queued_rows() {
+            if self.next_page >= self.decoder.properties.page_count {
+                return Err(self.decoder.truncated(self.decoder.rows_read()));
+            }
+            let page = read_page(&mut self.source, &self.decoder.properties, self.next_page)?;
+            self.next_page += 1;
+            self.decoder.load_rows(&page)?;
+        }
+        Ok(self.decoder.next_raw_row())
+    }
+
+    /// The source and decoder, for readers that share the parsing but read
+    /// pages their own way.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn into_parts(self) -> (R, Decoder) {
+        (self.source, self.decoder)
+    }
+
+    /// Name, offset and length of each character column within a row.
+    pub(crate) fn character_spans(&self) -> Vec<(String, usize, usize)> {
+        self.decoder.character_spans()
+    }
+}
+
+impl<R: ReadSeek> DatasetReader for Sas7bdatReader<R> {
+    fn schema(&self) -> &Schema {
+        &self.decoder.schema
+    }
+
+    fn file_info(&self) -> Vec<(String, String)> {
+        self.decoder.file_info()
+    }
+
+    fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        loop {
+            if self.decoder.is_finished() {
+                return Ok(None);
+            }
+            if let Some(row) = self.decoder.next_row()? {
+                return Ok(Some(row));
+            }
+            if self.next_page >= self.decoder.properties.page_count {
+                return Err(self.decoder.truncated(self.decoder.rows_read()));
+            }
+
+            let page = read_page(&mut self.source, &self.decoder.properties, self.next_page)?;
+            self.next_page += 1;
+            self.decoder.load_rows(&page)?;
+        }
+    }
+}
+
+/// Read the file header: the fixed part first, then the rest once its
+/// length is known.
+fn read_header<R: ReadSeek>(
+    source: &mut R,
+    encoding: Option<Encoding>,
+) -> Result<SasHeaderBinary, Error> {
+    let constants = SasConstants::new();
+    source.seek(SeekFrom::Start(0))?;
+
+    let mut bytes = vec![0_u8; constants.file_header_size_32_bit as usize];
+    source
+        .read_exact(&mut bytes)
+        .map_err(|error| Error::InvalidHeader(format!("File is too short: {}", error)))?;
+
+    let header = SasHeaderBinary::new(&bytes);
+    let properties = SasProperties::from_header_with_encoding(&header, encoding)?;
+    if properties.header_length > bytes.len() {
+        let mut rest = vec![0_u8; properties.header_length - bytes.len()];
+        source
+            .read_exact(&mut rest)
+            .map_err(|error| Error::InvalidHeader(format!("File is too short: {}", error)))?;
+        bytes.extend(rest);
+    }
+    Ok(SasHeaderBinary::new(&bytes))
+}
+
+pub(crate) fn read_page<R: ReadSeek>(
+    source: &mut R,
+    properties: &SasProperties,
+    index: usize,
+) -> Result<PageBinary, Error> {
+    let offset = properties.page_offset(index)?;
+    source.seek(SeekFrom::Start(offset))?;
+
+    let mut bytes = vec![0_u8; properties.page_size];
+    source
+        .read_exact(&mut bytes)
+        .map_err(|error| Error::InvalidPage(format!("Could not read page {}: {}", index, error)))?;
+    PageBinary::new(&bytes, &properties.layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sas::{ColumnType, Compression, MissingValue, Value};
+    use crate::traits::DatasetReader;
+
+    const FILENAME: &str = "test/hadley.sas7bdat";
+
+    #[test]
+    fn can_read_schema() {
+        let reader = Sas7bdatReader::open(FILENAME).unwrap();
+        let schema = reader.schema();
+
+        assert_eq!(schema.name, "HADLEY");
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["id", "workshop", "gender", "q1", "q2", "q3", "q4"]
+        );
+        assert_eq!(schema.columns[2].column_type, ColumnType::Character);
+        assert_eq!(schema.columns[1].format.name, "WORKSHOP");
+        assert_eq!(schema.columns[2].format.name, "$GENDER");
+        assert_eq!(schema.columns[3].label, "The instructor was well prepared");
+    }
+
+    #[test]
+    fn can_read_numeric_column() {
+        let mut reader = Sas7bdatReader::open(FILENAME).unwrap();
+        let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+        let column = reader.read_numeric_column("q1").unwrap();
+
+        assert_eq!(column.len(), 8);
+        for (row, (value, missing)) in rows.iter().zip(column.values.iter().zip(&column.missing)) {
+            match &row[3] {
+                Value::Number(number) => assert!(!missing && number == value),
+                Value::Missing(_) => assert!(missing),
+                _ => panic!("q1 is numeric"),
+            }
+        }
+        assert!(reader.read_numeric_column("gender").is_err());
+        assert!(reader.read_numeric_column("q9").is_err());
+    }
+
+    #[test]
+    fn can_read_numeric_column_across_compressed_pages() {
+        use crate::sas::{Column, Sas7bdatWriter, Schema, WriterOptionsBuilder};
+
+        let schema = Schema::new("MANY", vec![Column::numeric("x"), Column::numeric("y")]);
+        for compression in [Compression::None, Compression::Rle] {
+            let options = WriterOptionsBuilder::default()
+                .compression(compression)
+                .build()
+                .unwrap();
+            let mut writer =
+                Sas7bdatWriter::new(std::io::Cursor::new(Vec::new()), schema.clone(), options)
+                    .unwrap();
+            for i in 0..3000 {
+                let y = match i % 9 {
+                    0 => Value::Missing(MissingValue::Special('B')),
+                    _ => Value::Number(i as f64 / 4.0),
+                };
+                writer.write_row(&[Value::Number(1.0), y]).unwrap();
+            }
+            let bytes = writer.finish().unwrap().into_inner();
+
+            let mut reader = Sas7bdatReader::new(std::io::Cursor::new(bytes)).unwrap();
+            assert!(reader.properties().page_count > 2);
+            let column = reader.read_numeric_column("y").unwrap();
+            assert_eq!(column.len(), 3000);
+            assert_eq!(column.missing.iter().filter(|&&m| m).count(), 334);
+            assert_eq!(column.values[2999], 2999.0 / 4.0);
+        }
+    }
+
+    #[test]
+    fn can_read_row_properties() {
+        let reader = Sas7bdatReader::open(FILENAME).unwrap();
+        let properties = reader.properties();
+
+        assert_eq!(properties.row_count, 8);
+        assert_eq!(properties.row_length, 56);
+        assert_eq!(properties.column_count, 7);
+        assert_eq!(properties.compression, Compression::None);
+    }
+
+    #[test]
+    fn can_read_rows() {
+        let mut reader = Sas7bdatReader::open(FILENAME).unwrap();
+        let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(rows.len(), 8);
+        assert_eq!(
+            rows[0],
+            vec![
+                Value::Number(1.0),
+                Value::Number(1.0),
+                Value::from("f"),
+                Value::Number(1.0),
+                Value::Number(1.0),
+                Value::Number(5.0),
+                Value::Number(1.0),
+            ]
+        );
+        assert_eq!(rows[3][2], Value::from(""));
+        assert_eq!(rows[3][5], Value::Missing(MissingValue::System));
+        assert_eq!(rows[7][0], Value::Number(8.0));
+        assert_eq!(reader.read_row(), Ok(None));
+    }
+
+    #[test]
+    fn can_select_columns() {
+        let mut reader = Sas7bdatReader::open(FILENAME).unwrap();
+        reader.select(&["GENDER", "id"]).unwrap();
+
+        let names: Vec<&str> = reader
+            .schema()
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["gender", "id"]);
+        assert_eq!(
+            reader.read_row().unwrap(),
+            Some(vec![Value::from("f"), Value::Number(1.0)])
+        );
+        assert!(matches!(
+            reader.select(&["q1"]),
+            Err(Error::InvalidSchema(_))
+        ));
+    }
+
+    #[test]
+    fn can_override_encoding_and_handle_invalid_bytes() {
+        use crate::sas::{Column, Encoding, SasFixtureBuilder, Schema};
+
+        // Written as Windows-1252 but labelled UTF-8.
+        let mut bytes = SasFixtureBuilder::default()
+            .encoding(Encoding::Windows1252)
+            .schema(Schema::new("CITIES", vec![Column::character("city", 8)]))
+            .rows(vec![vec![Value::from("Zürich")], vec![Value::from("Bern")]])
+            .build()
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        bytes[70] = Encoding::Utf8.to_u8().unwrap();
+        let read = |options: ReaderOptions| {
+            let mut reader =
+                Sas7bdatReader::with_options(std::io::Cursor::new(bytes.clone()), options).unwrap();
+            reader.rows().collect::<Result<Vec<Row>, _>>()
+        };
+        let options = |invalid_bytes: InvalidBytes| {
+            ReaderOptionsBuilder::default()
+                .invalid_bytes(invalid_bytes)
+                .build()
+                .unwrap()
+        };
+
+        let replaced = read(ReaderOptions::default()).unwrap();
+        assert_eq!(replaced[0][0], Value::from("Z\u{FFFD}rich"));
+        assert!(matches!(
+            read(options(InvalidBytes::Strict)),
+            Err(Error::InvalidRow(_))
+        ));
+        let raw = read(options(InvalidBytes::Raw)).unwrap();
+        assert_eq!(raw[0][0], Value::Bytes(b"Z\xfcrich".to_vec()));
+        assert_eq!(raw[1][0], Value::from("Bern"));
+
+        let overridden = ReaderOptionsBuilder::default()
+            .encoding(Encoding::Windows1252)
+            .invalid_bytes(InvalidBytes::Strict)
+            .build()
+            .unwrap();
+        assert_eq!(read(overridden).unwrap()[0][0], Value::from("Zürich"));
+    }
+
+    #[test]
+    fn can_read_raw_rows() {
+        use crate::sas::{Column, SasFixtureBuilder, Schema};
+
+        // One column in Latin-1 and UTF-8 at once.
+        let fixture = SasFixtureBuilder::default()
+            .schema(Schema::new(
+                "MIXED",
+                vec![Column::numeric("id"), Column::character("city", 8)],
+            ))
+            .rows(vec![
+                vec![Value::Number(1.0), Value::Bytes(b"Z\xfcrich".to_vec())],
+                vec![Value::Number(2.0), Value::from("Zürich")],
+                vec![Value::Number(3.0), Value::from("Bern")],
+            ])
+            .build()
+            .unwrap();
+        let bytes = fixture.to_bytes().unwrap();
+        let mut reader = Sas7bdatReader::new(std::io::Cursor::new(bytes.clone())).unwrap();
+
+        let first = reader.read_raw_row().unwrap().unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first.cell(0), Some(&1.0_f64.to_le_bytes()[..]));
+        assert_eq!(first.cell(1), Some(&b"Z\xfcrich  "[..]));
+        assert_eq!(first.cell(2), None);
+        assert_eq!(first.as_bytes().len(), 16);
+        assert_eq!(
+            reader.read_row().unwrap(),
+            Some(vec![Value::Number(2.0), Value::from("Zürich")])
+        );
+        reader.select(&["city"]).unwrap();
+        let cells: Vec<&[u8]> = reader.read_raw_row().unwrap().unwrap().cells().collect();
+        assert_eq!(cells, vec![b"Bern    "]);
+        assert!(reader.read_raw_row().unwrap().is_none());
+
+        // Copying raw rows to a writer gives back the same rows.
+        let mut reader = Sas7bdatReader::new(std::io::Cursor::new(bytes.clone())).unwrap();
+        let mut writer = crate::sas::Sas7bdatWriter::new(
+            std::io::Cursor::new(Vec::new()),
+            fixture.schema.clone(),
+            crate::sas::WriterOptions::default(),
+        )
+        .unwrap();
+        while let Some(row) = reader.read_raw_row().unwrap() {
+            writer.write_row(&row.to_row().unwrap()).unwrap();
+        }
+        let copy = writer.finish().unwrap().into_inner();
+        let mut original = Sas7bdatReader::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut copied = Sas7bdatReader::new(std::io::Cursor::new(copy)).unwrap();
+        for _ in 0..3 {
+            let expected = original
+                .read_raw_row()
+                .unwrap()
+                .unwrap()
+                .as_bytes()
+                .to_vec();
+            assert_eq!(copied.read_raw_row().unwrap().unwrap().as_bytes(), expected);
+        }
+    }
+
+    #[test]
+    fn can_read_mainframe_files_in_the_given_code_page() {
+        use crate::sas::{Column, Encoding, OsType, SasFixtureBuilder, Schema};
+
+        let rows = vec![
+            vec![Value::Number(1.0), Value::from("Müller Ä")],
+            vec![Value::Number(2.0), Value::from("Straße Ö")],
+        ];
+        for encoding in Encoding::EBCDIC {
+            let fixture = SasFixtureBuilder::default()
+                .encoding(encoding)
+                .schema(Schema::new(
+                    "KUNDEN",
+                    vec![Column::numeric("id"), Column::character("name", 12)],
+                ))
+                .rows(rows.clone())
+                .build()
+                .unwrap();
+            for variant in fixture.variants() {
+                let bytes = variant.to_bytes().unwrap();
+                assert!(matches!(
+                    Sas7bdatReader::new(std::io::Cursor::new(bytes.clone())),
+                    Err(Error::InvalidHeader(_))
+                ));
+
+                let options = ReaderOptionsBuilder::default()
+                    .encoding(encoding)
+                    .invalid_bytes(InvalidBytes::Strict)
+                    .build()
+                    .unwrap();
+                let mut reader =
+                    Sas7bdatReader::with_options(std::io::Cursor::new(bytes), options).unwrap();
+
+                assert_eq!(reader.properties().os_type, Some(OsType::ZOs));
+                assert_eq!(reader.properties().encoding, encoding);
+                assert_eq!(reader.properties().release, "9.0401M0");
+                assert_eq!(reader.schema(), &fixture.schema);
+                let read: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+                assert_eq!(read, rows, "{:?}", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn cannot_read_truncated_file() {
+        let bytes = std::fs::read(FILENAME).unwrap();
+        let truncated = std::io::Cursor::new(bytes[..70000].to_vec());
+        assert!(Sas7bdatReader::new(truncated).is_err());
+    }
+
+    #[test]
+    fn cannot_read_non_sas_file() {
+        let bytes = std::io::Cursor::new(vec![0_u8; 2048]);
+        assert!(matches!(
+            Sas7bdatReader::new(bytes),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::sas::errors::Error;
+
+// Control bytes hold a command in the high nibble and the low bits of a
+// length in the low nibble.
+const COPY64: u8 = 0x0;
+const COPY64_PLUS_4096: u8 = 0x1;
+const COPY96: u8 = 0x2;
+const INSERT_BYTE18: u8 = 0x4;
+const INSERT_AT17: u8 = 0x5;
+const INSERT_BLANK17: u8 = 0x6;
+const INSERT_ZERO17: u8 = 0x7;
+const COPY1: u8 = 0x8;
+const COPY17: u8 = 0x9;
+const COPY33: u8 = 0xA;
+const COPY49: u8 = 0xB;
+const INSERT_BYTE3: u8 = 0xC;
+const INSERT_AT2: u8 = 0xD;
+const INSERT_BLANK2: u8 = 0xE;
+const INSERT_ZERO2: u8 = 0xF;
+
+/// Longest run a single two-byte-length command can hold.
+const MAX_LONG_LEN: usize = 255 + 15 * 256;
+
+/// Most output bytes one input byte can stand for, in a two-byte insert
+/// command of the longest run.
+const MAX_EXPANSION: usize = (17 + MAX_LONG_LEN) / 2 + 1;
+
+/// Runs shorter than this are cheaper to copy than to insert.
+const MIN_RUN: usize = 3;
+
+/// Expand a row compressed with the SAS RLE scheme (`COMPRESS=CHAR`).
+pub fn decompress(input: &[u8], row_length: usize) -> Result<Vec<u8>, Error> {
+    // The row length comes from the file, so only trust it as far as the
+    // input could possibly expand.
+    let mut output = Vec::with_capacity(row_length.min(input.len().saturating_mul(MAX_EXPANSION)));
+    let mut position = 0;
+    let next = |position: &mut usize| -> Result<usize, Error> {
+        let byte = *input
+            .get(*position)
+            .ok_or(Error::OutOfBounds(*position, 1))?;
+        *position += 1;
+        Ok(byte as usize)
+    };
+
+    while position < input.len() {
+        let control = input[position];
+        position += 1;
+        let command = control >> 4;
+        let low = (control & 0x0F) as usize;
+
+        let (copy_len, insert_len, insert_byte) = match command {
+            COPY64 => (next(&mut position)? + 64 + low * 256, 0, 0),
+            COPY64_PLUS_4096 => (next(&mut position)? + 64 + low * 256 + 4096, 0, 0),
+            COPY96 => (low + 96, 0, 0),
+            INSERT_BYTE18 => {
+                let len = next(&mut position)? + 18 + low * 256;
+                (0, len, next(&mut position)? as u8)
+            }
+            INSERT_AT17 => (0, next(&mut position)? + 17 + low * 256, b'@'),
+            INSERT_BLANK17 => (0, next(&mut position)? + 17 + low * 256, b' '),
+            INSERT_ZERO17 => (0, next(&mut position)? + 17 + low * 256, 0),
+            COPY1 => (low + 1, 0, 0),
+            COPY17 => (low + 17, 0, 0),
+            COPY33 => (low + 33, 0, 0),
+            COPY49 => (low + 49, 0, 0),
+            INSERT_BYTE3 => (0, low + 3, next(&mut position)? as u8),
+            INSERT_AT2 => (0, low + 2, b'@'),
+            INSERT_BLANK2 => (0, low + 2, b' '),
+            INSERT_ZERO2 => (0, low + 2, 0),
+            _ => {
+                return Err(Error::InvalidRow(format!(
+                    "Unknown RLE command {:#04x} at offset {}",
+                    control,
+                    position - 1
+                )))
+            }
+        };
+
+        if output.len() + copy_len + insert_len > row_length {
+            return Err(Error::InvalidRow(format!(
+                "RLE data expands past the row length of {}",
+                row_length
+            )));
+        }
+        if copy_len > 0 {
+            let bytes = input
+                .get(position..position + copy_len)
+                .ok_or(Error::OutOfBounds(position, copy_len))?;
+            output.extend_from_slice(bytes);
+            position += copy_len;
+        }
+        output.resize(output.len() + insert_len, insert_byte);
+    }
+
+    if output.len() != row_length {
+        return Err(Error::InvalidRow(format!(
+            "RLE data expands to {} bytes, expected {}",
+            output.len(),
+            row_length
+        )));
+    }
+    Ok(output)
+}
+
+/// Compress a row with the SAS RLE scheme. Runs of repeated bytes become
+/// insert commands and everything else is copied.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut literal_start = 0;
+    let mut position = 0;
+
+    while position < input.len() {
+        let byte = input[position];
+        let run = input[position..].iter().take_while(|&&b| b == byte).count();
+        if run >= MIN_RUN {
+            emit_copy(&mut output, &input[literal_start..position]);
+            emit_insert(&mut output, byte, run);
+            position += run;
+            literal_start = position;
+        } else {
+            position += run;
+        }
+    }
+    emit_copy(&mut output, &input[literal_start..]);
+    output
+}
+
+fn emit_copy(output: &mut Vec<u8>, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        let len = bytes.len().min(MAX_LONG_LEN + 64);
+        match len {
+            1..=16 => output.push(COPY1 << 4 | (len - 1) as u8),
+            17..=32 => output.push(COPY17 << 4 | (len - 17) as u8),
+            33..=48 => output.push(COPY33 << 4 | (len - 33) as u8),
+            49..=63 => output.push(COPY49 << 4 | (len - 49) as u8),
+            _ => {
+                let extra = len - 64;
+                output.push(COPY64 << 4 | (extra / 256) as u8);
+                output.push((extra % 256) as u8);
+            }
+        }
+        output.extend_from_slice(&bytes[..len]);
+        bytes = &bytes[len..];
+    }
+}
+
+fn emit_insert(output: &mut Vec<u8>, byte: u8, mut len: usize) {
+    let (short, long, short_min, long_min) = match byte {
+        b'@' => (INSERT_AT2, INSERT_AT17, 2, 17),
+        b' ' => (INSERT_BLANK2, INSERT_BLANK17, 2, 17),
+        0 => (INSERT_ZERO2, INSERT_ZERO17, 2, 17),
+        _ => (INSERT_BYTE3, INSERT_BYTE18, 3, 18),
+    };
+    let has_byte = short == INSERT_BYTE3;
+
+    while len > 0 {
+        if len < short_min {
+            // Too short for an insert; copy the leftovers instead.
+            emit_copy(output, &vec![byte; len]);
+            return;
+        }
+        let chunk = if len >= long_min {
+            let chunk = len.min(long_min + MAX_LONG_LEN);
+            let extra = chunk - long_min;
+            output.push(long << 4 | (extra / 256) as u8);
+            output.push((extra % 256) as u8);
+            chunk
+        } else {
+            output.push(short << 4 | (len - short_min) as u8);
+            len
+        };
+        if has_byte {
+            output.push(byte);
+        }
+        len -= chunk;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_decompress_copy_and_insert_commands() {
+        // COPY1 of 3 bytes, INSERT_BLANK2 of 5 blanks, INSERT_BYTE3 of 4 'x'
+        let input = [0x82, b'a', b'b', b'c', 0xE3, 0xC1, b'x'];
+        assert_eq!(decompress(&input, 12).unwrap(), b"abc     xxxx");
+    }
+
+    #[test]
+    fn can_decompress_long_commands() {
+        // INSERT_ZERO17 of 17 + 1 + 256 bytes, then COPY64 of 64 bytes
+        let mut input = vec![0x71, 0x01, 0x00, 0x00];
+        input.extend(vec![b'z'; 64]);
+        let output = decompress(&input, 274 + 64).unwrap();
+
+        assert!(output[..274].iter().all(|&b| b == 0));
+        assert!(output[274..].iter().all(|&b| b == b'z'));
+    }
+
+    #[test]
+    fn cannot_decompress_past_row_length() {
+        let input = [0xE3];
+        assert!(decompress(&input, 4).is_err());
+    }
+
+    #[test]
+    fn cannot_decompress_truncated_input() {
+        assert!(decompress(&[0x85, b'a'], 6).is_err());
+        assert!(decompress(&[0x40], 20).is_err());
+        assert!(decompress(&[0x30], 20).is_err());
+    }
+
+    #[test]
+    fn can_compress_padded_strings() {
+        let mut row = b"Smith".to_vec();
+        row.resize(200, b' ');
+        let compressed = compress(&row);
+
+        assert!(compressed.len() < 12);
+        assert_eq!(decompress(&compressed, row.len()).unwrap(), row);
+    }
+
+    #[test]
+    fn can_round_trip_varied_rows() {
+        let mut rows: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![b'a'],
+            vec![b' '; 2],
+            vec![0; 5000],
+            vec![b'q'; 9000],
+            (0..=255).collect(),
+            (0..10_000).map(|i| (i % 7) as u8).collect(),
+            (0..10_000).map(|i| (i / 40 % 3) as u8 * 32).collect(),
+        ];
+        let mut mixed = Vec::new();
+        for i in 0..300 {
+            mixed.extend(vec![b'@'; i % 20]);
+            mixed.extend((0..i % 70).map(|j| j as u8));
+        }
+        rows.push(mixed);
+
+        for row in rows {
+            let compressed = compress(&row);
+            assert_eq!(decompress(&compressed, row.len()).unwrap(), row);
+        }
+    }
+}
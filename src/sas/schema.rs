@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use derive_builder::Builder;
+
+use crate::sas::errors::Error;
+use crate::sas::{Column, Value};
+
+/// A row of values, in schema column order.
+pub type Row = Vec<Value>;
+
+/// The dataset-level metadata: its name, label and columns.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into))]
+pub struct Schema {
+    pub name: String,
+    #[builder(default)]
+    pub label: String,
+    pub columns: Vec<Column>,
+}
+
+impl Schema {
+    pub fn new(name: &str, columns: Vec<Column>) -> Self {
+        Schema {
+            name: name.to_string(),
+            label: String::new(),
+            columns,
+        }
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Find a column by name. SAS names are case-insensitive.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.column_index(name).map(|index| &self.columns[index])
+    }
+
+    /// Total width of a row in bytes.
+    pub fn row_length(&self) -> usize {
+        self.columns.iter().map(|column| column.length).sum()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::InvalidSchema("Dataset name is empty".to_string()));
+        }
+        if self.columns.is_empty() {
+            return Err(Error::InvalidSchema("Dataset has no columns".to_string()));
+        }
+
+        let mut names = HashSet::new();
+        for column in &self.columns {
+            if column.name.trim().is_empty() {
+                return Err(Error::InvalidSchema("Column name is empty".to_string()));
+            }
+            if !names.insert(column.name.to_uppercase()) {
+                return Err(Error::InvalidSchema(format!(
+                    "Duplicate column name: {}",
+                    column.name
+                )));
+            }
+            let valid_length = if column.is_numeric() {
+                (3..=8).contains(&column.length)
+            } else {
+                (1..=32767).contains(&column.length)
+            };
+            if !valid_length {
+                return Err(Error::InvalidSchema(format!(
+                    "Column {} has invalid length {}",
+                    column.name, column.length
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::new(
+            "CLASS",
+            vec![Column::character("Name", 8), Column::numeric("Age")],
+        )
+    }
+
+    #[test]
+    fn can_find_column_ignoring_case() {
+        let schema = schema();
+        assert_eq!(schema.column_index("AGE"), Some(1));
+        assert_eq!(schema.column("name").unwrap().length, 8);
+        assert_eq!(schema.column_index("height"), None);
+        assert_eq!(schema.row_length(), 16);
+    }
+
+    #[test]
+    fn can_validate_schema() {
+        assert!(schema().validate().is_ok());
+    }
+
+    #[test]
+    fn cannot_validate_duplicate_columns() {
+        let schema = Schema::new("CLASS", vec![Column::numeric("x"), Column::numeric("X")]);
+        assert_eq!(
+            schema.validate(),
+            Err(Error::InvalidSchema("Duplicate column name: X".to_string()))
+        );
+    }
+
+    #[test]
+    fn cannot_validate_bad_numeric_length() {
+        let mut column = Column::numeric("x");
+        column.length = 2;
+        let schema = Schema::new("CLASS", vec![column]);
+        assert!(schema.validate().is_err());
+    }
+
+    #[test]
+    fn can_build_schema() {
+        let schema = SchemaBuilder::default()
+            .name("CLASS")
+            .label("Students")
+            .columns(vec![Column::numeric("Age")])
+            .build()
+            .unwrap();
+        assert_eq!(schema.label, "Students");
+    }
+}
@@ -0,0 +1,251 @@
+use crate::sas::errors::Error;
+use crate::sas::layout::Layout;
+use crate::sas::Compression;
+
+const COLUMN_MASK: u32 = 0xFFFF_FFF8;
+
+/// The signature at the start of every metadata subheader.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SubheaderSignature {
+    RowSize,
+    ColumnSize,
+    SubheaderCounts,
+    ColumnFormat,
+    ColumnAttributes,
+    ColumnText,
+    ColumnList,
+    ColumnName,
+    Unknown(u32),
+}
+
+impl SubheaderSignature {
+    pub fn from_u32(value: u32) -> Self {
+        match value {
+            0xF7F7_F7F7 => SubheaderSignature::RowSize,
+            0xF6F6_F6F6 => SubheaderSignature::ColumnSize,
+            0xFFFF_FC00 => SubheaderSignature::SubheaderCounts,
+            0xFFFF_FBFE => SubheaderSignature::ColumnFormat,
+            0xFFFF_FFFC => SubheaderSignature::ColumnAttributes,
+            0xFFFF_FFFD => SubheaderSignature::ColumnText,
+            0xFFFF_FFFE => SubheaderSignature::ColumnList,
+            0xFFFF_FFFF => SubheaderSignature::ColumnName,
+            _ => SubheaderSignature::Unknown(value),
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        match self {
+            SubheaderSignature::RowSize => 0xF7F7_F7F7,
+            SubheaderSignature::ColumnSize => 0xF6F6_F6F6,
+            SubheaderSignature::SubheaderCounts => 0xFFFF_FC00,
+            SubheaderSignature::ColumnFormat => 0xFFFF_FBFE,
+            SubheaderSignature::ColumnAttributes => 0xFFFF_FFFC,
+            SubheaderSignature::ColumnText => 0xFFFF_FFFD,
+            SubheaderSignature::ColumnList => 0xFFFF_FFFE,
+            SubheaderSignature::ColumnName => 0xFFFF_FFFF,
+            SubheaderSignature::Unknown(value) => value,
+        }
+    }
+
+    /// Read the signature at the start of a subheader. 64-bit files store
+    /// signatures in 8 bytes, but only the low 4 bytes tell them apart.
+    pub fn read(bytes: &[u8], layout: &Layout) -> Result<Self, Error> {
+        let value = layout.read_int(bytes, 0)? as u32;
+        Ok(SubheaderSignature::from_u32(value))
+    }
+
+    /// Write the signature at the start of a subheader. In 64-bit files the
+    /// size signatures are zero-extended and the rest are sign-extended.
+    pub fn write(self, bytes: &mut [u8], layout: &Layout) {
+        let value = self.to_u32();
+        let value = match self {
+            SubheaderSignature::RowSize | SubheaderSignature::ColumnSize => u64::from(value),
+            _ => value as i32 as i64 as u64,
+        };
+        layout.write_int(bytes, 0, value);
+    }
+
+    /// Any signature under the column mask is metadata, even when we do not
+    /// know what it holds.
+    pub fn is_recognized(&self) -> bool {
+        match self {
+            SubheaderSignature::Unknown(value) => value & COLUMN_MASK == COLUMN_MASK,
+            _ => true,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubheaderSignature::RowSize => "row size",
+            SubheaderSignature::ColumnSize => "column size",
+            SubheaderSignature::SubheaderCounts => "subheader counts",
+            SubheaderSignature::ColumnFormat => "column format",
+            SubheaderSignature::ColumnAttributes => "column attributes",
+            SubheaderSignature::ColumnText => "column text",
+            SubheaderSignature::ColumnList => "column list",
+            SubheaderSignature::ColumnName => "column name",
+            SubheaderSignature::Unknown(_) => "unknown",
+        }
+    }
+}
+
+/// An entry in a page's subheader pointer table.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SubheaderPointer {
+    pub offset: usize,
+    pub length: usize,
+    pub compression: Compression,
+    /// Set when the subheader holds a row rather than metadata.
+    pub is_compressed_data: bool,
+}
+
+impl SubheaderPointer {
+    pub fn read(bytes: &[u8], offset: usize, layout: &Layout) -> Result<Self, Error> {
+        let int_len = layout.int_len();
+        let pointer_offset = layout.read_int(bytes, offset)? as usize;
+        let length = layout.read_int(bytes, offset + int_len)? as usize;
+        let flags = bytes
+            .get(offset + 2 * int_len..offset + 2 * int_len + 2)
+            .ok_or(Error::OutOfBounds(offset + 2 * int_len, 2))?;
+
+        Ok(SubheaderPointer {
+            offset: pointer_offset,
+            length,
+            compression: Compression::from_u8(flags[0])?,
+            is_compressed_data: flags[1] != 0,
+        })
+    }
+
+    pub fn write(&self, bytes: &mut [u8], offset: usize, layout: &Layout) {
+        let int_len = layout.int_len();
+        layout.write_int(bytes, offset, self.offset as u64);
+        layout.write_int(bytes, offset + int_len, self.length as u64);
+        bytes[offset + 2 * int_len] = self.compression.to_u8();
+        bytes[offset + 2 * int_len + 1] = u8::from(self.is_compressed_data);
+    }
+
+    /// Pointers with a length of zero, or marked as truncated, point at
+    /// nothing and should be skipped.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0 || self.compression == Compression::Truncated
+    }
+}
+
+/// A reference into the column text subheaders: which text subheader, and
+/// the offset and length of the string within it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct TextRef {
+    pub index: u16,
+    pub offset: u16,
+    pub length: u16,
+}
+
+impl TextRef {
+    pub fn read(bytes: &[u8], offset: usize, layout: &Layout) -> Result<Self, Error> {
+        Ok(TextRef {
+            index: layout.read_u16(bytes, offset)?,
+            offset: layout.read_u16(bytes, offset + 2)?,
+            length: layout.read_u16(bytes, offset + 4)?,
+        })
+    }
+
+    pub fn write(&self, bytes: &mut [u8], offset: usize, layout: &Layout) {
+        layout.write_u16(bytes, offset, self.index);
+        layout.write_u16(bytes, offset + 2, self.offset);
+        layout.write_u16(bytes, offset + 4, self.length);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sas::Endianness;
+
+    #[test]
+    fn test_signature_from_u32() {
+        assert_eq!(
+            SubheaderSignature::from_u32(0xF7F7F7F7),
+            SubheaderSignature::RowSize
+        );
+        assert_eq!(
+            SubheaderSignature::from_u32(0xFFFFFFFD),
+            SubheaderSignature::ColumnText
+        );
+        assert_eq!(
+            SubheaderSignature::from_u32(0x1234),
+            SubheaderSignature::Unknown(0x1234)
+        );
+        assert!(!SubheaderSignature::Unknown(0x1234).is_recognized());
+        assert!(SubheaderSignature::Unknown(0xFFFFFFFB).is_recognized());
+    }
+
+    #[test]
+    fn can_round_trip_signatures() {
+        let signatures = [
+            SubheaderSignature::RowSize,
+            SubheaderSignature::ColumnSize,
+            SubheaderSignature::SubheaderCounts,
+            SubheaderSignature::ColumnName,
+        ];
+        for u64 in [true, false] {
+            for endianness in [Endianness::Little, Endianness::Big] {
+                let layout = Layout::new(u64, endianness);
+                for signature in signatures {
+                    let mut bytes = [0_u8; 8];
+                    signature.write(&mut bytes, &layout);
+                    assert_eq!(SubheaderSignature::read(&bytes, &layout), Ok(signature));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_64_bit_signature_bytes() {
+        let layout = Layout::new(true, Endianness::Little);
+        let mut bytes = [0_u8; 8];
+        SubheaderSignature::RowSize.write(&mut bytes, &layout);
+        assert_eq!(bytes, [0xF7, 0xF7, 0xF7, 0xF7, 0, 0, 0, 0]);
+
+        SubheaderSignature::SubheaderCounts.write(&mut bytes, &layout);
+        assert_eq!(bytes, [0x00, 0xFC, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn can_round_trip_subheader_pointer() {
+        let layout = Layout::new(true, Endianness::Little);
+        let pointer = SubheaderPointer {
+            offset: 4000,
+            length: 96,
+            compression: Compression::Rle,
+            is_compressed_data: true,
+        };
+        let mut bytes = vec![0_u8; 24];
+        pointer.write(&mut bytes, 0, &layout);
+
+        assert_eq!(SubheaderPointer::read(&bytes, 0, &layout), Ok(pointer));
+        assert!(!pointer.is_empty());
+    }
+
+    #[test]
+    fn cannot_read_truncated_subheader_pointer() {
+        let layout = Layout::new(true, Endianness::Little);
+        assert!(SubheaderPointer::read(&[0; 17], 0, &layout).is_err());
+    }
+
+    #[test]
+    fn can_round_trip_text_ref() {
+        let layout = Layout::new(false, Endianness::Big);
+        let text_ref = TextRef {
+            index: 1,
+            offset: 28,
+            length: 8,
+        };
+        let mut bytes = [0_u8; 6];
+        text_ref.write(&mut bytes, 0, &layout);
+        assert_eq!(TextRef::read(&bytes, 0, &layout), Ok(text_ref));
+    }
+}
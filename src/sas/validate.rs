@@ -0,0 +1,648 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, SeekFrom};
+use std::path::Path;
+
+use crate::sas::errors::Error;
+use crate::sas::layout::Layout;
+use crate::sas::subheader::{SubheaderPointer, SubheaderSignature};
+use crate::sas::{rdc, rle};
+use crate::sas::{
+    Compression, Encoding, PageBinary, PageType, Sas7bdatReader, SasConstants, SasHeaderBinary,
+};
+use crate::traits::ReadSeek;
+use crate::util::time::sas_timestamp_to_datetime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something unusual that readers can work around.
+    Warning,
+    /// Something that makes the file unreadable or its contents wrong.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a file, located by the byte offset it concerns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at byte {}: {}",
+            self.severity, self.offset, self.message
+        )
+    }
+}
+
+/// Where the pages are, according to the file header.
+struct Geometry {
+    layout: Layout,
+    header_length: usize,
+    page_size: usize,
+    page_count: usize,
+}
+
+/// What the metadata says about rows, for checking the pages that hold them.
+struct RowLayout {
+    row_length: usize,
+    row_count: usize,
+    mix_page_row_count: usize,
+    compression: Compression,
+    encoding: Encoding,
+    columns: Vec<(String, usize, usize)>,
+}
+
+/// Undecodable values found in one character column.
+#[derive(Default, Clone, Copy)]
+struct StringTally {
+    count: usize,
+    first_offset: usize,
+    first_row: usize,
+}
+
+struct Validator<'a, R: ReadSeek> {
+    source: &'a mut R,
+    file_length: usize,
+    issues: Vec<Issue>,
+}
+
+/// Check a sas7bdat file from its header down to its rows, collecting every
+/// issue instead of stopping at the first. Errors are returned only when the
+/// source itself cannot be read.
+pub fn validate<R: ReadSeek>(source: &mut R) -> Result<Vec<Issue>, Error> {
+    let file_length = source.seek(SeekFrom::End(0))? as usize;
+    let mut validator = Validator {
+        source,
+        file_length,
+        issues: Vec::new(),
+    };
+
+    if let Some(geometry) = validator.check_header()? {
+        let rows = validator.read_row_layout(geometry.header_length)?;
+        validator.check_pages(&geometry, rows.as_ref())?;
+    }
+
+    let mut issues = validator.issues;
+    issues.sort_by_key(|issue| issue.offset);
+    Ok(issues)
+}
+
+pub fn validate_file<P: AsRef<Path>>(path: P) -> Result<Vec<Issue>, Error> {
+    let mut source = BufReader::new(File::open(path)?);
+    validate(&mut source)
+}
+
+impl<R: ReadSeek> Validator<'_, R> {
+    fn report(&mut self, severity: Severity, offset: usize, message: String) {
+        self.issues.push(Issue {
+            severity,
+            offset,
+            message,
+        });
+    }
+
+    fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
+
+    fn read_at(&mut self, offset: usize, length: usize) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![0_u8; length];
+        self.source.seek(SeekFrom::Start(offset as u64))?;
+        self.source.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Check the fixed header fields, returning where the pages are if the
+    /// header is sound enough to find them.
+    fn check_header(&mut self) -> Result<Option<Geometry>, Error> {
+        let constants = SasConstants::new();
+        let minimum = constants.file_header_size_32_bit as usize;
+        if self.file_length < minimum {
+            self.report(
+                Severity::Error,
+                0,
+                format!(
+                    "File is {} bytes, shorter than the smallest header of {} bytes",
+                    self.file_length, minimum
+                ),
+            );
+            return Ok(None);
+        }
+
+        let header = SasHeaderBinary::new(&self.read_at(0, minimum)?);
+        if header.get_magic_number_from_header() != constants.magic_number {
+            self.report(
+                Severity::Error,
+                0,
+                "Magic number is not that of a sas7bdat file".to_string(),
+            );
+        }
+        if !header.is_56_to_64_valid() {
+            self.report(
+                Severity::Error,
+                56,
+                format!(
+                    "Bytes 56..64 {:02x?} do not repeat bytes 32..40 {:02x?}",
+                    &header.bytes[56..64],
+                    &header.bytes[32..40]
+                ),
+            );
+        }
+        if header.get_sas_file_from_header_as_str() != "SAS FILE" {
+            self.report(
+                Severity::Error,
+                84,
+                "Bytes 84..92 do not read \"SAS FILE\"".to_string(),
+            );
+        }
+        if header.get_sas_filename_from_header().is_empty() {
+            self.report(Severity::Warning, 92, "Dataset name is empty".to_string());
+        }
+        if header.get_os_type_from_header().is_err() {
+            self.report(
+                Severity::Warning,
+                39,
+                format!("Unknown OS type {}", header.bytes[39]),
+            );
+        }
+        if let Err(error) = header.get_character_encoding_from_header() {
+            let message = if header.is_ebcdic_host() {
+                error
+            } else {
+                format!("Unknown character encoding {}", header.bytes[70])
+            };
+            self.report(Severity::Error, 70, message);
+        }
+        let endianness = match header.get_endianness_from_header() {
+            Ok(endianness) => endianness,
+            Err(_) => {
+                self.report(
+                    Severity::Error,
+                    37,
+                    format!("Unknown byte order {}", header.bytes[37]),
+                );
+                return Ok(None);
+            }
+        };
+
+        let layout = Layout::new(header.get_a2() == 4, endianness);
+        let a1 = header.get_a1() as usize;
+        let header_length = layout.read_u32(&header.bytes, 196 + a1)? as usize;
+        let page_size = layout.read_u32(&header.bytes, 200 + a1)? as usize;
+        let page_count = layout.read_int(&header.bytes, 204 + a1)? as usize;
+
+        for (name, offset) in [("Creation", 164 + a1), ("Modification", 172 + a1)] {
+            let timestamp = layout.read_f64(&header.bytes, offset)?;
+            if sas_timestamp_to_datetime(timestamp).is_none() {
+                self.report(
+                    Severity::Warning,
+                    offset,
+                    format!("{} timestamp {} is not a valid date", name, timestamp),
+                );
+            }
+        }
+
+        if header_length < minimum || header_length > self.file_length {
+            self.report(
+                Severity::Error,
+                196 + a1,
+                format!(
+                    "Header length {} is outside the file of {} bytes",
+                    header_length, self.file_length
+                ),
+            );
+            return Ok(None);
+        }
+        if page_size < layout.page_header_size() {
+            self.report(
+                Severity::Error,
+                200 + a1,
+                format!("Page size {} is smaller than a page header", page_size),
+            );
+            return Ok(None);
+        }
+
+        let room = (self.file_length - header_length) / page_size;
+        let expected = page_count
+            .checked_mul(page_size)
+            .and_then(|length| length.checked_add(header_length));
+        if expected != Some(self.file_length) {
+            self.report(
+                Severity::Error,
+                204 + a1,
+                format!(
+                    "Header says {} pages of {} bytes after {} header bytes, \
+                     but the file is {} bytes, room for {} pages",
+                    page_count, page_size, header_length, self.file_length, room
+                ),
+            );
+        }
+
+        Ok(Some(Geometry {
+            layout,
+            header_length,
+            page_size,
+            page_count: page_count.min(room),
+        }))
+    }
+
+    /// Read the dataset metadata with the regular reader. Failures are only
+    /// reported when the header checks did not already explain them.
+    fn read_row_layout(&mut self, header_length: usize) -> Result<Option<RowLayout>, Error> {
+        match Sas7bdatReader::new(&mut *self.source) {
+            Ok(reader) => {
+                let properties = reader.properties();
+                Ok(Some(RowLayout {
+                    row_length: properties.row_length,
+                    row_count: properties.row_count,
+                    mix_page_row_count: properties.mix_page_row_count,
+                    compression: properties.compression,
+                    encoding: properties.encoding,
+                    columns: reader.character_spans(),
+                }))
+            }
+            Err(Error::Io(message)) => Err(Error::Io(message)),
+            Err(error) => {
+                if !self.has_errors() {
+                    self.report(
+                        Severity::Error,
+                        header_length,
+                        format!("Could not read the dataset metadata: {}", error),
+                    );
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Walk every page that fits in the file, checking its subheader
+    /// pointers and, when the metadata could be read, its rows.
+    fn check_pages(&mut self, geometry: &Geometry, rows: Option<&RowLayout>) -> Result<(), Error> {
+        let layout = geometry.layout;
+        let header_size = layout.page_header_size();
+        let pointer_size = layout.subheader_pointer_size();
+        let mut found_rows = 0;
+        let mut row_count_offset = geometry.header_length;
+        let mut tallies = vec![StringTally::default(); rows.map_or(0, |rows| rows.columns.len())];
+        let mut unchecked_compression = false;
+
+        for index in 0..geometry.page_count {
+            let page_offset = geometry.header_length + index * geometry.page_size;
+            let bytes = self.read_at(page_offset, geometry.page_size)?;
+            let page = match PageBinary::new(&bytes, &layout) {
+                Ok(page) => page,
+                Err(error) => {
+                    self.report(
+                        Severity::Error,
+                        page_offset,
+                        format!("Page {} cannot be read: {}", index, error),
+                    );
+                    continue;
+                }
+            };
+            let page_type = page.page_type();
+            if let PageType::Unknown(value) = page_type {
+                self.report(
+                    Severity::Warning,
+                    page_offset + header_size - 8,
+                    format!("Page {} has unknown type {}", index, value),
+                );
+                continue;
+            }
+
+            let table_end = header_size + page.subheader_pointers.len();
+            for i in 0..page.subheader_count as usize {
+                let pointer_offset = page_offset + header_size + i * pointer_size;
+                let pointer = match SubheaderPointer::read(
+                    &page.subheader_pointers,
+                    i * pointer_size,
+                    &layout,
+                ) {
+                    Ok(pointer) => pointer,
+                    Err(error) => {
+                        self.report(
+                            Severity::Error,
+                            pointer_offset,
+                            format!("Page {} pointer {}: {}", index, i, error),
+                        );
+                        continue;
+                    }
+                };
+                if pointer.is_empty() {
+                    continue;
+                }
+                let end = pointer.offset.checked_add(pointer.length);
+                if pointer.offset < table_end || end.is_none_or(|end| end > geometry.page_size) {
+                    self.report(
+                        Severity::Error,
+                        pointer_offset,
+                        format!(
+                            "Page {} pointer {} covers {} bytes at {}, outside {}..{}",
+                            index, i, pointer.length, pointer.offset, table_end, geometry.page_size
+                        ),
+                    );
+                    continue;
+                }
+
+                let subheader = &page.data[pointer.offset..pointer.offset + pointer.length];
+                let subheader_offset = page_offset + pointer.offset;
+                let Some(rows) = rows else {
+                    continue;
+                };
+                if pointer.compression != Compression::None {
+                    found_rows += 1;
+                    let row = match rows.compression {
+                        Compression::Rle => rle::decompress(subheader, rows.row_length),
+                        Compression::Rdc => rdc::decompress(subheader, rows.row_length),
+                        _ => {
+                            unchecked_compression = true;
+                            continue;
+                        }
+                    };
+                    match row {
+                        Ok(row) => self.tally_strings(
+                            rows,
+                            &row,
+                            |_| subheader_offset,
+                            found_rows,
+                            &mut tallies,
+                        ),
+                        Err(error) => self.report(
+                            Severity::Error,
+                            subheader_offset,
+                            format!("Row {} cannot be decompressed: {}", found_rows, error),
+                        ),
+                    }
+                    continue;
+                }
+
+                let signature = SubheaderSignature::read(subheader, &layout).ok();
+                if signature == Some(SubheaderSignature::RowSize) {
+                    row_count_offset = subheader_offset + 6 * layout.int_len();
+                }
+                if pointer.is_compressed_data && !signature.is_some_and(|s| s.is_recognized()) {
+                    found_rows += 1;
+                    if subheader.len() != rows.row_length {
+                        self.report(
+                            Severity::Error,
+                            subheader_offset,
+                            format!(
+                                "Row {} is stored in {} bytes, expected {}",
+                                found_rows,
+                                subheader.len(),
+                                rows.row_length
+                            ),
+                        );
+                        continue;
+                    }
+                    self.tally_strings(
+                        rows,
+                        subheader,
+                        |cell| subheader_offset + cell,
+                        found_rows,
+                        &mut tallies,
+                    );
+                }
+            }
+
+            let Some(rows) = rows else {
+                continue;
+            };
+            let count = match page_type {
+                PageType::Data => page.block_count as usize,
+                PageType::Mix => rows
+                    .mix_page_row_count
+                    .min(rows.row_count.saturating_sub(found_rows)),
+                _ => 0,
+            };
+            let start = page.row_offset(&layout);
+            for i in 0..count {
+                let row_start = start + i * rows.row_length;
+                let Some(row) = page.data.get(row_start..row_start + rows.row_length) else {
+                    self.report(
+                        Severity::Error,
+                        page_offset + header_size - 6,
+                        format!(
+                            "Page {} says it holds {} rows, but only {} fit",
+                            index, count, i
+                        ),
+                    );
+                    break;
+                };
+                found_rows += 1;
+                self.tally_strings(
+                    rows,
+                    row,
+                    |cell| page_offset + row_start + cell,
+                    found_rows,
+                    &mut tallies,
+                );
+            }
+        }
+
+        let Some(rows) = rows else {
+            return Ok(());
+        };
+        if found_rows != rows.row_count {
+            self.report(
+                Severity::Error,
+                row_count_offset,
+                format!(
+                    "Row count is {}, but the pages hold {} rows",
+                    rows.row_count, found_rows
+                ),
+            );
+        }
+        if unchecked_compression {
+            self.report(
+                Severity::Warning,
+                geometry.header_length,
+                "Compressed rows use an unsupported compression and were not checked".to_string(),
+            );
+        }
+        for ((name, _, _), tally) in rows.columns.iter().zip(tallies) {
+            if tally.count > 0 {
+                self.report(
+                    Severity::Error,
+                    tally.first_offset,
+                    format!(
+                        "Column {} has {} values that are not valid {:?}, the first in row {}",
+                        name, tally.count, rows.encoding, tally.first_row
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the character cells of a row that do not decode. `locate` maps
+    /// an offset within the row to an offset in the file.
+    fn tally_strings(
+        &mut self,
+        rows: &RowLayout,
+        row: &[u8],
+        locate: impl Fn(usize) -> usize,
+        row_number: usize,
+        tallies: &mut [StringTally],
+    ) {
+        for ((_, offset, length), tally) in rows.columns.iter().zip(tallies.iter_mut()) {
+            let Some(cell) = row.get(*offset..offset + length) else {
+                continue;
+            };
+            if rows.encoding.is_decodable(cell) {
+                continue;
+            }
+            if tally.count == 0 {
+                tally.first_offset = locate(*offset);
+                tally.first_row = row_number;
+            }
+            tally.count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::sas::{Column, Sas7bdatWriter, Schema, Value, WriterOptions};
+
+    const FILENAME: &str = "test/hadley.sas7bdat";
+
+    fn validate_bytes(bytes: Vec<u8>) -> Vec<Issue> {
+        validate(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    fn messages(issues: &[Issue]) -> Vec<(Severity, usize, &str)> {
+        issues
+            .iter()
+            .map(|issue| (issue.severity, issue.offset, issue.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn can_validate_clean_file() {
+        assert_eq!(validate_file(FILENAME), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn can_collect_every_header_issue() {
+        let mut bytes = std::fs::read(FILENAME).unwrap();
+        bytes[0] ^= 0xff;
+        bytes[57] ^= 0xff;
+        let issues = validate_bytes(bytes);
+
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].offset, 0);
+        assert!(issues[0].message.contains("Magic number"));
+        assert_eq!(issues[1].offset, 56);
+        assert!(issues[1].message.starts_with("Bytes 56..64"));
+    }
+
+    #[test]
+    fn can_report_invalid_timestamps() {
+        let mut bytes = std::fs::read(FILENAME).unwrap();
+        let header = SasHeaderBinary::new(&bytes);
+        let offset = 164 + header.get_a1() as usize;
+        let layout = Sas7bdatReader::new(Cursor::new(bytes.clone()))
+            .unwrap()
+            .properties()
+            .layout;
+        layout.write_f64(&mut bytes, offset, f64::NAN);
+
+        let reader = Sas7bdatReader::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(reader.properties().created, None);
+        assert!(reader.properties().modified.is_some());
+        assert_eq!(
+            messages(&validate_bytes(bytes)),
+            vec![(
+                Severity::Warning,
+                offset,
+                "Creation timestamp NaN is not a valid date"
+            )]
+        );
+    }
+
+    #[test]
+    fn can_report_truncated_file() {
+        let bytes = std::fs::read(FILENAME).unwrap();
+        let issues = validate_bytes(bytes[..bytes.len() - 100].to_vec());
+
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert!(issues[0].message.starts_with("Header says 1 pages"));
+        assert!(issues.iter().all(|issue| issue.severity == Severity::Error));
+    }
+
+    #[test]
+    fn can_report_short_file() {
+        let issues = validate_bytes(vec![0_u8; 100]);
+        assert_eq!(
+            messages(&issues),
+            vec![(
+                Severity::Error,
+                0,
+                "File is 100 bytes, shorter than the smallest header of 1024 bytes"
+            )]
+        );
+    }
+
+    #[test]
+    fn can_report_invalid_pointer() {
+        let mut bytes = std::fs::read(FILENAME).unwrap();
+        let reader = Sas7bdatReader::new(Cursor::new(bytes.clone())).unwrap();
+        let properties = reader.properties().clone();
+        let layout = properties.layout;
+        let pointer_offset = properties.header_length + layout.page_header_size();
+        layout.write_int(&mut bytes, pointer_offset, properties.page_size as u64);
+
+        let issues = validate_bytes(bytes);
+        assert!(issues
+            .iter()
+            .any(|issue| issue.offset == pointer_offset && issue.message.contains("pointer 0")));
+    }
+
+    #[test]
+    fn can_report_undecodable_strings() {
+        let schema = Schema::new("TEXT", vec![Column::character("name", 8)]);
+        let path = std::env::temp_dir().join(format!(
+            "sas-reader-2000-validate-{}.sas7bdat",
+            std::process::id()
+        ));
+        let mut writer = Sas7bdatWriter::create(&path, schema, WriterOptions::default()).unwrap();
+        for name in ["caf\u{e9}", "ok", "na\u{ef}ve"] {
+            writer.write_row(&[Value::from(name)]).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for i in 0..bytes.len() - 1 {
+            if bytes[i..i + 2] == [0xc3, 0xa9] || bytes[i..i + 2] == [0xc3, 0xaf] {
+                bytes[i] = 0xff;
+            }
+        }
+
+        let issues = validate_bytes(bytes);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0]
+            .message
+            .starts_with("Column name has 2 values that are not valid Utf8, the first in row 1"));
+    }
+}
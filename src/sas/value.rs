@@ -0,0 +1,153 @@
+use std::fmt;
+
+/// A numeric missing value. SAS distinguishes the ordinary `.` from the
+/// special missing values `._` and `.A` through `.Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MissingValue {
+    System,
+    Special(char),
+}
+
+impl MissingValue {
+    pub fn special(tag: char) -> Result<Self, String> {
+        let tag = tag.to_ascii_uppercase();
+        if tag == '_' || tag.is_ascii_uppercase() {
+            Ok(MissingValue::Special(tag))
+        } else {
+            Err(format!(
+                "Invalid special missing value: .{}. Expected ._ or .A through .Z.",
+                tag
+            ))
+        }
+    }
+
+    /// The character SAS prints for this missing value under a numeric format.
+    pub fn display_char(&self) -> char {
+        match self {
+            MissingValue::System => '.',
+            MissingValue::Special(tag) => *tag,
+        }
+    }
+}
+
+impl fmt::Display for MissingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissingValue::System => write!(f, "."),
+            MissingValue::Special(tag) => write!(f, ".{}", tag),
+        }
+    }
+}
+
+/// A single cell of a SAS dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Missing(MissingValue),
+    /// A character cell kept as bytes: one that is not valid in the file's
+    /// encoding, read with [`InvalidBytes::Raw`](crate::sas::InvalidBytes::Raw)
+    /// and without its trailing blanks, or a whole cell from
+    /// [`RawRow::to_row`](crate::sas::RawRow::to_row). Text exporters
+    /// write it with replacement characters; the sas7bdat and XPORT writers
+    /// store it unchanged.
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Value::Missing(_))
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Character cells as text, with undecodable bytes replaced.
+    pub fn to_text(&self) -> Option<std::borrow::Cow<'_, str>> {
+        match self {
+            Value::String(value) => Some(value.into()),
+            Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes)),
+            _ => None,
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<MissingValue> for Value {
+    fn from(value: MissingValue) -> Self {
+        Value::Missing(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_make_special_missing_value() {
+        assert_eq!(MissingValue::special('a'), Ok(MissingValue::Special('A')));
+        assert_eq!(MissingValue::special('_'), Ok(MissingValue::Special('_')));
+    }
+
+    #[test]
+    fn cannot_make_special_missing_value_from_digit() {
+        assert!(MissingValue::special('1').is_err());
+    }
+
+    #[test]
+    fn can_display_missing_values() {
+        assert_eq!(MissingValue::System.to_string(), ".");
+        assert_eq!(MissingValue::Special('Z').to_string(), ".Z");
+        assert_eq!(MissingValue::Special('Z').display_char(), 'Z');
+    }
+
+    #[test]
+    fn can_convert_into_values() {
+        assert_eq!(Value::from(1.5), Value::Number(1.5));
+        assert_eq!(Value::from("abc"), Value::String("abc".to_string()));
+        assert_eq!(
+            Value::from(MissingValue::System),
+            Value::Missing(MissingValue::System)
+        );
+    }
+
+    #[test]
+    fn test_value_accessors() {
+        assert_eq!(Value::Number(2.0).as_f64(), Some(2.0));
+        assert_eq!(Value::String("x".to_string()).as_str(), Some("x"));
+        assert!(Value::Missing(MissingValue::System).is_missing());
+        assert!(!Value::Number(0.0).is_missing());
+        assert_eq!(
+            Value::Bytes(b"a\xff".to_vec()).to_text().unwrap(),
+            "a\u{FFFD}"
+        );
+    }
+}
@@ -0,0 +1,934 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime};
+use derive_builder::Builder;
+
+use crate::sas::compression::{RDC_SIGNATURE, RLE_SIGNATURE};
+use crate::sas::errors::Error;
+use crate::sas::layout::Layout;
+use crate::sas::numeric::encode_numeric;
+use crate::sas::schema::Schema;
+use crate::sas::subheader::{SubheaderPointer, SubheaderSignature, TextRef};
+use crate::sas::{rdc, rle};
+use crate::sas::{
+    Column, ColumnType, Compression, Encoding, Endianness, PageType, SasConstants, Value,
+};
+use crate::util::time::datetime_to_sas_timestamp;
+
+const SAS_RELEASE: &str = "9.0401M0";
+const SERVER_TYPE: &str = "Linux";
+const OS_NAME: &str = "x86_64";
+/// Server type written for the EBCDIC code pages, as SAS on z/OS does.
+const MAINFRAME_SERVER_TYPE: &str = "z/OS";
+
+/// Text in a column text subheader starts after the remainder, the
+/// compression literal and the creator name.
+const TEXT_START: usize = 28;
+const MAX_TEXT_BLOB: usize = 32_740;
+
+/// Options for writing a sas7bdat file.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct WriterOptions {
+    pub page_size: usize,
+    pub compression: Compression,
+    pub encoding: Encoding,
+    pub created: NaiveDateTime,
+    pub modified: NaiveDateTime,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        let now = Local::now().naive_local();
+        WriterOptions {
+            page_size: SasConstants::new().default_page_size as usize,
+            compression: Compression::None,
+            encoding: Encoding::Utf8,
+            created: now,
+            modified: now,
+        }
+    }
+}
+
+/// Collects strings into column text subheaders and hands out references
+/// to them.
+struct TextPool {
+    blobs: Vec<Vec<u8>>,
+    refs: HashMap<Vec<u8>, TextRef>,
+    max_blob: usize,
+}
+
+impl TextPool {
+    fn new(max_blob: usize) -> Self {
+        TextPool {
+            blobs: vec![vec![b' '; TEXT_START]],
+            refs: HashMap::new(),
+            max_blob,
+        }
+    }
+
+    /// Store the compression literal in the slot reserved for it at the
+    /// start of the first text subheader.
+    fn set_compression_literal(&mut self, literal: &[u8]) -> TextRef {
+        self.blobs[0][12..20].copy_from_slice(literal);
+        TextRef {
+            index: 0,
+            offset: 12,
+            length: 8,
+        }
+    }
+
+    fn add(&mut self, bytes: &[u8]) -> TextRef {
+        if bytes.is_empty() {
+            return TextRef::default();
+        }
+        if let Some(text_ref) = self.refs.get(bytes) {
+            return *text_ref;
+        }
+
+        let padded = bytes.len().div_ceil(4) * 4;
+        if self.blobs.last().unwrap().len() + padded > self.max_blob {
+            self.blobs.push(vec![b' '; TEXT_START]);
+        }
+
+        let index = self.blobs.len() - 1;
+        let blob = &mut self.blobs[index];
+        let text_ref = TextRef {
+            index: index as u16,
+            offset: blob.len() as u16,
+            length: bytes.len() as u16,
+        };
+        blob.extend_from_slice(bytes);
+        blob.resize(blob.len() + padded - bytes.len(), b' ');
+        self.refs.insert(bytes.to_vec(), text_ref);
+        text_ref
+    }
+}
+
+/// A page being filled with subheaders. Pointers grow from the start of the
+/// page and subheaders from the end.
+struct SubheaderPage {
+    bytes: Vec<u8>,
+    count: usize,
+    data_start: usize,
+}
+
+impl SubheaderPage {
+    fn new(page_size: usize) -> Self {
+        SubheaderPage {
+            bytes: vec![0_u8; page_size],
+            count: 0,
+            data_start: page_size,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn fits(&self, len: usize, layout: &Layout) -> bool {
+        let pointers_end =
+            layout.page_header_size() + (self.count + 1) * layout.subheader_pointer_size();
+        self.count < u16::MAX as usize
+            && self.data_start >= len
+            && pointers_end <= (self.data_start - len) & !7
+    }
+
+    fn add(&mut self, subheader: &[u8], compression: Compression, is_row: bool, layout: &Layout) {
+        let offset = (self.data_start - subheader.len()) & !7;
+        self.bytes[offset..offset + subheader.len()].copy_from_slice(subheader);
+        let pointer = SubheaderPointer {
+            offset,
+            length: subheader.len(),
+            compression,
+            is_compressed_data: is_row,
+        };
+        let pointer_offset =
+            layout.page_header_size() + self.count * layout.subheader_pointer_size();
+        pointer.write(&mut self.bytes, pointer_offset, layout);
+        self.count += 1;
+        self.data_start = offset;
+    }
+
+    fn finish(mut self, page_type: PageType, layout: &Layout) -> Vec<u8> {
+        let header_size = layout.page_header_size();
+        layout.write_u16(&mut self.bytes, header_size - 8, page_type.to_u16());
+        layout.write_u16(&mut self.bytes, header_size - 6, self.count as u16);
+        layout.write_u16(&mut self.bytes, header_size - 4, self.count as u16);
+        self.bytes
+    }
+}
+
+/// Writes a schema and rows as a 64-bit little-endian sas7bdat file. The
+/// `testing` fixtures also write the other layouts.
+///
+/// The header and metadata pages are reserved up front and filled in by
+/// `finish`, once the row count is known; data pages are streamed. With RLE
+/// or RDC compression, rows are stored as subheaders on meta pages instead.
+pub struct Sas7bdatWriter<W: Write + Seek> {
+    sink: W,
+    schema: Schema,
+    options: WriterOptions,
+    layout: Layout,
+    page_size: usize,
+    start: u64,
+    meta_page_count: usize,
+    data_page_count: usize,
+    rows_per_page: usize,
+    page_rows: Vec<u8>,
+    rows_on_page: usize,
+    row_page: SubheaderPage,
+    row_count: usize,
+}
+
+impl Sas7bdatWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        schema: Schema,
+        options: WriterOptions,
+    ) -> Result<Self, Error> {
+        let file = File::create(path)?;
+        Sas7bdatWriter::new(BufWriter::new(file), schema, options)
+    }
+}
+
+impl<W: Write + Seek> Sas7bdatWriter<W> {
+    pub fn new(sink: W, schema: Schema, options: WriterOptions) -> Result<Self, Error> {
+        Sas7bdatWriter::with_layout(sink, schema, options, Layout::new(true, Endianness::Little))
+    }
+
+    pub(crate) fn with_layout(
+        mut sink: W,
+        schema: Schema,
+        options: WriterOptions,
+        layout: Layout,
+    ) -> Result<Self, Error> {
+        schema.validate()?;
+        if options.compression == Compression::Truncated {
+            return Err(Error::UnsupportedCompression(
+                "Datasets are compressed with RLE, RDC or not at all".to_string(),
+            ));
+        }
+
+        let row_length = schema.row_length();
+        let page_size = required_page_size(&schema, &options, &layout)?;
+        // The page header counts rows in 16 bits.
+        let rows_per_page =
+            ((page_size - layout.page_header_size()) / row_length).min(u16::MAX as usize);
+
+        let mut writer = Sas7bdatWriter {
+            start: sink.stream_position()?,
+            sink,
+            schema,
+            options,
+            layout,
+            page_size,
+            meta_page_count: 0,
+            data_page_count: 0,
+            rows_per_page,
+            page_rows: Vec::with_capacity(rows_per_page * row_length),
+            rows_on_page: 0,
+            row_page: SubheaderPage::new(page_size),
+            row_count: 0,
+        };
+
+        // Metadata has the same size whatever the row count, so its pages can
+        // be reserved now and rewritten at the end.
+        writer.meta_page_count = writer.meta_pages()?.len();
+        let reserved = header_length(&layout) + writer.meta_page_count * page_size;
+        writer.sink.write_all(&vec![0_u8; reserved])?;
+        Ok(writer)
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+        let bytes = self.encode_row(row)?;
+        self.row_count += 1;
+        if self.options.compression != Compression::None {
+            return self.write_compressed_row(&bytes);
+        }
+
+        self.page_rows.extend_from_slice(&bytes);
+        self.rows_on_page += 1;
+        if self.rows_on_page == self.rows_per_page {
+            self.flush_data_page()?;
+        }
+        Ok(())
+    }
+
+    /// Rows that do not shrink are stored as they are, unless their first
+    /// bytes would be mistaken for a subheader signature.
+    fn write_compressed_row(&mut self, row: &[u8]) -> Result<(), Error> {
+        let compressed = match self.options.compression {
+            Compression::Rdc => rdc::compress(row),
+            _ => rle::compress(row),
+        };
+        let looks_like_metadata = SubheaderSignature::read(row, &self.layout)
+            .map_or(true, |signature| signature.is_recognized());
+        let (bytes, compression) = if compressed.len() < row.len() || looks_like_metadata {
+            (compressed.as_slice(), self.options.compression)
+        } else {
+            (row, Compression::None)
+        };
+
+        if !self.row_page.fits(bytes.len(), &self.layout) {
+            self.flush_row_page()?;
+        }
+        self.row_page.add(bytes, compression, true, &self.layout);
+        Ok(())
+    }
+
+    fn flush_row_page(&mut self) -> Result<(), Error> {
+        let page = std::mem::replace(&mut self.row_page, SubheaderPage::new(self.page_size));
+        self.sink
+            .write_all(&page.finish(PageType::Meta, &self.layout))?;
+        self.data_page_count += 1;
+        Ok(())
+    }
+
+    /// Write the last data page, then go back and fill in the header and
+    /// metadata pages.
+    pub fn finish(mut self) -> Result<W, Error> {
+        if self.rows_on_page > 0 {
+            self.flush_data_page()?;
+        }
+        if !self.row_page.is_empty() {
+            self.flush_row_page()?;
+        }
+
+        let meta_pages = self.meta_pages()?;
+        let header = self.header_bytes()?;
+        self.sink.seek(SeekFrom::Start(self.start))?;
+        self.sink.write_all(&header)?;
+        for page in meta_pages {
+            self.sink.write_all(&page)?;
+        }
+        self.sink.seek(SeekFrom::End(0))?;
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    fn encode_row(&self, row: &[Value]) -> Result<Vec<u8>, Error> {
+        if row.len() != self.schema.columns.len() {
+            return Err(Error::InvalidRow(format!(
+                "Expected {} values but got {}",
+                self.schema.columns.len(),
+                row.len()
+            )));
+        }
+
+        let mut bytes = Vec::with_capacity(self.schema.row_length());
+        for (column, value) in self.schema.columns.iter().zip(row) {
+            let start = bytes.len();
+            bytes.resize(start + column.length, self.options.encoding.blank());
+            let cell = &mut bytes[start..];
+            match column.column_type {
+                ColumnType::Numeric => encode_numeric(value, cell, self.layout.endianness)
+                    .map_err(|error| {
+                        Error::InvalidRow(format!("Column {}: {}", column.name, error))
+                    })?,
+                ColumnType::Character => self.encode_string(column, value, cell)?,
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn encode_string(&self, column: &Column, value: &Value, cell: &mut [u8]) -> Result<(), Error> {
+        let encoded = match value {
+            Value::String(text) => self
+                .options
+                .encoding
+                .encode(text)
+                .map_err(Error::InvalidRow)?,
+            Value::Bytes(bytes) => bytes.clone(),
+            Value::Missing(_) => return Ok(()),
+            Value::Number(number) => {
+                return Err(Error::InvalidRow(format!(
+                    "Cannot store number {} in character column {}",
+                    number, column.name
+                )))
+            }
+        };
+
+        if encoded.len() > column.length {
+            return Err(Error::InvalidRow(format!(
+                "Value {:?} is {} bytes, longer than column {} ({} bytes)",
+                value.to_text().unwrap_or_default(),
+                encoded.len(),
+                column.name,
+                column.length
+            )));
+        }
+        cell[..encoded.len()].copy_from_slice(&encoded);
+        Ok(())
+    }
+
+    fn flush_data_page(&mut self) -> Result<(), Error> {
+        let header_size = self.layout.page_header_size();
+        let mut page = vec![0_u8; self.page_size];
+        self.layout
+            .write_u16(&mut page, header_size - 8, PageType::Data.to_u16());
+        self.layout
+            .write_u16(&mut page, header_size - 6, self.rows_on_page as u16);
+        page[header_size..header_size + self.page_rows.len()].copy_from_slice(&self.page_rows);
+
+        self.sink.write_all(&page)?;
+        self.page_rows.clear();
+        self.rows_on_page = 0;
+        self.data_page_count += 1;
+        Ok(())
+    }
+
+    fn encode_text(&self, text: &str) -> Result<Vec<u8>, Error> {
+        self.options
+            .encoding
+            .encode(text)
+            .map_err(Error::InvalidSchema)
+    }
+
+    /// Fixed header and subheader text, which a mainframe stores in EBCDIC.
+    fn host_text(&self, text: &str) -> Result<Vec<u8>, Error> {
+        if self.options.encoding.is_ebcdic() {
+            self.encode_text(text)
+        } else {
+            Ok(text.as_bytes().to_vec())
+        }
+    }
+
+    /// Largest subheader that fits on a page by itself.
+    fn max_subheader_len(&self) -> usize {
+        self.page_size - self.layout.page_header_size() - self.layout.subheader_pointer_size()
+    }
+
+    fn metadata_subheaders(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let layout = &self.layout;
+        let signature_len = layout.signature_len();
+        let max_blob = MAX_TEXT_BLOB.min(self.max_subheader_len() - signature_len);
+        let mut text = TextPool::new(max_blob);
+
+        let label_ref = text.add(&self.encode_text(&self.schema.label)?);
+        let compression_ref = match self.options.compression {
+            Compression::Rle => text.set_compression_literal(&self.host_text(RLE_SIGNATURE)?),
+            Compression::Rdc => text.set_compression_literal(&self.host_text(RDC_SIGNATURE)?),
+            _ => TextRef::default(),
+        };
+
+        let mut names = Vec::new();
+        let mut formats = Vec::new();
+        for column in &self.schema.columns {
+            names.push(text.add(&self.encode_text(&column.name)?));
+            formats.push((
+                text.add(&self.encode_text(&column.format.name)?),
+                text.add(&self.encode_text(&column.label)?),
+            ));
+        }
+
+        let mut subheaders = vec![
+            self.row_size_subheader(label_ref, compression_ref),
+            self.column_size_subheader(),
+        ];
+
+        for blob in &text.blobs {
+            let mut bytes = vec![0_u8; signature_len + blob.len()];
+            bytes[signature_len..].copy_from_slice(blob);
+            SubheaderSignature::ColumnText.write(&mut bytes, layout);
+            write_remainder(&mut bytes, layout);
+            subheaders.push(bytes);
+        }
+
+        // Name and attribute subheaders hold a signature, eight bytes, their
+        // entries, then twelve bytes and another signature's worth.
+        let int_len = layout.int_len();
+        let list_overhead = 12 + 2 * signature_len;
+        let per_name_subheader = (self.max_subheader_len() - list_overhead) / 8;
+        for chunk in names.chunks(per_name_subheader) {
+            let mut bytes = vec![0_u8; list_overhead + 8 * chunk.len()];
+            SubheaderSignature::ColumnName.write(&mut bytes, layout);
+            write_remainder(&mut bytes, layout);
+            for (i, name_ref) in chunk.iter().enumerate() {
+                name_ref.write(&mut bytes, signature_len + 8 + 8 * i, layout);
+            }
+            subheaders.push(bytes);
+        }
+
+        let entry_len = int_len + 8;
+        let per_attrs_subheader = (self.max_subheader_len() - list_overhead) / entry_len;
+        let columns: Vec<(usize, &Column)> = self
+            .schema
+            .columns
+            .iter()
+            .scan(0, |offset, column| {
+                let start = *offset;
+                *offset += column.length;
+                Some((start, column))
+            })
+            .collect();
+        for chunk in columns.chunks(per_attrs_subheader) {
+            let mut bytes = vec![0_u8; list_overhead + entry_len * chunk.len()];
+            SubheaderSignature::ColumnAttributes.write(&mut bytes, layout);
+            write_remainder(&mut bytes, layout);
+            for (i, (offset, column)) in chunk.iter().enumerate() {
+                let entry = signature_len + 8 + entry_len * i;
+                let name_flag = if column.name.len() <= 8 { 4 } else { 2048 };
+                layout.write_int(&mut bytes, entry, *offset as u64);
+                layout.write_u32(&mut bytes, entry + int_len, column.length as u32);
+                layout.write_u16(&mut bytes, entry + int_len + 4, name_flag);
+                bytes[entry + int_len + 6] = column.column_type.to_u8();
+            }
+            subheaders.push(bytes);
+        }
+
+        let (format_len, format_offset, label_offset) = if layout.u64 {
+            (64, 46, 52)
+        } else {
+            (52, 34, 40)
+        };
+        for (column, (format_ref, label_ref)) in self.schema.columns.iter().zip(formats) {
+            let mut bytes = vec![0_u8; format_len];
+            SubheaderSignature::ColumnFormat.write(&mut bytes, layout);
+            layout.write_u16(&mut bytes, 3 * int_len, column.format.width);
+            layout.write_u16(&mut bytes, 3 * int_len + 2, column.format.decimals);
+            format_ref.write(&mut bytes, format_offset, layout);
+            label_ref.write(&mut bytes, label_offset, layout);
+            subheaders.push(bytes);
+        }
+
+        Ok(subheaders)
+    }
+
+    fn row_size_subheader(&self, label_ref: TextRef, compression_ref: TextRef) -> Vec<u8> {
+        let layout = &self.layout;
+        let int_len = layout.int_len();
+        let len = row_size_len(layout);
+        let mut bytes = vec![0_u8; len];
+        SubheaderSignature::RowSize.write(&mut bytes, layout);
+        layout.write_int(&mut bytes, 5 * int_len, self.schema.row_length() as u64);
+        layout.write_int(&mut bytes, 6 * int_len, self.row_count as u64);
+        layout.write_int(&mut bytes, 9 * int_len, self.schema.columns.len() as u64);
+        layout.write_int(&mut bytes, 13 * int_len, self.page_size as u64);
+        layout.write_int(&mut bytes, 15 * int_len, self.rows_per_page as u64);
+        bytes[16 * int_len..17 * int_len].fill(0xFF);
+        label_ref.write(&mut bytes, len - 130, layout);
+        compression_ref.write(&mut bytes, len - 118, layout);
+        bytes
+    }
+
+    fn column_size_subheader(&self) -> Vec<u8> {
+        let int_len = self.layout.int_len();
+        let mut bytes = vec![0_u8; 3 * int_len];
+        SubheaderSignature::ColumnSize.write(&mut bytes, &self.layout);
+        self.layout
+            .write_int(&mut bytes, int_len, self.schema.columns.len() as u64);
+        bytes
+    }
+
+    /// Pack the metadata subheaders onto meta pages.
+    fn meta_pages(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let mut pages: Vec<SubheaderPage> = Vec::new();
+        for subheader in self.metadata_subheaders()? {
+            if !pages
+                .last()
+                .is_some_and(|page| page.fits(subheader.len(), &self.layout))
+            {
+                pages.push(SubheaderPage::new(self.page_size));
+            }
+            let page = pages.last_mut().unwrap();
+            page.add(&subheader, Compression::None, false, &self.layout);
+        }
+        Ok(pages
+            .into_iter()
+            .map(|page| page.finish(PageType::Meta, &self.layout))
+            .collect())
+    }
+
+    fn header_bytes(&self) -> Result<Vec<u8>, Error> {
+        let layout = &self.layout;
+        let mut bytes = vec![0_u8; header_length(layout)];
+        bytes[..32].copy_from_slice(&SasConstants::new().magic_number);
+
+        // a2, a1, endianness and OS type; bytes 56..64 repeat 32..40
+        let (u64_flag, a2) = if layout.u64 { (0x33, 4) } else { (0x22, 0) };
+        let endianness = match layout.endianness {
+            Endianness::Big => 0x00,
+            Endianness::Little => 0x01,
+        };
+        bytes[32..40].copy_from_slice(&[u64_flag, 0x22, 0x00, 0x33, 0x33, endianness, 0x02, b'1']);
+        bytes.copy_within(32..40, 56);
+        // No code is known for the EBCDIC pages, so it is left unset and
+        // readers have to be given the code page.
+        bytes[70] = self.options.encoding.to_u8().unwrap_or(0);
+
+        bytes[84..92].copy_from_slice(&self.host_text("SAS FILE")?);
+        let name = self.encode_text(&self.schema.name)?;
+        if name.len() > 64 {
+            return Err(Error::InvalidSchema(format!(
+                "Dataset name {} is longer than 64 bytes",
+                self.schema.name
+            )));
+        }
+        bytes[92..156].fill(self.options.encoding.blank());
+        bytes[92..92 + name.len()].copy_from_slice(&name);
+        bytes[156..164].copy_from_slice(&self.host_text("DATA    ")?);
+
+        let a1 = 4;
+        layout.write_f64(
+            &mut bytes,
+            164 + a1,
+            datetime_to_sas_timestamp(self.options.created),
+        );
+        layout.write_f64(
+            &mut bytes,
+            172 + a1,
+            datetime_to_sas_timestamp(self.options.modified),
+        );
+        layout.write_u32(&mut bytes, 196 + a1, header_length(layout) as u32);
+        layout.write_u32(&mut bytes, 200 + a1, self.page_size as u32);
+        layout.write_int(
+            &mut bytes,
+            204 + a1,
+            (self.meta_page_count + self.data_page_count) as u64,
+        );
+
+        write_text(&mut bytes, 216 + a1 + a2, &self.host_text(SAS_RELEASE)?);
+        if self.options.encoding.is_ebcdic() {
+            write_text(
+                &mut bytes,
+                224 + a1 + a2,
+                &self.host_text(MAINFRAME_SERVER_TYPE)?,
+            );
+        } else {
+            write_text(&mut bytes, 224 + a1 + a2, SERVER_TYPE.as_bytes());
+            write_text(&mut bytes, 272 + a1 + a2, OS_NAME.as_bytes());
+        }
+        Ok(bytes)
+    }
+}
+
+fn header_length(layout: &Layout) -> usize {
+    let constants = SasConstants::new();
+    if layout.u64 {
+        constants.file_header_size_64_bit as usize
+    } else {
+        constants.file_header_size_32_bit as usize
+    }
+}
+
+fn row_size_len(layout: &Layout) -> usize {
+    if layout.u64 {
+        808
+    } else {
+        480
+    }
+}
+
+/// The smallest page that holds a row and the largest subheader, rounded up
+/// to a multiple of 1024, or the requested size if that is larger.
+fn required_page_size(
+    schema: &Schema,
+    options: &WriterOptions,
+    layout: &Layout,
+) -> Result<usize, Error> {
+    let overhead = layout.page_header_size() + layout.subheader_pointer_size();
+    let mut longest_text = schema.label.len();
+    for column in &schema.columns {
+        longest_text = longest_text
+            .max(column.name.len())
+            .max(column.label.len())
+            .max(column.format.name.len());
+    }
+    // Allow for multi-byte encodings of non-ASCII text.
+    let longest_text = 8 + TEXT_START + 4 * longest_text + 4;
+
+    // Compressed rows can come out slightly longer than the row itself.
+    let longest_row = schema.row_length() + schema.row_length() / 64 + 16;
+
+    let required = [
+        overhead + 8 + longest_row,
+        overhead + row_size_len(layout),
+        overhead + longest_text,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(0);
+    let required = required.div_ceil(1024) * 1024;
+
+    let page_size = options.page_size.max(required);
+    let max_page_size = SasConstants::new().max_page_size as usize;
+    if page_size > max_page_size {
+        return Err(Error::InvalidSchema(format!(
+            "Page size {} is over the limit of {}",
+            page_size, max_page_size
+        )));
+    }
+    Ok(page_size)
+}
+
+fn write_remainder(bytes: &mut [u8], layout: &Layout) {
+    let remainder = bytes.len() - 4 - 2 * layout.signature_len();
+    layout.write_u16(bytes, layout.signature_len(), remainder as u16);
+}
+
+fn write_text(bytes: &mut [u8], offset: usize, text: &[u8]) {
+    bytes[offset..offset + text.len()].copy_from_slice(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::sas::{ColumnFormat, MissingValue, Sas7bdatReader, SasHeaderBinary};
+    use crate::traits::DatasetReader;
+
+    fn schema() -> Schema {
+        let mut price = Column::numeric("price");
+        price.label = "Unit price".to_string();
+        price.format = ColumnFormat::new("DOLLAR", 10, 2);
+
+        Schema {
+            name: "PRODUCTS".to_string(),
+            label: "Product list".to_string(),
+            columns: vec![Column::numeric("id"), Column::character("name", 12), price],
+        }
+    }
+
+    fn rows(count: usize) -> Vec<Vec<Value>> {
+        (0..count)
+            .map(|i| {
+                let price = match i % 5 {
+                    0 => Value::Missing(MissingValue::System),
+                    1 => Value::Missing(MissingValue::Special('A')),
+                    _ => Value::Number(i as f64 * 1.25),
+                };
+                vec![
+                    Value::Number(i as f64),
+                    Value::String(format!("item {}", i)),
+                    price,
+                ]
+            })
+            .collect()
+    }
+
+    fn write(schema: Schema, rows: &[Vec<Value>], options: WriterOptions) -> Vec<u8> {
+        let mut writer = Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, options).unwrap();
+        for row in rows {
+            writer.write_row(row).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn written_file_passes_header_validation() {
+        let bytes = write(schema(), &rows(3), WriterOptions::default());
+        let header = SasHeaderBinary::new(&bytes[..8192]);
+
+        assert_eq!(header.validate_sas_file(), Ok(()));
+        assert_eq!(header.get_a1(), 4);
+        assert_eq!(header.get_a2(), 4);
+        assert_eq!(header.get_sas_filename_from_header(), "PRODUCTS");
+        assert_eq!(header.get_page_size_from_header(), 4096);
+    }
+
+    #[test]
+    fn can_round_trip_rows_across_pages() {
+        let rows = rows(1000);
+        let created = NaiveDate::from_ymd_opt(2024, 5, 6)
+            .unwrap()
+            .and_hms_opt(7, 8, 9)
+            .unwrap();
+        let options = WriterOptionsBuilder::default()
+            .created(created)
+            .modified(created)
+            .encoding(Encoding::Windows1252)
+            .build()
+            .unwrap();
+        let bytes = write(schema(), &rows, options);
+
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.schema(), &schema());
+        assert_eq!(reader.properties().row_count, 1000);
+        assert_eq!(reader.properties().created, Some(created));
+        assert_eq!(reader.properties().encoding, Encoding::Windows1252);
+        assert!(reader.properties().page_count > 2);
+
+        let read: Vec<Vec<Value>> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, rows);
+    }
+
+    #[test]
+    fn can_round_trip_many_columns() {
+        let columns: Vec<Column> = (0..600)
+            .map(|i| Column::numeric(&format!("a_long_column_name_{}", i)))
+            .collect();
+        let schema = Schema::new("WIDE", columns);
+        let row: Vec<Value> = (0..600).map(|i| Value::Number(i as f64)).collect();
+        let bytes = write(
+            schema.clone(),
+            std::slice::from_ref(&row),
+            WriterOptions::default(),
+        );
+
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.schema(), &schema);
+        assert_eq!(reader.read_row(), Ok(Some(row)));
+        assert_eq!(reader.read_row(), Ok(None));
+    }
+
+    #[test]
+    fn can_write_empty_dataset() {
+        let bytes = write(schema(), &[], WriterOptions::default());
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.properties().row_count, 0);
+        assert_eq!(reader.read_row(), Ok(None));
+    }
+
+    #[test]
+    fn page_size_grows_for_long_rows() {
+        let schema = Schema::new("LONG", vec![Column::character("text", 5000)]);
+        let row = vec![Value::from("x".repeat(5000))];
+        let bytes = write(schema, std::slice::from_ref(&row), WriterOptions::default());
+
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.properties().page_size, 6144);
+        assert_eq!(reader.read_row(), Ok(Some(row)));
+    }
+
+    #[test]
+    fn can_round_trip_rle_compressed_rows() {
+        let schema = Schema::new(
+            "PADDED",
+            vec![
+                Column::numeric("id"),
+                Column::character("name", 200),
+                Column::character("code", 3),
+            ],
+        );
+        let rows: Vec<Vec<Value>> = (0..2000)
+            .map(|i| {
+                vec![
+                    Value::Number(i as f64),
+                    Value::String(format!("customer {}", i)),
+                    Value::from("abc"),
+                ]
+            })
+            .collect();
+
+        let plain = write(schema.clone(), &rows, WriterOptions::default());
+        let options = WriterOptionsBuilder::default()
+            .compression(Compression::Rle)
+            .build()
+            .unwrap();
+        let compressed = write(schema.clone(), &rows, options);
+        assert!(compressed.len() * 3 < plain.len());
+
+        let mut reader = Sas7bdatReader::new(Cursor::new(compressed)).unwrap();
+        assert_eq!(reader.properties().compression, Compression::Rle);
+        assert_eq!(reader.schema(), &schema);
+        let read: Vec<Vec<Value>> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, rows);
+    }
+
+    #[test]
+    fn can_round_trip_rows_that_do_not_compress() {
+        let schema = Schema::new("DENSE", vec![Column::numeric("x"), Column::numeric("y")]);
+        let rows: Vec<Vec<Value>> = (1..50)
+            .map(|i| {
+                vec![
+                    Value::Number(i as f64 / 7.0),
+                    Value::Number(-(i as f64) / 3.0),
+                ]
+            })
+            .collect();
+        let options = WriterOptionsBuilder::default()
+            .compression(Compression::Rle)
+            .build()
+            .unwrap();
+        let bytes = write(schema, &rows, options);
+
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes)).unwrap();
+        let read: Vec<Vec<Value>> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, rows);
+    }
+
+    #[test]
+    fn can_round_trip_raw_bytes() {
+        use crate::sas::{InvalidBytes, ReaderOptions};
+
+        let schema = Schema::new("RAW", vec![Column::character("text", 8)]);
+        let row = vec![Value::Bytes(b"ab\xff\xfecd".to_vec())];
+        let bytes = write(schema, std::slice::from_ref(&row), WriterOptions::default());
+
+        let options = ReaderOptions {
+            invalid_bytes: InvalidBytes::Raw,
+            ..ReaderOptions::default()
+        };
+        let mut reader = Sas7bdatReader::with_options(Cursor::new(bytes), options).unwrap();
+        assert_eq!(reader.read_row(), Ok(Some(row)));
+    }
+
+    #[test]
+    fn can_write_more_rows_than_a_page_header_counts() {
+        let schema = Schema::new("NARROW", vec![Column::numeric("x")]);
+        let rows: Vec<Vec<Value>> = (0..70_000).map(|i| vec![Value::Number(i as f64)]).collect();
+        let options = WriterOptionsBuilder::default()
+            .page_size(1_usize << 20)
+            .build()
+            .unwrap();
+        let bytes = write(schema, &rows, options);
+
+        let mut reader = Sas7bdatReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.properties().page_count, 3);
+        let read: Vec<Vec<Value>> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, rows);
+    }
+
+    #[test]
+    fn cannot_write_pages_over_the_size_limit() {
+        let options = WriterOptionsBuilder::default()
+            .page_size(1_usize << 27)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema(), options),
+            Err(Error::InvalidSchema(_))
+        ));
+    }
+
+    #[test]
+    fn cannot_write_string_longer_than_column() {
+        let mut writer =
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema(), WriterOptions::default())
+                .unwrap();
+        let row = vec![
+            Value::Number(1.0),
+            Value::from("a name that is too long"),
+            Value::Number(2.0),
+        ];
+        assert!(matches!(writer.write_row(&row), Err(Error::InvalidRow(_))));
+    }
+
+    #[test]
+    fn cannot_write_mismatched_row() {
+        let mut writer =
+            Sas7bdatWriter::new(Cursor::new(Vec::new()), schema(), WriterOptions::default())
+                .unwrap();
+        assert!(writer.write_row(&[Value::Number(1.0)]).is_err());
+        let row = vec![Value::from("1"), Value::from("name"), Value::Number(2.0)];
+        assert!(writer.write_row(&row).is_err());
+    }
+
+    #[test]
+    fn cannot_write_invalid_schema() {
+        let schema = Schema::new("", vec![Column::numeric("x")]);
+        let result = Sas7bdatWriter::new(Cursor::new(Vec::new()), schema, WriterOptions::default());
+        assert!(matches!(result, Err(Error::InvalidSchema(_))));
+    }
+}
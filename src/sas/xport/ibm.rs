@@ -0,0 +1,179 @@
+use crate::sas::errors::Error;
+use crate::sas::{MissingValue, Value};
+
+/// Convert an IBM System/360 double (sign bit, base-16 exponent in excess
+/// 64, 56-bit fraction) to an IEEE double.
+pub fn ibm_to_f64(bytes: [u8; 8]) -> f64 {
+    let sign = if bytes[0] & 0x80 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bytes[0] & 0x7F) as i32 - 64;
+    let mut fraction_bytes = bytes;
+    fraction_bytes[0] = 0;
+    let fraction = u64::from_be_bytes(fraction_bytes);
+
+    if fraction == 0 {
+        return 0.0;
+    }
+    sign * fraction as f64 * 2_f64.powi(4 * exponent - 56)
+}
+
+/// Convert an IEEE double to IBM format. The 53-bit IEEE mantissa fits the
+/// 56-bit IBM fraction exactly; values too small for IBM become zero.
+pub fn f64_to_ibm(value: f64) -> Result<[u8; 8], Error> {
+    if !value.is_finite() {
+        return Err(Error::InvalidRow(format!(
+            "{} cannot be stored in a transport file",
+            value
+        )));
+    }
+    let bits = value.to_bits();
+    let biased = ((bits >> 52) & 0x7FF) as i32;
+    if biased == 0 {
+        return Ok([0; 8]);
+    }
+
+    // value = mantissa * 2^(exponent - 52) with the mantissa in [2^52, 2^53).
+    // Choosing the base-16 exponent as floor(exponent / 4) + 1 leaves a
+    // fraction in [1/16, 1), i.e. the mantissa shifted left by up to 3 bits.
+    let exponent = biased - 1023;
+    let mantissa = (bits & 0x000F_FFFF_FFFF_FFFF) | 1 << 52;
+    let ibm_exponent = exponent.div_euclid(4) + 1 + 64;
+    if ibm_exponent > 127 {
+        return Err(Error::InvalidRow(format!(
+            "{} is too large for an IBM double",
+            value
+        )));
+    }
+    if ibm_exponent < 0 {
+        return Ok([0; 8]);
+    }
+
+    let fraction = mantissa << exponent.rem_euclid(4);
+    let mut bytes = fraction.to_be_bytes();
+    bytes[0] = (ibm_exponent as u8) | ((bits >> 56) as u8 & 0x80);
+    Ok(bytes)
+}
+
+/// Encode a numeric cell, keeping the leading bytes when the cell is
+/// narrower than 8 bytes. NaN is stored as the ordinary missing value.
+pub fn encode_numeric(value: &Value, out: &mut [u8]) -> Result<(), Error> {
+    let bytes = match value {
+        Value::Number(number) if number.is_nan() => missing_bytes(MissingValue::System),
+        Value::Number(number) => f64_to_ibm(*number)?,
+        Value::Missing(missing) => missing_bytes(*missing),
+        Value::String(_) | Value::Bytes(_) => {
+            return Err(Error::InvalidRow(format!(
+                "Cannot store string {:?} in a numeric column",
+                value.to_text().unwrap_or_default()
+            )))
+        }
+    };
+    out.copy_from_slice(&bytes[..out.len()]);
+    Ok(())
+}
+
+fn missing_bytes(missing: MissingValue) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes[0] = match missing {
+        MissingValue::System => b'.',
+        MissingValue::Special(tag) => tag as u8,
+    };
+    bytes
+}
+
+/// Decode a numeric cell of an XPORT file. Cells narrower than 8 bytes
+/// hold the leading bytes of the double. Missing values have `.`, `_` or a
+/// letter in the first byte and zeros after it.
+pub fn decode_numeric(bytes: &[u8]) -> Result<Value, Error> {
+    let width = bytes.len();
+    if !(2..=8).contains(&width) {
+        return Err(Error::InvalidRow(format!(
+            "XPORT numeric cells must be 2 to 8 bytes wide, got {}",
+            width
+        )));
+    }
+
+    if bytes[1..].iter().all(|&b| b == 0) {
+        match bytes[0] {
+            b'.' => return Ok(Value::Missing(MissingValue::System)),
+            tag @ (b'_' | b'A'..=b'Z') => {
+                return Ok(Value::Missing(MissingValue::Special(tag as char)))
+            }
+            _ => {}
+        }
+    }
+
+    let mut buffer = [0_u8; 8];
+    buffer[..width].copy_from_slice(bytes);
+    Ok(Value::Number(ibm_to_f64(buffer)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_convert_ibm_to_f64() {
+        assert_eq!(ibm_to_f64([0x41, 0x10, 0, 0, 0, 0, 0, 0]), 1.0);
+        assert_eq!(ibm_to_f64([0xC1, 0x10, 0, 0, 0, 0, 0, 0]), -1.0);
+        assert_eq!(ibm_to_f64([0x42, 0x64, 0, 0, 0, 0, 0, 0]), 100.0);
+        assert_eq!(ibm_to_f64([0x40, 0x80, 0, 0, 0, 0, 0, 0]), 0.5);
+        assert_eq!(ibm_to_f64([0; 8]), 0.0);
+    }
+
+    #[test]
+    fn can_convert_fractional_ibm_value() {
+        // 0.1 has no exact representation in either format
+        let value = ibm_to_f64([0x40, 0x19, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A]);
+        assert!((value - 0.1).abs() < 1e-16);
+    }
+
+    #[test]
+    fn can_convert_f64_to_ibm() {
+        assert_eq!(f64_to_ibm(1.0), Ok([0x41, 0x10, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(f64_to_ibm(-1.0), Ok([0xC1, 0x10, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(f64_to_ibm(100.0), Ok([0x42, 0x64, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(f64_to_ibm(0.5), Ok([0x40, 0x80, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(f64_to_ibm(0.0), Ok([0; 8]));
+        assert_eq!(f64_to_ibm(1e-300), Ok([0; 8]));
+        assert!(f64_to_ibm(1e300).is_err());
+        assert!(f64_to_ibm(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn can_round_trip_through_ibm() {
+        for value in [0.1, -3.75, 1e-70, 123_456_789.123, 7.2e75, f64::EPSILON] {
+            assert_eq!(ibm_to_f64(f64_to_ibm(value).unwrap()), value);
+        }
+    }
+
+    #[test]
+    fn can_encode_missing_and_short_numeric() {
+        let mut cell = [0xFF; 3];
+        encode_numeric(&Value::Missing(MissingValue::Special('B')), &mut cell).unwrap();
+        assert_eq!(cell, [b'B', 0, 0]);
+
+        encode_numeric(&Value::Number(f64::NAN), &mut cell).unwrap();
+        assert_eq!(cell, [b'.', 0, 0]);
+
+        encode_numeric(&Value::Number(100.0), &mut cell).unwrap();
+        assert_eq!(decode_numeric(&cell), Ok(Value::Number(100.0)));
+    }
+
+    #[test]
+    fn can_decode_missing_values() {
+        assert_eq!(
+            decode_numeric(&[b'.', 0, 0, 0, 0, 0, 0, 0]),
+            Ok(Value::Missing(MissingValue::System))
+        );
+        assert_eq!(
+            decode_numeric(&[b'C', 0, 0]),
+            Ok(Value::Missing(MissingValue::Special('C')))
+        );
+    }
+
+    #[test]
+    fn can_decode_truncated_numeric() {
+        assert_eq!(decode_numeric(&[0x42, 0x64, 0]), Ok(Value::Number(100.0)));
+        assert!(decode_numeric(&[0x42]).is_err());
+    }
+}
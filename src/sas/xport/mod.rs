@@ -0,0 +1,8 @@
+pub mod ibm;
+pub mod reader;
+pub mod record;
+pub mod writer;
+
+pub use reader::{XportLibrary, XportMember, XportReader};
+pub use record::{Namestr, XportVersion};
+pub use writer::{check_limits, XportWriter, XportWriterOptions, XportWriterOptionsBuilder};
@@ -0,0 +1,709 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+
+use crate::sas::errors::Error;
+use crate::sas::schema::{Row, Schema};
+use crate::sas::xport::ibm::decode_numeric;
+use crate::sas::xport::record::{
+    decode_text, expect_header, header_kind, parse_datetime, parse_leading_number, parse_number,
+    Namestr, XportVersion, NAMESTR_LEN, RECORD_LEN,
+};
+use crate::sas::{Column, ColumnFormat, ColumnType, Value};
+use crate::traits::{DatasetReader, ReadSeek};
+
+const READ_CHUNK: u64 = 8192;
+
+/// Library-level facts from the first header records of a transport file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XportLibrary {
+    pub version: XportVersion,
+    pub sas_version: String,
+    pub os: String,
+    pub created: Option<NaiveDateTime>,
+    pub modified: Option<NaiveDateTime>,
+}
+
+/// One dataset of a transport file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XportMember {
+    pub schema: Schema,
+    pub dataset_type: String,
+    pub sas_version: String,
+    pub os: String,
+    pub created: Option<NaiveDateTime>,
+    pub modified: Option<NaiveDateTime>,
+    pub variables: Vec<Namestr>,
+}
+
+/// Buffers the source so header records can be peeked at before they are
+/// consumed, and keeps track of the position within the current record.
+struct Records<R: ReadSeek> {
+    source: R,
+    buffer: Vec<u8>,
+    start: usize,
+    offset: usize,
+}
+
+impl<R: ReadSeek> Records<R> {
+    /// Return up to `len` unconsumed bytes; fewer only at the end of the file.
+    fn fill(&mut self, len: usize) -> Result<&[u8], Error> {
+        while self.buffer.len() - self.start < len {
+            if self.start > 0 {
+                self.buffer.drain(..self.start);
+                self.start = 0;
+            }
+            let read = (&mut self.source)
+                .take(READ_CHUNK)
+                .read_to_end(&mut self.buffer)?;
+            if read == 0 {
+                break;
+            }
+        }
+        let end = self.buffer.len().min(self.start + len);
+        Ok(&self.buffer[self.start..end])
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+        self.offset += len;
+    }
+
+    /// Bytes left before the next record boundary.
+    fn padding(&self) -> usize {
+        (RECORD_LEN - self.offset % RECORD_LEN) % RECORD_LEN
+    }
+
+    /// Read exactly `len` bytes of a header section.
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let bytes = self.fill(len)?.to_vec();
+        if bytes.len() < len {
+            return Err(Error::InvalidHeader(format!(
+                "File ends inside a header section at offset {}",
+                self.offset
+            )));
+        }
+        self.consume(len);
+        Ok(bytes)
+    }
+
+    /// Skip the blank padding up to the next record boundary.
+    fn skip_padding(&mut self) -> Result<(), Error> {
+        let padding = self.padding();
+        let available = self.fill(padding)?.len();
+        self.consume(available);
+        Ok(())
+    }
+
+    fn read_record(&mut self) -> Result<Vec<u8>, Error> {
+        self.read_bytes(RECORD_LEN)
+    }
+
+    fn is_at_end(&mut self) -> Result<bool, Error> {
+        Ok(self.fill(1)?.is_empty())
+    }
+}
+
+/// Where a variable's cells sit within a row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ColumnSpan {
+    offset: usize,
+    length: usize,
+    column_type: ColumnType,
+}
+
+/// Reads the members of a SAS transport (XPORT) file, one after another.
+pub struct XportReader<R: ReadSeek> {
+    records: Records<R>,
+    library: XportLibrary,
+    member: XportMember,
+    spans: Vec<ColumnSpan>,
+    row_length: usize,
+    member_done: bool,
+}
+
+impl XportReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        XportReader::new(BufReader::new(file))
+    }
+}
+
+impl<R: ReadSeek> XportReader<R> {
+    /// Read the library header and the header of the first member.
+    pub fn new(source: R) -> Result<Self, Error> {
+        let mut records = Records {
+            source,
+            buffer: Vec::new(),
+            start: 0,
+            offset: 0,
+        };
+        let library = read_library(&mut records)?;
+        if records.is_at_end()? {
+            return Err(Error::InvalidHeader(
+                "Transport file has no members".to_string(),
+            ));
+        }
+
+        let member = read_member(&mut records, library.version)?;
+        let (spans, row_length) = column_spans(&member.variables);
+
+        Ok(XportReader {
+            records,
+            library,
+            member,
+            spans,
+            row_length,
+            member_done: false,
+        })
+    }
+
+    pub fn library(&self) -> &XportLibrary {
+        &self.library
+    }
+
+    pub fn member(&self) -> &XportMember {
+        &self.member
+    }
+
+    /// Skip the rest of the current member and read the header of the next
+    /// one. Returns `false` once there are no more members.
+    pub fn next_member(&mut self) -> Result<bool, Error> {
+        while self.read_row()?.is_some() {}
+        if self.records.is_at_end()? {
+            return Ok(false);
+        }
+        self.start_member()?;
+        Ok(true)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.records.source
+    }
+
+    fn start_member(&mut self) -> Result<(), Error> {
+        self.member = read_member(&mut self.records, self.library.version)?;
+        (self.spans, self.row_length) = column_spans(&self.member.variables);
+        self.member_done = false;
+        Ok(())
+    }
+
+    /// The member ends where the rest of the record is blank and the next
+    /// record is another member header or the end of the file.
+    fn at_member_end(&mut self) -> Result<bool, Error> {
+        let padding = self.records.padding();
+        let ahead = self.records.fill(padding + RECORD_LEN)?;
+        if !ahead[..padding.min(ahead.len())].iter().all(|&b| b == b' ') {
+            return Ok(false);
+        }
+        Ok(ahead.len() <= padding
+            || header_kind(&ahead[padding..])
+                .is_some_and(|kind| kind == self.library.version.member_header()))
+    }
+}
+
+impl<R: ReadSeek> DatasetReader for XportReader<R> {
+    fn schema(&self) -> &Schema {
+        &self.member.schema
+    }
+
+    fn file_info(&self) -> Vec<(String, String)> {
+        let datetime =
+            |value: Option<NaiveDateTime>| value.map(|v| v.to_string()).unwrap_or_default();
+        let info = [
+            ("format", "xport".to_string()),
+            ("version", format!("{:?}", self.library.version)),
+            ("sas_version", self.member.sas_version.clone()),
+            ("os", self.member.os.clone()),
+            ("created", datetime(self.member.created)),
+            ("modified", datetime(self.member.modified)),
+        ];
+        info.into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        if self.member_done {
+            return Ok(None);
+        }
+        if self.row_length == 0 || self.at_member_end()? {
+            self.records.skip_padding()?;
+            self.member_done = true;
+            return Ok(None);
+        }
+
+        let bytes = self.records.fill(self.row_length)?;
+        if bytes.len() < self.row_length {
+            return Err(Error::InvalidRow(format!(
+                "File ends inside a row of {} bytes",
+                self.row_length
+            )));
+        }
+        let row = decode_row(&self.spans, bytes)?;
+        self.records.consume(self.row_length);
+        Ok(Some(row))
+    }
+}
+
+fn read_library<R: ReadSeek>(records: &mut Records<R>) -> Result<XportLibrary, Error> {
+    let version = XportVersion::from_library_header(&records.read_record()?)?;
+
+    let first = records.read_record()?;
+    if !first.starts_with(b"SAS     SAS     SASLIB  ") {
+        return Err(Error::InvalidHeader(
+            "Missing SASLIB record after the library header".to_string(),
+        ));
+    }
+    let second = records.read_record()?;
+
+    Ok(XportLibrary {
+        version,
+        sas_version: decode_text(&first[24..32]),
+        os: decode_text(&first[32..40]),
+        created: parse_datetime(&first[64..80]),
+        modified: parse_datetime(&second[0..16]),
+    })
+}
+
+fn read_member<R: ReadSeek>(
+    records: &mut Records<R>,
+    version: XportVersion,
+) -> Result<XportMember, Error> {
+    let header = records.read_record()?;
+    let digits = expect_header(&header, &[version.member_header()])?;
+    let namestr_len = parse_number(&digits[26..30])?;
+    if namestr_len != NAMESTR_LEN && namestr_len != 136 {
+        return Err(Error::InvalidHeader(format!(
+            "Unexpected NAMESTR length {}",
+            namestr_len
+        )));
+    }
+    expect_header(&records.read_record()?, &[version.descriptor_header()])?;
+
+    // The dataset name is wider in version 8, which shifts the fields after it.
+    let first = records.read_record()?;
+    let name_end = 8 + version.dataset_name_len();
+    if !first.starts_with(b"SAS     ") || &first[name_end..name_end + 8] != b"SASDATA " {
+        return Err(Error::InvalidHeader(
+            "Missing SASDATA record after the member header".to_string(),
+        ));
+    }
+    let second = records.read_record()?;
+
+    let digits = expect_header(&records.read_record()?, &[version.namestr_header()])?.to_vec();
+    let count = match version {
+        XportVersion::V5 => parse_number(&digits[6..10])?,
+        XportVersion::V8 => parse_number(&digits[6..12])?,
+    };
+    let mut variables = Vec::with_capacity(count);
+    for _ in 0..count {
+        variables.push(Namestr::parse(&records.read_bytes(namestr_len)?, version)?);
+    }
+    records.skip_padding()?;
+
+    loop {
+        let header = records.read_record()?;
+        match header_kind(&header).as_deref() {
+            Some("LABELV8") => read_long_labels(records, &header, &mut variables, false)?,
+            Some("LABELV9") => read_long_labels(records, &header, &mut variables, true)?,
+            _ => {
+                expect_header(&header, &[version.obs_header()])?;
+                break;
+            }
+        }
+    }
+
+    let columns = variables
+        .iter()
+        .map(|variable| Column {
+            name: variable.name.clone(),
+            label: variable.label.clone(),
+            column_type: variable.column_type,
+            length: variable.length,
+            format: variable.format.clone(),
+        })
+        .collect();
+    let mut schema = Schema::new(&decode_text(&first[8..name_end]), columns);
+    schema.label = decode_text(&second[32..72]);
+
+    Ok(XportMember {
+        schema,
+        dataset_type: decode_text(&second[72..80]),
+        sas_version: decode_text(&first[name_end + 8..name_end + 16]),
+        os: decode_text(&first[name_end + 16..name_end + 24]),
+        created: parse_datetime(&first[64..80]),
+        modified: parse_datetime(&second[0..16]),
+        variables,
+    })
+}
+
+/// Read a LABELV8 section, which holds labels longer than 40 characters, or
+/// a LABELV9 section, which also holds formats and informats too long for the
+/// NAMESTR. Each entry starts with the variable number and field lengths.
+fn read_long_labels<R: ReadSeek>(
+    records: &mut Records<R>,
+    header: &[u8],
+    variables: &mut [Namestr],
+    with_formats: bool,
+) -> Result<(), Error> {
+    let count = parse_leading_number(&header[48..])?;
+    let field_count = if with_formats { 5 } else { 3 };
+
+    for _ in 0..count {
+        let fields = records.read_bytes(2 * field_count)?;
+        let field = |index: usize| u16::from_be_bytes([fields[2 * index], fields[2 * index + 1]]);
+        let number = field(0);
+        let lengths: Vec<usize> = (1..field_count).map(|i| field(i) as usize).collect();
+        let text = records.read_bytes(lengths.iter().sum())?;
+
+        let mut parts = Vec::with_capacity(lengths.len());
+        let mut start = 0;
+        for length in lengths {
+            parts.push(decode_text(&text[start..start + length]));
+            start += length;
+        }
+
+        let variable = variables
+            .iter_mut()
+            .find(|variable| variable.number == number)
+            .ok_or_else(|| {
+                Error::InvalidHeader(format!("Long label for unknown variable {}", number))
+            })?;
+        variable.label = parts[1].clone();
+        if with_formats {
+            if !parts[2].is_empty() {
+                variable.format = ColumnFormat::parse(&parts[2]).map_err(Error::InvalidHeader)?;
+            }
+            if !parts[3].is_empty() {
+                variable.informat = ColumnFormat::parse(&parts[3]).map_err(Error::InvalidHeader)?;
+            }
+        }
+    }
+    records.skip_padding()
+}
+
+fn column_spans(variables: &[Namestr]) -> (Vec<ColumnSpan>, usize) {
+    let spans: Vec<ColumnSpan> = variables
+        .iter()
+        .map(|variable| ColumnSpan {
+            offset: variable.position,
+            length: variable.length,
+            column_type: variable.column_type,
+        })
+        .collect();
+    let row_length = spans
+        .iter()
+        .map(|span| span.offset + span.length)
+        .max()
+        .unwrap_or(0);
+    (spans, row_length)
+}
+
+fn decode_row(spans: &[ColumnSpan], bytes: &[u8]) -> Result<Row, Error> {
+    spans
+        .iter()
+        .map(|span| {
+            let cell = bytes
+                .get(span.offset..span.offset + span.length)
+                .ok_or(Error::OutOfBounds(span.offset, span.length))?;
+            match span.column_type {
+                ColumnType::Numeric => decode_numeric(cell),
+                ColumnType::Character => Ok(Value::String(decode_text(cell))),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::sas::{ColumnFormat, MissingValue};
+
+    const ONE: [u8; 8] = [0x41, 0x10, 0, 0, 0, 0, 0, 0];
+    const HUNDRED: [u8; 8] = [0x42, 0x64, 0, 0, 0, 0, 0, 0];
+
+    fn pad(bytes: &mut Vec<u8>) {
+        while !bytes.len().is_multiple_of(RECORD_LEN) {
+            bytes.push(b' ');
+        }
+    }
+
+    fn push_record(bytes: &mut Vec<u8>, text: &str) {
+        bytes.extend_from_slice(text.as_bytes());
+        pad(bytes);
+    }
+
+    fn push_header(bytes: &mut Vec<u8>, kind: &str, digits: &str) {
+        push_record(
+            bytes,
+            &format!(
+                "HEADER RECORD*******{:<8}HEADER RECORD!!!!!!!{:0<30}",
+                kind, digits
+            ),
+        );
+    }
+
+    fn namestr(column_type: u16, length: u16, number: u16, name: &str, position: u32) -> Vec<u8> {
+        let mut bytes = vec![0_u8; NAMESTR_LEN];
+        bytes[0..2].copy_from_slice(&column_type.to_be_bytes());
+        bytes[4..6].copy_from_slice(&length.to_be_bytes());
+        bytes[6..8].copy_from_slice(&number.to_be_bytes());
+        bytes[8..16].copy_from_slice(format!("{:<8}", name).as_bytes());
+        bytes[16..56].copy_from_slice(format!("{:<40}", format!("{} label", name)).as_bytes());
+        bytes[56..64].copy_from_slice(b"        ");
+        bytes[72..80].copy_from_slice(b"        ");
+        bytes[84..88].copy_from_slice(&position.to_be_bytes());
+        bytes
+    }
+
+    fn push_member(bytes: &mut Vec<u8>, name: &str, variables: &[Vec<u8>], rows: &[u8]) {
+        push_header(bytes, "MEMBER", "00000000000000000160000000014");
+        push_header(bytes, "DSCRPTR", "");
+        push_record(
+            bytes,
+            &format!(
+                "SAS     {:<8}SASDATA 9.4     X64_10PR{:24}16FEB11:10:07:55",
+                name, ""
+            ),
+        );
+        push_record(
+            bytes,
+            &format!("16FEB11:10:07:55{:16}{:<40}DATA    ", "", "Test data"),
+        );
+        push_header(bytes, "NAMESTR", &format!("000000{:04}", variables.len()));
+        for variable in variables {
+            bytes.extend_from_slice(variable);
+        }
+        pad(bytes);
+        push_header(bytes, "OBS", "");
+        bytes.extend_from_slice(rows);
+        pad(bytes);
+    }
+
+    fn library() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_header(&mut bytes, "LIBRARY", "");
+        push_record(
+            &mut bytes,
+            &format!(
+                "SAS     SAS     SASLIB  9.4     X64_10PR{:24}16FEB11:10:07:55",
+                ""
+            ),
+        );
+        push_record(&mut bytes, "16FEB11:10:07:55");
+        bytes
+    }
+
+    fn two_members() -> Vec<u8> {
+        let mut bytes = library();
+
+        let mut rows = Vec::new();
+        for (number, name) in [(ONE, "Ann   "), (HUNDRED, "Bob   ")] {
+            rows.extend_from_slice(&number);
+            rows.extend_from_slice(name.as_bytes());
+        }
+        rows.extend_from_slice(&[b'.', 0, 0, 0, 0, 0, 0, 0]);
+        rows.extend_from_slice(b"      ");
+        let variables = [namestr(1, 8, 1, "X", 0), namestr(2, 6, 2, "NAME", 8)];
+        push_member(&mut bytes, "FIRST", &variables, &rows);
+
+        let mut rows = Vec::new();
+        rows.extend_from_slice(&[b'Z', 0, 0]);
+        rows.extend_from_slice(&[0x41, 0x10, 0]);
+        push_member(&mut bytes, "SECOND", &[namestr(1, 3, 1, "Y", 0)], &rows);
+        bytes
+    }
+
+    fn v8_file() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_header(&mut bytes, "LIBV8", "");
+        push_record(
+            &mut bytes,
+            &format!(
+                "SAS     SAS     SASLIB  9.4     X64_10PR{:24}16FEB11:10:07:55",
+                ""
+            ),
+        );
+        push_record(&mut bytes, "16FEB11:10:07:55");
+
+        push_header(&mut bytes, "MEMBV8", "00000000000000000160000000014");
+        push_header(&mut bytes, "DSCPTV8", "");
+        push_record(
+            &mut bytes,
+            &format!(
+                "SAS     {:<32}SASDATA 9.4     X64_10PR16FEB11:10:07:55",
+                "ADVERSE_EVENTS_LISTING"
+            ),
+        );
+        push_record(
+            &mut bytes,
+            &format!("16FEB11:10:07:55{:16}{:<40}DATA    ", "", "Adverse events"),
+        );
+        push_header(&mut bytes, "NAMSTV8", "000000000002");
+        let mut first = namestr(1, 8, 1, "ONSETDAT", 0);
+        first[88..120].copy_from_slice(format!("{:<32}", "ONSET_DATE_OF_EVENT").as_bytes());
+        bytes.extend_from_slice(&first);
+        bytes.extend_from_slice(&namestr(2, 4, 2, "TERM", 8));
+        pad(&mut bytes);
+
+        let long_label = "Date the adverse event was first reported by the site";
+        push_record(
+            &mut bytes,
+            "HEADER RECORD*******LABELV8 HEADER RECORD!!!!!!!1",
+        );
+        bytes.extend_from_slice(&1_u16.to_be_bytes());
+        bytes.extend_from_slice(&19_u16.to_be_bytes());
+        bytes.extend_from_slice(&(long_label.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(b"ONSET_DATE_OF_EVENT");
+        bytes.extend_from_slice(long_label.as_bytes());
+        pad(&mut bytes);
+
+        push_record(
+            &mut bytes,
+            "HEADER RECORD*******LABELV9 HEADER RECORD!!!!!!!1",
+        );
+        for field in [2_u16, 4, 4, 22, 0] {
+            bytes.extend_from_slice(&field.to_be_bytes());
+        }
+        bytes.extend_from_slice(b"TERMTerm$VERY_LONG_FORMAT_NAME.");
+        pad(&mut bytes);
+
+        push_header(&mut bytes, "OBSV8", "");
+        bytes.extend_from_slice(&ONE);
+        bytes.extend_from_slice(b"Rash");
+        pad(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn can_read_library() {
+        let reader = XportReader::new(Cursor::new(two_members())).unwrap();
+        let library = reader.library();
+
+        assert_eq!(library.sas_version, "9.4");
+        assert_eq!(library.os, "X64_10PR");
+        assert_eq!(library.created.unwrap().to_string(), "2011-02-16 10:07:55");
+    }
+
+    #[test]
+    fn can_read_member_schema() {
+        let reader = XportReader::new(Cursor::new(two_members())).unwrap();
+        let schema = reader.schema();
+
+        assert_eq!(schema.name, "FIRST");
+        assert_eq!(schema.label, "Test data");
+        assert_eq!(reader.member().dataset_type, "DATA");
+        assert_eq!(schema.column_count(), 2);
+        assert_eq!(schema.columns[1].name, "NAME");
+        assert_eq!(schema.columns[1].label, "NAME label");
+        assert_eq!(schema.columns[1].column_type, ColumnType::Character);
+        assert_eq!(schema.columns[1].length, 6);
+        assert_eq!(schema.columns[1].format, ColumnFormat::default());
+    }
+
+    #[test]
+    fn can_read_rows_of_each_member() {
+        let mut reader = XportReader::new(Cursor::new(two_members())).unwrap();
+        let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Number(1.0), Value::String("Ann".to_string())],
+                vec![Value::Number(100.0), Value::String("Bob".to_string())],
+                vec![
+                    Value::Missing(MissingValue::System),
+                    Value::String(String::new())
+                ],
+            ]
+        );
+
+        assert!(reader.next_member().unwrap());
+        assert_eq!(reader.schema().name, "SECOND");
+        let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Missing(MissingValue::Special('Z'))],
+                vec![Value::Number(1.0)],
+            ]
+        );
+
+        assert!(!reader.next_member().unwrap());
+    }
+
+    #[test]
+    fn can_skip_unread_rows() {
+        let mut reader = XportReader::new(Cursor::new(two_members())).unwrap();
+        assert!(reader.next_member().unwrap());
+        assert_eq!(reader.rows().count(), 2);
+    }
+
+    #[test]
+    fn can_read_row_that_fills_a_record() {
+        let mut bytes = library();
+        let rows: Vec<u8> = [ONE; 20].concat();
+        push_member(&mut bytes, "EXACT", &[namestr(1, 8, 1, "X", 0)], &rows);
+
+        let mut reader = XportReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.rows().count(), 20);
+    }
+
+    #[test]
+    fn can_read_v8_member() {
+        let mut reader = XportReader::new(Cursor::new(v8_file())).unwrap();
+        assert_eq!(reader.library().version, XportVersion::V8);
+        assert_eq!(reader.member().os, "X64_10PR");
+
+        let schema = reader.schema().clone();
+        assert_eq!(schema.name, "ADVERSE_EVENTS_LISTING");
+        assert_eq!(schema.label, "Adverse events");
+        assert_eq!(schema.columns[0].name, "ONSET_DATE_OF_EVENT");
+        assert_eq!(
+            schema.columns[0].label,
+            "Date the adverse event was first reported by the site"
+        );
+        assert_eq!(schema.columns[1].label, "Term");
+        assert_eq!(
+            schema.columns[1].format,
+            ColumnFormat::new("$VERY_LONG_FORMAT_NAME", 0, 0)
+        );
+
+        let rows: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            rows,
+            vec![vec![Value::Number(1.0), Value::String("Rash".to_string())]]
+        );
+        assert!(!reader.next_member().unwrap());
+    }
+
+    #[test]
+    fn can_detect_v5_file() {
+        let reader = XportReader::new(Cursor::new(two_members())).unwrap();
+        assert_eq!(reader.library().version, XportVersion::V5);
+    }
+
+    #[test]
+    fn cannot_read_non_xport_file() {
+        let bytes = std::fs::read("test/hadley.sas7bdat").unwrap();
+        assert!(matches!(
+            XportReader::new(Cursor::new(bytes)),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn cannot_read_truncated_row() {
+        let mut bytes = library();
+        let variables = [namestr(2, 100, 1, "TEXT", 0)];
+        push_member(&mut bytes, "SHORT", &variables, b"abc");
+        bytes.truncate(bytes.len() - RECORD_LEN + 3);
+
+        let mut reader = XportReader::new(Cursor::new(bytes)).unwrap();
+        assert!(matches!(reader.read_row(), Err(Error::InvalidRow(_))));
+    }
+}
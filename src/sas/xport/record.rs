@@ -0,0 +1,381 @@
+use chrono::NaiveDateTime;
+
+use crate::sas::errors::Error;
+use crate::sas::{Column, ColumnFormat, ColumnType, Encoding};
+
+/// XPORT files are a sequence of 80-byte card images.
+pub const RECORD_LEN: usize = 80;
+
+/// Length of a NAMESTR record on most hosts; VAX/VMS files use 136.
+pub const NAMESTR_LEN: usize = 140;
+
+const HEADER_PREFIX: &[u8] = b"HEADER RECORD*******";
+const HEADER_SUFFIX: &[u8] = b"HEADER RECORD!!!!!!!";
+const DATETIME_FORMAT: &str = "%d%b%y:%H:%M:%S";
+
+/// The transport format version. Version 8 (also written by SAS 9) allows
+/// 32-character dataset and variable names and labels longer than 40
+/// characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XportVersion {
+    V5,
+    V8,
+}
+
+impl XportVersion {
+    /// Detect the version from the first header record of a file.
+    pub fn from_library_header(record: &[u8]) -> Result<Self, Error> {
+        match header_kind(record).as_deref() {
+            Some("LIBRARY") => Ok(XportVersion::V5),
+            Some("LIBV8") => Ok(XportVersion::V8),
+            _ => Err(Error::InvalidHeader(
+                "File does not start with a transport library header".to_string(),
+            )),
+        }
+    }
+
+    pub fn member_header(self) -> &'static str {
+        match self {
+            XportVersion::V5 => "MEMBER",
+            XportVersion::V8 => "MEMBV8",
+        }
+    }
+
+    pub fn descriptor_header(self) -> &'static str {
+        match self {
+            XportVersion::V5 => "DSCRPTR",
+            XportVersion::V8 => "DSCPTV8",
+        }
+    }
+
+    pub fn namestr_header(self) -> &'static str {
+        match self {
+            XportVersion::V5 => "NAMESTR",
+            XportVersion::V8 => "NAMSTV8",
+        }
+    }
+
+    pub fn obs_header(self) -> &'static str {
+        match self {
+            XportVersion::V5 => "OBS",
+            XportVersion::V8 => "OBSV8",
+        }
+    }
+
+    /// Width of the dataset name in the member header.
+    pub fn dataset_name_len(self) -> usize {
+        match self {
+            XportVersion::V5 => 8,
+            XportVersion::V8 => 32,
+        }
+    }
+}
+
+/// The name of a header record, such as `LIBRARY` or `NAMESTR`, or `None`
+/// if the record is not a header.
+pub fn header_kind(record: &[u8]) -> Option<String> {
+    if record.len() < 48 || !record.starts_with(HEADER_PREFIX) || &record[28..48] != HEADER_SUFFIX {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&record[20..28])
+            .trim_end()
+            .to_string(),
+    )
+}
+
+/// Build a header record. `digits` fills the numeric field and is padded
+/// with zeros.
+pub fn header_record(kind: &str, digits: &str) -> Vec<u8> {
+    format!(
+        "HEADER RECORD*******{:<8}HEADER RECORD!!!!!!!{:0<30}  ",
+        kind, digits
+    )
+    .into_bytes()
+}
+
+/// Blank-pad text to a fixed-width field.
+pub fn pad_text(text: &str, width: usize) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.resize(width, b' ');
+    bytes
+}
+
+/// Encode text and blank-pad it to `width` bytes, failing if it is longer.
+pub fn encode_text(text: &str, width: usize, encoding: Encoding) -> Result<Vec<u8>, Error> {
+    let mut bytes = encoding.encode(text).map_err(Error::InvalidSchema)?;
+    if bytes.len() > width {
+        return Err(Error::InvalidSchema(format!(
+            "{:?} is {} bytes as {:?}, longer than {}",
+            text,
+            bytes.len(),
+            encoding,
+            width
+        )));
+    }
+    bytes.resize(width, b' ');
+    Ok(bytes)
+}
+
+/// Check that a record is the expected header and return its numeric field.
+pub fn expect_header<'a>(record: &'a [u8], kinds: &[&str]) -> Result<&'a [u8], Error> {
+    match header_kind(record) {
+        Some(kind) if kinds.contains(&kind.as_str()) => Ok(&record[48..]),
+        _ => Err(Error::InvalidHeader(format!(
+            "Expected {} header record, found {:?}",
+            kinds.join(" or "),
+            String::from_utf8_lossy(record)
+        ))),
+    }
+}
+
+/// Parse the leading run of ASCII digits, as in the count field of the
+/// LABELV8 and LABELV9 headers.
+pub fn parse_leading_number(bytes: &[u8]) -> Result<usize, Error> {
+    let start = bytes.iter().position(|&b| b != b' ').unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+    let end = bytes
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(bytes.len());
+    parse_number(&bytes[..end])
+}
+
+/// Parse a run of ASCII digits.
+pub fn parse_number(bytes: &[u8]) -> Result<usize, Error> {
+    let text = String::from_utf8_lossy(bytes);
+    text.trim()
+        .parse()
+        .map_err(|_| Error::InvalidHeader(format!("Expected a number, found {:?}", text)))
+}
+
+/// Text fields are blank padded. XPORT has no encoding field, so UTF-8 is
+/// tried first and Windows-1252 used otherwise.
+pub fn decode_text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .rposition(|&b| b != b' ' && b != 0)
+        .map_or(0, |i| i + 1);
+    let bytes = &bytes[..end];
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => Encoding::Windows1252.decode(bytes),
+    }
+}
+
+/// Parse a `ddMMMyy:hh:mm:ss` timestamp.
+pub fn parse_datetime(bytes: &[u8]) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(&decode_text(bytes), DATETIME_FORMAT).ok()
+}
+
+/// Format a timestamp the way header records store it, e.g. `16FEB11:10:07:55`.
+pub fn format_datetime(datetime: &NaiveDateTime) -> String {
+    datetime.format(DATETIME_FORMAT).to_string().to_uppercase()
+}
+
+/// A variable description in a member's NAMESTR section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namestr {
+    pub column_type: ColumnType,
+    pub length: usize,
+    pub number: u16,
+    pub name: String,
+    pub label: String,
+    pub format: ColumnFormat,
+    pub informat: ColumnFormat,
+    pub position: usize,
+}
+
+impl Namestr {
+    /// Parse a NAMESTR record. Integers are big-endian. Version 8 records
+    /// carry the full variable name after the fixed fields.
+    pub fn parse(bytes: &[u8], version: XportVersion) -> Result<Self, Error> {
+        if bytes.len() < 88 {
+            return Err(Error::InvalidHeader(format!(
+                "NAMESTR record is only {} bytes",
+                bytes.len()
+            )));
+        }
+        let short = |offset: usize| u16::from_be_bytes([bytes[offset], bytes[offset + 1]]);
+        let mut name = decode_text(&bytes[8..16]);
+        if version == XportVersion::V8 && bytes.len() >= 120 {
+            let long_name = decode_text(&bytes[88..120]);
+            if !long_name.is_empty() {
+                name = long_name;
+            }
+        }
+
+        Ok(Namestr {
+            column_type: ColumnType::from_u8(short(0) as u8).map_err(Error::InvalidHeader)?,
+            length: short(4) as usize,
+            number: short(6),
+            name,
+            label: decode_text(&bytes[16..56]),
+            format: ColumnFormat::new(&decode_text(&bytes[56..64]), short(64), short(66)),
+            informat: ColumnFormat::new(&decode_text(&bytes[72..80]), short(80), short(82)),
+            position: u32::from_be_bytes([bytes[84], bytes[85], bytes[86], bytes[87]]) as usize,
+        })
+    }
+
+    /// Describe a column stored at `position` within the row.
+    pub fn from_column(column: &Column, number: u16, position: usize) -> Self {
+        Namestr {
+            column_type: column.column_type,
+            length: column.length,
+            number,
+            name: column.name.clone(),
+            label: column.label.clone(),
+            format: column.format.clone(),
+            informat: ColumnFormat::default(),
+            position,
+        }
+    }
+
+    /// Serialize as a version 5 NAMESTR record, with text in `encoding`.
+    pub fn to_bytes(&self, encoding: Encoding) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![0_u8; NAMESTR_LEN];
+        let mut short = |offset: usize, value: u16| {
+            bytes[offset..offset + 2].copy_from_slice(&value.to_be_bytes())
+        };
+        short(0, self.column_type.to_u8() as u16);
+        short(4, self.length as u16);
+        short(6, self.number);
+        short(64, self.format.width);
+        short(66, self.format.decimals);
+        short(80, self.informat.width);
+        short(82, self.informat.decimals);
+
+        bytes[8..16].copy_from_slice(&encode_text(&self.name, 8, encoding)?);
+        bytes[16..56].copy_from_slice(&encode_text(&self.label, 40, encoding)?);
+        bytes[56..64].copy_from_slice(&encode_text(&self.format.name, 8, encoding)?);
+        bytes[72..80].copy_from_slice(&encode_text(&self.informat.name, 8, encoding)?);
+        bytes[84..88].copy_from_slice(&(self.position as u32).to_be_bytes());
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(text: &str) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize(RECORD_LEN, b' ');
+        bytes
+    }
+
+    #[test]
+    fn can_get_header_kind() {
+        let header = record(
+            "HEADER RECORD*******NAMESTR HEADER RECORD!!!!!!!000000000300000000000000000000",
+        );
+        assert_eq!(header_kind(&header), Some("NAMESTR".to_string()));
+        assert_eq!(header_kind(&record("SAS     SAS     SASLIB")), None);
+    }
+
+    #[test]
+    fn can_expect_header() {
+        let header = record(
+            "HEADER RECORD*******NAMESTR HEADER RECORD!!!!!!!000000000300000000000000000000",
+        );
+        let digits = expect_header(&header, &["NAMESTR"]).unwrap();
+        assert_eq!(parse_number(&digits[6..10]), Ok(3));
+        assert!(expect_header(&header, &["OBS"]).is_err());
+    }
+
+    #[test]
+    fn can_parse_leading_number() {
+        assert_eq!(parse_leading_number(b"12   "), Ok(12));
+        assert_eq!(parse_leading_number(b"   7  "), Ok(7));
+        assert!(parse_leading_number(b"    ").is_err());
+    }
+
+    #[test]
+    fn can_parse_datetime() {
+        let datetime = parse_datetime(b"16FEB11:10:07:55").unwrap();
+        assert_eq!(datetime.to_string(), "2011-02-16 10:07:55");
+        assert_eq!(parse_datetime(b"                "), None);
+    }
+
+    #[test]
+    fn can_decode_text() {
+        assert_eq!(decode_text(b"AGE     "), "AGE");
+        assert_eq!(decode_text(b"caf\xe9  "), "caf\u{e9}");
+    }
+
+    #[test]
+    fn can_parse_namestr() {
+        let mut bytes = vec![0_u8; NAMESTR_LEN];
+        bytes[0..2].copy_from_slice(&2_u16.to_be_bytes());
+        bytes[4..6].copy_from_slice(&20_u16.to_be_bytes());
+        bytes[6..8].copy_from_slice(&3_u16.to_be_bytes());
+        bytes[8..16].copy_from_slice(b"NAME    ");
+        bytes[16..56].copy_from_slice(&record("Student name")[..40]);
+        bytes[56..64].copy_from_slice(b"$CHAR   ");
+        bytes[64..66].copy_from_slice(&20_u16.to_be_bytes());
+        bytes[72..80].copy_from_slice(b"        ");
+        bytes[84..88].copy_from_slice(&16_u32.to_be_bytes());
+
+        let namestr = Namestr::parse(&bytes, XportVersion::V5).unwrap();
+        assert_eq!(namestr.column_type, ColumnType::Character);
+        assert_eq!(namestr.length, 20);
+        assert_eq!(namestr.number, 3);
+        assert_eq!(namestr.name, "NAME");
+        assert_eq!(namestr.label, "Student name");
+        assert_eq!(namestr.format, ColumnFormat::new("$CHAR", 20, 0));
+        assert!(namestr.informat.is_empty());
+        assert_eq!(namestr.position, 16);
+    }
+
+    #[test]
+    fn can_round_trip_namestr() {
+        let mut column = Column::numeric("VISIT");
+        column.label = "Visit number".to_string();
+        column.format = ColumnFormat::new("BEST", 12, 0);
+        let namestr = Namestr::from_column(&column, 2, 24);
+
+        let bytes = namestr.to_bytes(Encoding::Utf8).unwrap();
+        assert_eq!(bytes.len(), NAMESTR_LEN);
+        assert_eq!(Namestr::parse(&bytes, XportVersion::V5), Ok(namestr));
+    }
+
+    #[test]
+    fn can_build_header_record() {
+        let header = header_record("OBS", "");
+        assert_eq!(header.len(), RECORD_LEN);
+        assert_eq!(header_kind(&header), Some("OBS".to_string()));
+    }
+
+    #[test]
+    fn can_format_datetime() {
+        let datetime = parse_datetime(b"16FEB11:10:07:55").unwrap();
+        assert_eq!(format_datetime(&datetime), "16FEB11:10:07:55");
+    }
+
+    #[test]
+    fn can_parse_v8_namestr_name() {
+        let mut bytes = vec![0_u8; NAMESTR_LEN];
+        bytes[0..2].copy_from_slice(&1_u16.to_be_bytes());
+        bytes[4..6].copy_from_slice(&8_u16.to_be_bytes());
+        bytes[8..16].copy_from_slice(b"VISITDAT");
+        bytes[88..120].copy_from_slice(format!("{:<32}", "VISIT_DATE_OF_ENROLLMENT").as_bytes());
+
+        let v8 = Namestr::parse(&bytes, XportVersion::V8).unwrap();
+        assert_eq!(v8.name, "VISIT_DATE_OF_ENROLLMENT");
+        let v5 = Namestr::parse(&bytes, XportVersion::V5).unwrap();
+        assert_eq!(v5.name, "VISITDAT");
+    }
+
+    #[test]
+    fn can_detect_version() {
+        let v5 = record(
+            "HEADER RECORD*******LIBRARY HEADER RECORD!!!!!!!000000000000000000000000000000",
+        );
+        let v8 = record(
+            "HEADER RECORD*******LIBV8   HEADER RECORD!!!!!!!000000000000000000000000000000",
+        );
+        assert_eq!(XportVersion::from_library_header(&v5), Ok(XportVersion::V5));
+        assert_eq!(XportVersion::from_library_header(&v8), Ok(XportVersion::V8));
+        assert!(XportVersion::from_library_header(&record("SAS")).is_err());
+    }
+}
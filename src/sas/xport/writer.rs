@@ -0,0 +1,531 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime};
+use derive_builder::Builder;
+
+use crate::sas::errors::Error;
+use crate::sas::schema::Schema;
+use crate::sas::xport::ibm::encode_numeric;
+use crate::sas::xport::record::{
+    encode_text, format_datetime, header_record, pad_text, Namestr, NAMESTR_LEN, RECORD_LEN,
+};
+use crate::sas::{Column, ColumnType, Encoding, Value};
+
+const MAX_NAME_LEN: usize = 8;
+const MAX_LABEL_LEN: usize = 40;
+const MAX_CHARACTER_LEN: usize = 200;
+const MAX_VARIABLES: usize = 9999;
+
+/// Options for writing a transport file.
+#[derive(Debug, Clone, PartialEq, Builder)]
+#[builder(setter(into), default)]
+pub struct XportWriterOptions {
+    pub sas_version: String,
+    pub os: String,
+    pub created: NaiveDateTime,
+    pub modified: NaiveDateTime,
+    /// How character values are stored. The file does not record it.
+    pub encoding: Encoding,
+}
+
+impl Default for XportWriterOptions {
+    fn default() -> Self {
+        let now = Local::now().naive_local();
+        XportWriterOptions {
+            sas_version: "9.4".to_string(),
+            os: "X64_10PR".to_string(),
+            created: now,
+            modified: now,
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+/// Check a schema against the limits of a version 5 transport file, with
+/// text in `encoding`, listing every violation rather than stopping at the
+/// first.
+pub fn check_limits(schema: &Schema, encoding: Encoding) -> Result<(), Error> {
+    let mut violations = Vec::new();
+
+    if !is_sas_name(&schema.name) {
+        violations.push(format!(
+            "Dataset name {:?} must be 1 to {} letters, digits or underscores, not starting with a digit",
+            schema.name, MAX_NAME_LEN
+        ));
+    }
+    if schema.name.to_uppercase() != schema.name {
+        violations.push(format!("Dataset name {} must be uppercase", schema.name));
+    }
+    match encoding.encode(&schema.label) {
+        Ok(label) if label.len() > MAX_LABEL_LEN => violations.push(format!(
+            "Dataset label is {} bytes as {:?}, longer than {}",
+            label.len(),
+            encoding,
+            MAX_LABEL_LEN
+        )),
+        Ok(_) => {}
+        Err(error) => violations.push(format!("Dataset label: {}", error)),
+    }
+    if schema.columns.is_empty() {
+        violations.push("Dataset has no variables".to_string());
+    }
+    if schema.columns.len() > MAX_VARIABLES {
+        violations.push(format!(
+            "Dataset has {} variables, more than {}",
+            schema.columns.len(),
+            MAX_VARIABLES
+        ));
+    }
+
+    let mut names = HashSet::new();
+    for column in &schema.columns {
+        violations.extend(column_violations(column, encoding));
+        if !names.insert(column.name.to_uppercase()) {
+            violations.push(format!("Variable name {} is used twice", column.name));
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::TransportLimits(violations))
+    }
+}
+
+fn column_violations(column: &Column, encoding: Encoding) -> Vec<String> {
+    let mut violations = Vec::new();
+    let name = &column.name;
+
+    if !is_sas_name(name) {
+        violations.push(format!(
+            "Variable name {:?} must be 1 to {} letters, digits or underscores, not starting with a digit",
+            name, MAX_NAME_LEN
+        ));
+    }
+    if name.to_uppercase() != *name {
+        violations.push(format!("Variable name {} must be uppercase", name));
+    }
+    match encoding.encode(&column.label) {
+        Ok(label) if label.len() > MAX_LABEL_LEN => violations.push(format!(
+            "Variable {} has a label of {} bytes as {:?}, longer than {}",
+            name,
+            label.len(),
+            encoding,
+            MAX_LABEL_LEN
+        )),
+        Ok(_) => {}
+        Err(error) => violations.push(format!("Variable {} label: {}", name, error)),
+    }
+    if column.format.name.len() > MAX_NAME_LEN {
+        violations.push(format!(
+            "Variable {} has format {}, whose name is longer than {} characters",
+            name, column.format.name, MAX_NAME_LEN
+        ));
+    }
+    match column.column_type {
+        ColumnType::Character if !(1..=MAX_CHARACTER_LEN).contains(&column.length) => violations
+            .push(format!(
+                "Character variable {} is {} bytes, outside 1 to {}",
+                name, column.length, MAX_CHARACTER_LEN
+            )),
+        ColumnType::Numeric if !(2..=8).contains(&column.length) => violations.push(format!(
+            "Numeric variable {} is {} bytes, outside 2 to 8",
+            name, column.length
+        )),
+        _ => {}
+    }
+    violations
+}
+
+fn is_sas_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {}
+        _ => return false,
+    }
+    name.len() <= MAX_NAME_LEN && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Writes schemas and rows as a version 5 SAS transport (XPORT) file.
+/// Rows are streamed; each schema becomes one member of the library.
+pub struct XportWriter<W: Write> {
+    sink: W,
+    schema: Schema,
+    options: XportWriterOptions,
+    offsets: Vec<usize>,
+    row_length: usize,
+    written: usize,
+}
+
+impl XportWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        schema: Schema,
+        options: XportWriterOptions,
+    ) -> Result<Self, Error> {
+        let file = File::create(path)?;
+        XportWriter::new(BufWriter::new(file), schema, options)
+    }
+}
+
+impl<W: Write> XportWriter<W> {
+    pub fn new(sink: W, schema: Schema, options: XportWriterOptions) -> Result<Self, Error> {
+        check_limits(&schema, options.encoding)?;
+
+        let mut writer = XportWriter {
+            sink,
+            schema: Schema::new("", Vec::new()),
+            options,
+            offsets: Vec::new(),
+            row_length: 0,
+            written: 0,
+        };
+        writer.write_library_header()?;
+        writer.start_member(schema)?;
+        Ok(writer)
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+        let bytes = self.encode_row(row)?;
+        self.write(&bytes)
+    }
+
+    /// End the current member and start another with a new schema.
+    pub fn next_member(&mut self, schema: Schema) -> Result<(), Error> {
+        check_limits(&schema, self.options.encoding)?;
+        self.pad()?;
+        self.start_member(schema)
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.pad()?;
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    fn write_library_header(&mut self) -> Result<(), Error> {
+        self.write(&header_record("LIBRARY", ""))?;
+        let mut first = b"SAS     SAS     SASLIB  ".to_vec();
+        first.extend(self.host_fields());
+        self.write(&first)?;
+        self.write(&pad_text(
+            &format_datetime(&self.options.modified),
+            RECORD_LEN,
+        ))
+    }
+
+    /// SAS version, operating system and creation time, as written in both
+    /// the library and member headers.
+    fn host_fields(&self) -> Vec<u8> {
+        let mut bytes = pad_text(&self.options.sas_version, 8);
+        bytes.extend(pad_text(&self.options.os, 8));
+        bytes.extend(pad_text("", 24));
+        bytes.extend(pad_text(&format_datetime(&self.options.created), 16));
+        bytes
+    }
+
+    fn start_member(&mut self, schema: Schema) -> Result<(), Error> {
+        self.write(&header_record(
+            "MEMBER",
+            &format!("00000000000000000160000000{:04}", NAMESTR_LEN),
+        ))?;
+        self.write(&header_record("DSCRPTR", ""))?;
+
+        let mut first = b"SAS     ".to_vec();
+        let encoding = self.options.encoding;
+        first.extend(encode_text(&schema.name, 8, encoding)?);
+        first.extend(b"SASDATA ");
+        first.extend(self.host_fields());
+        self.write(&first)?;
+
+        let mut second = pad_text(&format_datetime(&self.options.modified), 32);
+        second.extend(encode_text(&schema.label, MAX_LABEL_LEN, encoding)?);
+        second.extend(pad_text("", 8));
+        self.write(&second)?;
+
+        self.write(&header_record(
+            "NAMESTR",
+            &format!("000000{:04}", schema.columns.len()),
+        ))?;
+        let mut position = 0;
+        self.offsets.clear();
+        for (index, column) in schema.columns.iter().enumerate() {
+            let namestr = Namestr::from_column(column, index as u16 + 1, position);
+            self.write(&namestr.to_bytes(encoding)?)?;
+            self.offsets.push(position);
+            position += column.length;
+        }
+        self.row_length = position;
+        self.pad()?;
+        self.write(&header_record("OBS", ""))?;
+
+        self.schema = schema;
+        Ok(())
+    }
+
+    fn encode_row(&self, row: &[Value]) -> Result<Vec<u8>, Error> {
+        if row.len() != self.schema.columns.len() {
+            return Err(Error::InvalidRow(format!(
+                "Expected {} values but got {}",
+                self.schema.columns.len(),
+                row.len()
+            )));
+        }
+
+        let mut bytes = vec![b' '; self.row_length];
+        for ((column, value), &offset) in self.schema.columns.iter().zip(row).zip(&self.offsets) {
+            let cell = &mut bytes[offset..offset + column.length];
+            match column.column_type {
+                ColumnType::Numeric => encode_numeric(value, cell).map_err(|error| {
+                    Error::InvalidRow(format!("Column {}: {}", column.name, error))
+                })?,
+                ColumnType::Character => encode_string(column, value, self.options.encoding, cell)?,
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.sink.write_all(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    /// Blank-fill up to the next record boundary.
+    fn pad(&mut self) -> Result<(), Error> {
+        let padding = (RECORD_LEN - self.written % RECORD_LEN) % RECORD_LEN;
+        self.write(&vec![b' '; padding])
+    }
+}
+
+fn encode_string(
+    column: &Column,
+    value: &Value,
+    encoding: Encoding,
+    cell: &mut [u8],
+) -> Result<(), Error> {
+    let bytes = match value {
+        Value::String(text) => encoding.encode(text).map_err(Error::InvalidRow)?,
+        Value::Bytes(bytes) => bytes.clone(),
+        Value::Missing(_) => return Ok(()),
+        Value::Number(number) => {
+            return Err(Error::InvalidRow(format!(
+                "Cannot store number {} in character column {}",
+                number, column.name
+            )))
+        }
+    };
+
+    if bytes.len() > column.length {
+        return Err(Error::InvalidRow(format!(
+            "Value {:?} is {} bytes, longer than column {} ({} bytes)",
+            value.to_text().unwrap_or_default(),
+            bytes.len(),
+            column.name,
+            column.length
+        )));
+    }
+    cell[..bytes.len()].copy_from_slice(&bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::sas::xport::XportReader;
+    use crate::sas::{ColumnFormat, MissingValue, Row};
+    use crate::traits::DatasetReader;
+
+    fn options() -> XportWriterOptions {
+        let datetime =
+            NaiveDateTime::parse_from_str("2024-03-05 14:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        XportWriterOptionsBuilder::default()
+            .created(datetime)
+            .modified(datetime)
+            .build()
+            .unwrap()
+    }
+
+    fn schema() -> Schema {
+        let mut name = Column::character("NAME", 10);
+        name.label = "Subject name".to_string();
+        let mut weight = Column::numeric("WEIGHT");
+        weight.format = ColumnFormat::new("", 8, 2);
+        let mut schema = Schema::new("DM", vec![name, weight, Column::numeric("AGE")]);
+        schema.label = "Demographics".to_string();
+        schema
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            vec![
+                Value::String("Ann".to_string()),
+                Value::Number(61.25),
+                Value::Number(34.0),
+            ],
+            vec![
+                Value::String("Bob".to_string()),
+                Value::Missing(MissingValue::System),
+                Value::Missing(MissingValue::Special('A')),
+            ],
+            vec![
+                Value::String(String::new()),
+                Value::Number(-0.1),
+                Value::Number(1e20),
+            ],
+        ]
+    }
+
+    #[test]
+    fn can_round_trip_rows() {
+        let mut writer = XportWriter::new(Cursor::new(Vec::new()), schema(), options()).unwrap();
+        for row in rows() {
+            writer.write_row(&row).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+        assert_eq!(bytes.len() % RECORD_LEN, 0);
+
+        let mut reader = XportReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            reader.library().created.unwrap().to_string(),
+            "2024-03-05 14:30:00"
+        );
+        let schema = reader.schema().clone();
+        assert_eq!(schema.name, "DM");
+        assert_eq!(schema.label, "Demographics");
+        assert_eq!(schema.columns[0].label, "Subject name");
+        assert_eq!(schema.columns[1].format, ColumnFormat::new("", 8, 2));
+
+        let read: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, rows());
+    }
+
+    #[test]
+    fn can_write_several_members() {
+        let mut writer = XportWriter::new(Cursor::new(Vec::new()), schema(), options()).unwrap();
+        writer.write_row(&rows()[0]).unwrap();
+        writer
+            .next_member(Schema::new("AE", vec![Column::character("TERM", 5)]))
+            .unwrap();
+        writer
+            .write_row(&[Value::String("Rash".to_string())])
+            .unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut reader = XportReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.rows().count(), 1);
+        assert!(reader.next_member().unwrap());
+        assert_eq!(reader.schema().name, "AE");
+        let read: Vec<Row> = reader.rows().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, vec![vec![Value::String("Rash".to_string())]]);
+    }
+
+    #[test]
+    fn cannot_write_schema_beyond_limits() {
+        let mut long_label = Column::numeric("DOSE");
+        long_label.label = "x".repeat(41);
+        let mut long_format = Column::numeric("VISIT");
+        long_format.format = ColumnFormat::new("LONGFORMAT", 10, 0);
+        let columns = vec![
+            Column::numeric("TREATMENT_ARM"),
+            Column::numeric("age"),
+            long_label,
+            long_format,
+            Column::character("COMMENT", 201),
+            Column::numeric("DOSE"),
+        ];
+        let schema = Schema::new("ADVERSE_EVENTS", columns);
+
+        let result = XportWriter::new(Cursor::new(Vec::new()), schema, options());
+        let Err(Error::TransportLimits(violations)) = result else {
+            panic!("expected transport limit violations");
+        };
+        assert_eq!(violations.len(), 7, "{:?}", violations);
+        assert!(violations[0].contains("ADVERSE_EVENTS"));
+        assert!(violations[1].contains("TREATMENT_ARM"));
+        assert_eq!(violations[2], "Variable name age must be uppercase");
+        assert!(violations[3].contains("label of 41 bytes"));
+        assert!(violations[4].contains("LONGFORMAT"));
+        assert!(violations[5].contains("COMMENT"));
+        assert_eq!(violations[6], "Variable name DOSE is used twice");
+
+        let schema = Schema::new("dm", vec![Column::numeric("AGE")]);
+        let result = XportWriter::new(Cursor::new(Vec::new()), schema, options());
+        let Err(Error::TransportLimits(violations)) = result else {
+            panic!("expected transport limit violations");
+        };
+        assert_eq!(violations, ["Dataset name dm must be uppercase"]);
+    }
+
+    #[test]
+    fn can_write_labels_in_the_output_encoding() {
+        let mut city = Column::character("CITY", 8);
+        city.label = "é".repeat(MAX_LABEL_LEN);
+        let mut schema = Schema::new("TOWNS", vec![city]);
+        schema.label = "Städte".to_string();
+        let windows = XportWriterOptions {
+            encoding: Encoding::Windows1252,
+            ..options()
+        };
+
+        let writer = XportWriter::new(Cursor::new(Vec::new()), schema.clone(), windows).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+        let reader = XportReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.schema().label, "Städte");
+        assert_eq!(reader.schema().columns[0].label, "é".repeat(MAX_LABEL_LEN));
+
+        let result = XportWriter::new(Cursor::new(Vec::new()), schema.clone(), options());
+        let Err(Error::TransportLimits(violations)) = result else {
+            panic!("expected transport limit violations");
+        };
+        assert_eq!(
+            violations,
+            ["Variable CITY has a label of 80 bytes as Utf8, longer than 40"]
+        );
+
+        schema.label = "€ values".to_string();
+        let latin = XportWriterOptions {
+            encoding: Encoding::Iso8859_1,
+            ..options()
+        };
+        let result = XportWriter::new(Cursor::new(Vec::new()), schema, latin);
+        let Err(Error::TransportLimits(violations)) = result else {
+            panic!("expected transport limit violations");
+        };
+        assert_eq!(
+            violations[0],
+            "Dataset label: Cannot encode \"€ values\" as Iso8859_1"
+        );
+    }
+
+    #[test]
+    fn cannot_write_value_longer_than_column() {
+        let mut writer = XportWriter::new(Cursor::new(Vec::new()), schema(), options()).unwrap();
+        let row = [
+            Value::String("Bartholomew".to_string()),
+            Value::Number(1.0),
+            Value::Number(1.0),
+        ];
+        assert!(matches!(writer.write_row(&row), Err(Error::InvalidRow(_))));
+    }
+
+    #[test]
+    fn cannot_write_number_too_large_for_ibm() {
+        let mut writer = XportWriter::new(Cursor::new(Vec::new()), schema(), options()).unwrap();
+        let row = [
+            Value::String("Ann".to_string()),
+            Value::Number(1e100),
+            Value::Number(1.0),
+        ];
+        assert!(matches!(writer.write_row(&row), Err(Error::InvalidRow(_))));
+    }
+}
@@ -1,6 +1,9 @@
 use std::fs::File;
 use std::io::prelude::*;
 
+use crate::sas::validate::validate_file;
+use crate::sas::Severity;
+
 pub fn read_sas_file(filename: String) -> Result<String, std::io::Error> {
     // let file = File::open(filename)?;
     Ok(filename)
@@ -25,20 +28,24 @@ impl SasReader {
         Ok(contents)
     }
 
+    /// Check the whole file, failing with every error found. Warnings are
+    /// left to `sas::validate::validate_file`.
     pub fn validate_format(&self) -> Result<(), String> {
-        //     let contents = self.get_file_contents();
-        //     // let header = SasHeader::new(&contents.unwrap());
-        //     let header = SasHeader::read(&contents.unwrap());
-        //     match contents {
-        //         Ok(_) => Ok(()),
-        //         Err(e) => Err(format!("Failed to read file contents: {}", e)),
-        //     }
-        Ok(())
+        let issues = validate_file(&self.filename).map_err(|error| error.to_string())?;
+        let errors: Vec<String> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| issue.to_string())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
     }
 }
 
 #[cfg(test)]
-
 mod tests {
     const FILENAME: &str = "test/hadley.sas7bdat";
 
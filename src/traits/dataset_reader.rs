@@ -0,0 +1,82 @@
+#[cfg(feature = "arrow")]
+use crate::export::arrow::RecordBatches;
+#[cfg(feature = "serde")]
+use crate::sas::DeserializeRows;
+use crate::sas::{Error, Row, Schema};
+
+/// Common interface of the dataset readers, so callers do not need to know
+/// which file format they are reading.
+pub trait DatasetReader {
+    fn schema(&self) -> &Schema;
+
+    /// Read the next row, or `None` once every row has been read.
+    fn read_row(&mut self) -> Result<Option<Row>, Error>;
+
+    /// File-level facts to show alongside the schema, such as the creation
+    /// time or encoding, as name and value pairs.
+    fn file_info(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn rows(&mut self) -> Rows<'_, Self>
+    where
+        Self: Sized,
+    {
+        Rows {
+            reader: self,
+            failed: false,
+        }
+    }
+
+    /// Read the remaining rows as Arrow record batches.
+    #[cfg(feature = "arrow")]
+    fn record_batches(&mut self, batch_size: usize) -> RecordBatches<'_, Self>
+    where
+        Self: Sized,
+    {
+        RecordBatches::new(self, batch_size)
+    }
+
+    /// Deserialize the remaining rows, matching struct fields to columns by
+    /// name without regard to case.
+    #[cfg(feature = "serde")]
+    fn deserialize<T: serde::de::DeserializeOwned>(&mut self) -> DeserializeRows<'_, Self, T>
+    where
+        Self: Sized,
+    {
+        DeserializeRows::new(self)
+    }
+}
+
+impl<D: DatasetReader + ?Sized> DatasetReader for Box<D> {
+    fn schema(&self) -> &Schema {
+        (**self).schema()
+    }
+
+    fn read_row(&mut self) -> Result<Option<Row>, Error> {
+        (**self).read_row()
+    }
+
+    fn file_info(&self) -> Vec<(String, String)> {
+        (**self).file_info()
+    }
+}
+
+/// Iterator over the remaining rows of a reader. Stops after the first error.
+pub struct Rows<'a, D: DatasetReader> {
+    reader: &'a mut D,
+    failed: bool,
+}
+
+impl<D: DatasetReader> Iterator for Rows<'_, D> {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let row = self.reader.read_row().transpose();
+        self.failed = matches!(row, Some(Err(_)));
+        row
+    }
+}
@@ -1,3 +1,5 @@
+pub mod dataset_reader;
 pub mod read_seek;
 
+pub use dataset_reader::{DatasetReader, Rows};
 pub use read_seek::ReadSeek;
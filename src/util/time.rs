@@ -1,5 +1,34 @@
 use chrono::{Duration, NaiveDate, NaiveDateTime};
 
+/// Days from the SAS epoch (1960-01-01) to the Unix epoch.
+pub const SAS_TO_UNIX_DAYS: i64 = 3653;
+
+/// Seconds from the SAS epoch to the Unix epoch.
+pub const SAS_TO_UNIX_SECONDS: i64 = SAS_TO_UNIX_DAYS * 86_400;
+
+/// SAS dates count days from 1960, Unix dates from 1970. Gives `None` for
+/// dates outside the range of 32-bit days.
+pub fn unix_days(days: f64) -> Option<i32> {
+    if !days.is_finite() {
+        return None;
+    }
+    // Casting saturates, which only happens far outside the i32 range
+    let days = (days.floor() as i64).checked_sub(SAS_TO_UNIX_DAYS)?;
+    i32::try_from(days).ok()
+}
+
+/// Seconds as whole microseconds, or `None` when they do not fit in an i64.
+pub fn microseconds(seconds: f64) -> Option<i64> {
+    let micros = (seconds * 1e6).round();
+    (i64::MIN as f64..i64::MAX as f64)
+        .contains(&micros)
+        .then_some(micros as i64)
+}
+
+pub fn unix_microseconds(seconds: f64) -> Option<i64> {
+    microseconds(seconds - SAS_TO_UNIX_SECONDS as f64)
+}
+
 pub fn get_sas_epoch() -> NaiveDateTime {
     NaiveDate::from_ymd_opt(1960, 1, 1)
         .unwrap()
@@ -7,10 +36,13 @@ pub fn get_sas_epoch() -> NaiveDateTime {
         .unwrap()
 }
 
-pub fn sas_timestamp_to_datetime(timestamp: f64) -> NaiveDateTime {
-    let sas_epoch = get_sas_epoch();
-    let seconds = Duration::seconds(timestamp as i64);
-    sas_epoch + seconds
+/// `None` for timestamps that are not finite or fall outside chrono's range.
+pub fn sas_timestamp_to_datetime(timestamp: f64) -> Option<NaiveDateTime> {
+    if !timestamp.is_finite() {
+        return None;
+    }
+    Duration::try_seconds(timestamp as i64)
+        .and_then(|seconds| get_sas_epoch().checked_add_signed(seconds))
 }
 
 pub fn datetime_to_sas_timestamp(datetime: NaiveDateTime) -> f64 {
@@ -20,13 +52,42 @@ pub fn datetime_to_sas_timestamp(datetime: NaiveDateTime) -> f64 {
 }
 
 #[cfg(test)]
-
 mod tests {
 
     use super::*;
     use assert_approx_eq::assert_approx_eq;
     use chrono::Timelike;
 
+    #[test]
+    fn can_offset_sas_epoch_to_unix_epoch() {
+        let unix_epoch = NaiveDate::from_ymd_opt(1970, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(
+            datetime_to_sas_timestamp(unix_epoch),
+            SAS_TO_UNIX_SECONDS as f64
+        );
+    }
+
+    #[test]
+    fn can_convert_to_unix_units() {
+        assert_eq!(unix_days(3653.5), Some(0));
+        assert_eq!(unix_days(-0.5), Some(-3654));
+        assert_eq!(microseconds(1.5), Some(1_500_000));
+        assert_eq!(unix_microseconds(SAS_TO_UNIX_SECONDS as f64), Some(0));
+    }
+
+    #[test]
+    fn cannot_convert_out_of_range_values_to_unix_units() {
+        assert_eq!(unix_days(1e12), None);
+        assert_eq!(unix_days(-1e12), None);
+        assert_eq!(unix_days(f64::NAN), None);
+        assert_eq!(microseconds(1e14), None);
+        assert_eq!(microseconds(-1e14), None);
+        assert_eq!(unix_microseconds(f64::INFINITY), None);
+    }
+
     #[test]
     fn can_get_sas_epoch() {
         let sas_epoch = get_sas_epoch();
@@ -44,7 +105,7 @@ mod tests {
     #[test]
     fn can_convert_sas_timestamp_to_datetime() {
         let timestamp = 0.0;
-        let datetime = sas_timestamp_to_datetime(timestamp);
+        let datetime = sas_timestamp_to_datetime(timestamp).unwrap();
         assert_approx_eq!(
             datetime.and_utc().timestamp_subsec_nanos() as f64,
             NaiveDate::from_ymd_opt(1960, 1, 1)
@@ -56,6 +117,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cannot_convert_out_of_range_timestamps() {
+        for timestamp in [f64::NAN, f64::INFINITY, -1e300, 1e18] {
+            assert_eq!(sas_timestamp_to_datetime(timestamp), None);
+        }
+    }
+
     #[test]
     fn can_convert_datetime_to_sas_timestamp() {
         let datetime = NaiveDate::from_ymd_opt(1960, 1, 1)
@@ -69,7 +137,7 @@ mod tests {
     #[test]
     fn can_convert_sas_timestamp_to_datetime_with_time() {
         let one_nano_after_epoch = 0.000000001;
-        let datetime = sas_timestamp_to_datetime(one_nano_after_epoch);
+        let datetime = sas_timestamp_to_datetime(one_nano_after_epoch).unwrap();
 
         let expected = NaiveDate::from_ymd_opt(1960, 1, 1)
             .unwrap()